@@ -1,6 +1,6 @@
 use std::collections::VecDeque;
 
-use libtetris::Controller;
+use libtetris::{Board, ColoredRow, Controller, FallingPiece, LockResult};
 use rand::prelude::*;
 use rand_pcg::Pcg64Mcg;
 use serde::{Deserialize, Serialize};
@@ -39,6 +39,7 @@ impl Battle {
                 p1_seed,
                 p2_seed,
                 garbage_seed,
+                garbage_pattern: vec![],
                 updates: VecDeque::new(),
             },
             player_1,
@@ -50,6 +51,18 @@ impl Battle {
         }
     }
 
+    /// Makes the holes of garbage lines sent to either player follow `columns` in order instead of
+    /// being random. Returns false without changing anything if a column is out of range. See
+    /// `Game::set_garbage_pattern`.
+    pub fn set_garbage_pattern(&mut self, columns: Vec<usize>) -> bool {
+        if !self.player_1.set_garbage_pattern(&columns) {
+            return false;
+        }
+        self.player_2.set_garbage_pattern(&columns);
+        self.replay.garbage_pattern = columns;
+        true
+    }
+
     pub fn update(&mut self, p1: Controller, p2: Controller) -> BattleUpdate {
         self.time += 1;
 
@@ -109,5 +122,231 @@ pub struct Replay {
     pub garbage_seed: <Pcg64Mcg as SeedableRng>::Seed,
     pub p1_config: GameConfig,
     pub p2_config: GameConfig,
+    /// The garbage hole columns set with `Battle::set_garbage_pattern`, if any.
+    #[serde(default)]
+    pub garbage_pattern: Vec<usize>,
     pub updates: VecDeque<(Controller, Controller)>,
 }
+
+type Placement = (FallingPiece, Board<ColoredRow>, LockResult);
+
+/// How a player topped out, as found by [`Replay::death_info`].
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct DeathInfo {
+    /// 1 or 2, depending on which player topped out.
+    pub player: u32,
+    /// The index of the update during which the player topped out.
+    pub frame: u32,
+    /// The amount of garbage that was still waiting to be added when the player topped out.
+    pub pending_garbage: u32,
+    /// The height of the tallest column of the player's board when they topped out.
+    pub final_height: i32,
+}
+
+impl Replay {
+    /// Re-simulates the replay and yields each piece player 1 placed along with the board just
+    /// before it was placed and the result of placing it.
+    pub fn p1_placements(&self) -> impl Iterator<Item = Placement> {
+        self.placements().0.into_iter()
+    }
+
+    /// Re-simulates the replay and yields each piece player 2 placed along with the board just
+    /// before it was placed and the result of placing it.
+    pub fn p2_placements(&self) -> impl Iterator<Item = Placement> {
+        self.placements().1.into_iter()
+    }
+
+    /// Re-simulates the replay and finds the first player to top out, if either did.
+    pub fn death_info(&self) -> Option<DeathInfo> {
+        let mut battle = self.battle();
+        for (frame, &(p1, p2)) in self.updates.iter().enumerate() {
+            let update = battle.update(p1, p2);
+            let players = [
+                (1, &update.player_1, &battle.player_1),
+                (2, &update.player_2, &battle.player_2),
+            ];
+            for &(player, update, game) in &players {
+                if update.events.iter().any(|e| matches!(e, Event::GameOver)) {
+                    return Some(DeathInfo {
+                        player,
+                        frame: frame as u32,
+                        pending_garbage: update.garbage_queue,
+                        final_height: *game.board.column_heights().iter().max().unwrap(),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Creates a battle in the state this replay starts from.
+    pub fn battle(&self) -> Battle {
+        let mut battle = Battle::new(
+            self.p1_config,
+            self.p2_config,
+            self.p1_seed,
+            self.p2_seed,
+            self.garbage_seed,
+        );
+        if !self.garbage_pattern.is_empty() {
+            battle.set_garbage_pattern(self.garbage_pattern.clone());
+        }
+        battle
+    }
+
+    fn placements(&self) -> (Vec<Placement>, Vec<Placement>) {
+        let mut battle = self.battle();
+        let mut p1_placements = vec![];
+        let mut p2_placements = vec![];
+        for &(p1, p2) in &self.updates {
+            let p1_board = battle.player_1.board.clone();
+            let p2_board = battle.player_2.board.clone();
+            let update = battle.update(p1, p2);
+            for event in update.player_1.events {
+                if let Event::PiecePlaced { piece, locked, .. } = event {
+                    p1_placements.push((piece, p1_board.clone(), locked));
+                }
+            }
+            for event in update.player_2.events {
+                if let Event::PiecePlaced { piece, locked, .. } = event {
+                    p2_placements.push((piece, p2_board.clone(), locked));
+                }
+            }
+        }
+        (p1_placements, p2_placements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libtetris::*;
+
+    use super::*;
+    use crate::PieceMoveExecutor;
+
+    /// Picks the placement that clears the most lines, then leaves the fewest holes, then keeps
+    /// the stack lowest.
+    fn greedy_placement(board: &Board<ColoredRow>, spawned: FallingPiece) -> libtetris::Placement {
+        let mut board_u16 = Board::<u16>::new();
+        board_u16.set_field(board.get_field());
+        find_moves(
+            &board_u16,
+            spawned,
+            MovementMode::ZeroG,
+            false,
+            KickTable::Srs,
+            SpinDetectionMode::default(),
+        )
+        .into_iter()
+        .max_by_key(|p| {
+            let mut result = board_u16.clone();
+            let lines = result.lock_piece(p.location).cleared_lines.len();
+            let heights = result.column_heights();
+            let holes = (0..10)
+                .flat_map(|x| (0..heights[x]).map(move |y| (x as i32, y)))
+                .filter(|&(x, y)| !result.occupied(x, y))
+                .count();
+            (lines, -(holes as i32), -heights.iter().sum::<i32>())
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn replay_placements_match_pieces_placed() {
+        let config = GameConfig::fast_config();
+        let mut battle = Battle::new(config, config, [1; 16], [2; 16], [3; 16]);
+        let mut placed = 0;
+        for t in 0..40 {
+            let p1 = Controller {
+                hard_drop: t % 4 == 3,
+                ..Default::default()
+            };
+            let update = battle.update(p1, Controller::default());
+            placed += update
+                .player_1
+                .events
+                .iter()
+                .filter(|e| matches!(e, Event::PiecePlaced { .. }))
+                .count();
+        }
+        assert!(placed > 0);
+
+        let placements: Vec<_> = battle.replay.p1_placements().collect();
+        assert_eq!(placements.len(), placed);
+        assert_eq!(battle.replay.p2_placements().count(), 0);
+        // each placement comes with the board from before it was placed
+        for (i, (piece, board, _)) in placements.iter().enumerate() {
+            assert!(!board.obstructed(piece));
+            let cells = (0..40)
+                .flat_map(|y| (0..10).map(move |x| (x, y)))
+                .filter(|&(x, y)| board.occupied(x, y))
+                .count();
+            assert_eq!(cells, 4 * i);
+        }
+    }
+
+    #[test]
+    fn death_info_reports_garbage_pending_at_top_out() {
+        // with practically no gravity, pieces only lock when hard dropped
+        let config = GameConfig {
+            gravity: 1_000_000_000,
+            ..GameConfig::fast_config()
+        };
+        let p1_config = GameConfig {
+            max_garbage_add: 1,
+            ..config
+        };
+        let mut battle = Battle::new(p1_config, config, [1; 16], [2; 16], [3; 16]);
+
+        let mut p1 = Controller::default();
+        let mut p2 = Controller::default();
+        let mut p2_executor: Option<PieceMoveExecutor> = None;
+        let mut death_frame = None;
+        for frame in 0..20_000 {
+            let update = battle.update(p1, p2);
+            if update
+                .player_1
+                .events
+                .iter()
+                .any(|e| matches!(e, Event::GameOver))
+            {
+                death_frame = Some(frame);
+                break;
+            }
+
+            // player 1 builds a tall stack, then waits for garbage before dropping more pieces
+            let height = *battle.player_1.board.column_heights().iter().max().unwrap();
+            let drop = height < 17 || battle.player_1.garbage_queue >= 2;
+            p1.hard_drop = drop && !p1.hard_drop;
+
+            // player 2 clears lines to send garbage
+            for event in &update.player_2.events {
+                if let &Event::PieceFalling(piece, _) = event {
+                    if p2_executor.is_none() {
+                        let placement = greedy_placement(&battle.player_2.board, piece);
+                        p2_executor = Some(PieceMoveExecutor::new(
+                            false,
+                            placement.inputs.movements.into_iter().collect(),
+                            0,
+                        ));
+                    }
+                }
+            }
+            if let Some(executor) = &mut p2_executor {
+                let board = &battle.player_2.board;
+                if executor
+                    .update(&mut p2, board, &update.player_2.events)
+                    .is_some()
+                {
+                    p2_executor = None;
+                }
+            }
+        }
+
+        let death = battle.replay.death_info().unwrap();
+        assert_eq!(death.player, 1);
+        assert_eq!(Some(death.frame), death_frame);
+        assert!(death.pending_garbage > 0);
+        assert!(death.final_height >= 17);
+    }
+}
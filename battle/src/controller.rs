@@ -51,6 +51,7 @@ impl PieceMoveExecutor {
                                     self.controller.rotate_left = false;
                                     self.controller.rotate_right = false;
                                     self.controller.left = false;
+                                    self.controller.rotate_180 = false;
 
                                     self.controller.soft_drop = true;
                                     if board.on_stack(piece) {
@@ -63,6 +64,7 @@ impl PieceMoveExecutor {
                                     self.controller.rotate_left = false;
                                     self.controller.rotate_right = false;
                                     self.controller.soft_drop = false;
+                                    self.controller.rotate_180 = false;
 
                                     self.controller.left ^= true;
                                     if self.controller.left {
@@ -74,6 +76,7 @@ impl PieceMoveExecutor {
                                     self.controller.rotate_left = false;
                                     self.controller.rotate_right = false;
                                     self.controller.soft_drop = false;
+                                    self.controller.rotate_180 = false;
 
                                     self.controller.right ^= true;
                                     if self.controller.right {
@@ -85,17 +88,31 @@ impl PieceMoveExecutor {
                                     self.controller.rotate_left = false;
                                     self.controller.left = false;
                                     self.controller.soft_drop = false;
+                                    self.controller.rotate_180 = false;
 
                                     self.controller.rotate_right ^= true;
                                     if self.controller.rotate_right {
                                         self.executing.pop_front();
                                     }
                                 }
+                                Some(PieceMovement::Flip) => {
+                                    self.controller.left = false;
+                                    self.controller.right = false;
+                                    self.controller.rotate_left = false;
+                                    self.controller.rotate_right = false;
+                                    self.controller.soft_drop = false;
+
+                                    self.controller.rotate_180 ^= true;
+                                    if self.controller.rotate_180 {
+                                        self.executing.pop_front();
+                                    }
+                                }
                                 Some(PieceMovement::Ccw) => {
                                     self.controller.left = false;
                                     self.controller.right = false;
                                     self.controller.rotate_right = false;
                                     self.controller.soft_drop = false;
+                                    self.controller.rotate_180 = false;
 
                                     self.controller.rotate_left ^= true;
                                     if self.controller.rotate_left {
@@ -16,6 +16,8 @@ pub struct Game {
     going_right: bool,
     pub garbage_queue: u32,
     pub attacking: u32,
+    garbage_pattern: Vec<usize>,
+    garbage_lines: usize,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -62,10 +64,18 @@ struct FallingState {
 
 impl Game {
     pub fn new(config: GameConfig, piece_rng: &mut impl Rng) -> Self {
-        let mut board = Board::new();
+        let mut board = Board::with_starting_garbage(config.starting_garbage as usize, piece_rng);
         for _ in 0..config.next_queue_size {
             board.add_next_piece(board.generate_next_piece(piece_rng));
         }
+        Game::with_board(board, config)
+    }
+
+    /// Creates a game starting from the specified board.
+    ///
+    /// The next queue of the board is used as-is, so it should usually contain
+    /// `config.next_queue_size` pieces.
+    pub fn with_board(board: Board<ColoredRow>, config: GameConfig) -> Self {
         Game {
             board,
             config,
@@ -78,7 +88,32 @@ impl Game {
             state: GameState::SpawnDelay(config.spawn_delay),
             garbage_queue: 0,
             attacking: 0,
+            garbage_pattern: vec![],
+            garbage_lines: 0,
+        }
+    }
+
+    /// Makes the holes of the garbage lines added to this player's board follow `columns` in
+    /// order, starting over when the end is reached, instead of being random.
+    ///
+    /// An empty pattern goes back to random holes. Returns false without changing anything if a
+    /// column is out of range.
+    pub fn set_garbage_pattern(&mut self, columns: &[usize]) -> bool {
+        if columns.iter().any(|&c| c >= 10) {
+            return false;
         }
+        self.garbage_pattern = columns.to_vec();
+        self.garbage_lines = 0;
+        true
+    }
+
+    /// The amount of garbage this player will send once the pending attack and the garbage queue
+    /// cancel each other out. Negative values are the amount of garbage that will be received.
+    ///
+    /// With garbage blocking, attack from consecutive line clears builds up until a piece is
+    /// placed without clearing lines, so this can differ a lot from the garbage queue alone.
+    pub fn net_garbage_balance(&self) -> i32 {
+        self.attacking as i32 - self.garbage_queue as i32
     }
 
     pub fn update(
@@ -99,6 +134,11 @@ impl Game {
             self.prev.rotate_left,
             current.rotate_left,
         );
+        update_input(
+            &mut self.used.rotate_180,
+            self.prev.rotate_180,
+            current.rotate_180,
+        );
         update_input(
             &mut self.used.soft_drop,
             self.prev.soft_drop,
@@ -271,6 +311,18 @@ impl Game {
                         }
                     }
                 }
+                if self.used.rotate_180 {
+                    if falling.piece.flip(&self.board) {
+                        self.used.rotate_180 = false;
+                        falling.rotation_move_count += 1;
+                        falling.lock_delay = self.config.lock_delay;
+                        if falling.piece.tspin != TspinStatus::None {
+                            events.push(Event::PieceTSpined);
+                        } else {
+                            events.push(Event::PieceRotated);
+                        }
+                    }
+                }
 
                 // Shift
                 while self.used.left && falling.piece.shift(&self.board, -1, 0) {
@@ -414,7 +466,10 @@ impl Game {
             let mut col = rng.gen_range(0, 10);
             let mut garbage_columns = vec![];
             for _ in 0..self.garbage_queue.min(self.config.max_garbage_add) {
-                if rng.gen_bool(self.config.garbage_messiness.into_inner()) {
+                if !self.garbage_pattern.is_empty() {
+                    col = self.garbage_pattern[self.garbage_lines % self.garbage_pattern.len()];
+                    self.garbage_lines += 1;
+                } else if rng.gen_bool(self.config.garbage_messiness.into_inner()) {
                     col = rng.gen_range(0, 10);
                 }
                 garbage_columns.push(col);
@@ -440,3 +495,169 @@ fn update_input(used: &mut bool, prev: bool, current: bool) {
         *used = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand_pcg::Pcg64Mcg;
+
+    use super::*;
+
+    /// Moves the falling piece to `location`, then hard drops it and runs the game until the next
+    /// piece spawns.
+    fn place(
+        game: &mut Game,
+        location: impl FnOnce(&mut FallingPiece, &Board<ColoredRow>),
+        rng: &mut Pcg64Mcg,
+    ) -> Vec<Event> {
+        let mut events = vec![];
+        while !matches!(game.state, GameState::Falling(_)) {
+            events.extend(game.update(Controller::default(), &mut rng.clone(), rng));
+        }
+        if let GameState::Falling(falling) = &mut game.state {
+            location(&mut falling.piece, &game.board);
+        }
+        let drop = Controller {
+            hard_drop: true,
+            ..Default::default()
+        };
+        events.extend(game.update(drop, &mut rng.clone(), rng));
+        while !matches!(game.state, GameState::Falling(_) | GameState::GameOver) {
+            events.extend(game.update(Controller::default(), &mut rng.clone(), rng));
+        }
+        events
+    }
+
+    #[test]
+    fn blocked_garbage_is_cancelled_by_attack() {
+        let mut board = Board::<ColoredRow>::new();
+        let mut field = [[false; 10]; 40];
+        for row in &mut field[..4] {
+            for cell in &mut row[..9] {
+                *cell = true;
+            }
+        }
+        // left over after the tetris so that it isn't a perfect clear
+        field[4][0] = true;
+        board.set_field(field);
+        board.add_next_piece(Piece::I);
+        board.add_next_piece(Piece::O);
+        let config = GameConfig {
+            garbage_blocking: true,
+            ..GameConfig::fast_config()
+        };
+        let mut game = Game::with_board(board, config);
+        game.garbage_queue = 6;
+        let mut rng = Pcg64Mcg::seed_from_u64(0);
+
+        place(
+            &mut game,
+            |piece, board| {
+                piece.cw(board);
+                while piece.shift(board, 1, 0) {}
+            },
+            &mut rng,
+        );
+        assert_eq!(game.net_garbage_balance(), -2);
+
+        // the tetris cancels 4 of the 6 incoming lines once a piece is placed without clearing
+        let events = place(&mut game, |_, _| {}, &mut rng);
+        let added: usize = events
+            .iter()
+            .map(|e| match e {
+                Event::GarbageAdded(columns) => columns.len(),
+                _ => 0,
+            })
+            .sum();
+        assert_eq!(added, 2);
+        assert_eq!(game.net_garbage_balance(), 0);
+    }
+
+    #[test]
+    fn garbage_holes_follow_pattern() {
+        let mut board = Board::<ColoredRow>::new();
+        board.add_next_piece(Piece::O);
+        let mut game = Game::with_board(board, GameConfig::fast_config());
+        assert!(!game.set_garbage_pattern(&[3, 10]));
+        assert!(game.set_garbage_pattern(&[3, 7, 0]));
+        game.garbage_queue = 5;
+        let mut rng = Pcg64Mcg::seed_from_u64(0);
+
+        let events = place(&mut game, |_, _| {}, &mut rng);
+        let added: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                Event::GarbageAdded(columns) => Some(columns.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(added, vec![vec![3, 7, 0, 3, 7]]);
+        // each line is pushed in from below, so the first one ends up on top
+        for (y, &col) in [7, 3, 0, 7, 3].iter().enumerate() {
+            let row = game.board.get_row(y as i32);
+            let holes: Vec<_> = (0..10).filter(|&x| !row.get(x)).collect();
+            assert_eq!(holes, vec![col], "row {}", y);
+        }
+    }
+
+    #[test]
+    fn flip_kick_reaches_tspin_slot() {
+        let mut board = Board::<ColoredRow>::new();
+        let mut field = [[false; 10]; 40];
+        field[0] = [true; 10];
+        field[0][9] = false;
+        field[1] = [true; 10];
+        for cell in &mut field[1][2..5] {
+            *cell = false;
+        }
+        for cell in &mut field[2][..3] {
+            *cell = true;
+        }
+        // the overhang blocks the unkicked 180 and keeps the slot closed from above
+        for cell in &mut field[3][..5] {
+            *cell = true;
+        }
+        board.set_field(field);
+        board.add_next_piece(Piece::T);
+        board.add_next_piece(Piece::O);
+        let mut game = Game::with_board(board, GameConfig::fast_config());
+        let mut rng = Pcg64Mcg::seed_from_u64(0);
+
+        while !matches!(game.state, GameState::Falling(_)) {
+            game.update(Controller::default(), &mut rng.clone(), &mut rng);
+        }
+        let start = FallingPiece {
+            kind: PieceState(Piece::T, RotationState::South),
+            x: 4,
+            y: 2,
+            tspin: TspinStatus::None,
+        };
+        // two clockwise rotations don't get into the slot
+        let mut cw = start;
+        cw.cw(&game.board);
+        cw.cw(&game.board);
+        assert!(!(cw.kind.1 == RotationState::North && cw.x == 3 && cw.y == 1));
+        if let GameState::Falling(falling) = &mut game.state {
+            falling.piece = start;
+        }
+
+        let flip = Controller {
+            rotate_180: true,
+            ..Default::default()
+        };
+        let events = game.update(flip, &mut rng.clone(), &mut rng);
+        assert!(events.iter().any(|e| matches!(e, Event::PieceTSpined)));
+        let drop = Controller {
+            hard_drop: true,
+            ..Default::default()
+        };
+        let events = game.update(drop, &mut rng.clone(), &mut rng);
+        let placed = events.iter().find_map(|e| match e {
+            Event::PiecePlaced { piece, locked, .. } => Some((piece, locked)),
+            _ => None,
+        });
+        let (piece, locked) = placed.unwrap();
+        assert_eq!(piece.kind, PieceState(Piece::T, RotationState::North));
+        assert_eq!((piece.x, piece.y), (3, 1));
+        assert_eq!(locked.placement_kind, PlacementKind::MiniTspin1);
+    }
+}
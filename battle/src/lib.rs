@@ -2,11 +2,13 @@ use ordered_float::NotNan;
 use serde::{Deserialize, Serialize};
 
 mod battle;
-pub use battle::{Battle, BattleUpdate, PlayerUpdate, Replay};
+pub use battle::{Battle, BattleUpdate, DeathInfo, PlayerUpdate, Replay};
 mod controller;
 pub use controller::PieceMoveExecutor;
 mod game;
 pub use game::{Event, Game};
+mod simulate;
+pub use simulate::simulate_inputs;
 
 /// Units are in ticks
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
@@ -26,6 +28,7 @@ pub struct GameConfig {
     pub move_lock_rule: u32,
     pub garbage_blocking: bool,
     pub garbage_messiness: NotNan<f64>,
+    pub starting_garbage: u32,
 }
 
 impl Default for GameConfig {
@@ -44,6 +47,7 @@ impl Default for GameConfig {
             move_lock_rule: 15,
             garbage_blocking: false,
             garbage_messiness: NotNan::new(0.3).unwrap(),
+            starting_garbage: 0,
         }
     }
 }
@@ -63,6 +67,7 @@ impl GameConfig {
             move_lock_rule: 15,
             garbage_blocking: true,
             garbage_messiness: NotNan::new(0.0).unwrap(),
+            starting_garbage: 0,
         }
     }
 }
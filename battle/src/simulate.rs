@@ -0,0 +1,64 @@
+use libtetris::{Board, ColoredRow, Controller};
+use rand::prelude::*;
+use rand_pcg::Pcg64Mcg;
+
+use crate::{Event, Game, GameConfig};
+
+/// Runs a game starting from the specified board, with one tick per controller state.
+///
+/// The game is stepped deterministically: new pieces and garbage are generated from fixed seeds,
+/// so the same inputs on the same board always produce the same result. Returns the board after
+/// the last tick and every event that occurred.
+pub fn simulate_inputs(
+    board: Board<ColoredRow>,
+    inputs: &[Controller],
+    config: GameConfig,
+) -> (Board<ColoredRow>, Vec<Event>) {
+    let mut piece_rng = Pcg64Mcg::seed_from_u64(0);
+    let mut garbage_rng = Pcg64Mcg::seed_from_u64(1);
+    let mut game = Game::with_board(board, config);
+    let mut events = vec![];
+    for &input in inputs {
+        events.extend(game.update(input, &mut piece_rng, &mut garbage_rng));
+    }
+    (game.board, events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hard_drop_once() -> Vec<Controller> {
+        let mut inputs = vec![Controller::default(); 10];
+        inputs.push(Controller {
+            hard_drop: true,
+            ..Default::default()
+        });
+        inputs.extend(vec![Controller::default(); 5]);
+        inputs
+    }
+
+    #[test]
+    fn hard_drop_places_one_piece() {
+        let (board, events) =
+            simulate_inputs(Board::new(), &hard_drop_once(), GameConfig::fast_config());
+        let placed = events
+            .iter()
+            .filter(|e| matches!(e, Event::PiecePlaced { .. }))
+            .count();
+        assert_eq!(placed, 1);
+        let cells = (0..40)
+            .flat_map(|y| (0..10).map(move |x| (x, y)))
+            .filter(|&(x, y)| board.occupied(x, y))
+            .count();
+        assert_eq!(cells, 4);
+    }
+
+    #[test]
+    fn same_inputs_give_same_result() {
+        let inputs = hard_drop_once();
+        let first = simulate_inputs(Board::new(), &inputs, GameConfig::fast_config());
+        let second = simulate_inputs(Board::new(), &inputs, GameConfig::fast_config());
+        assert_eq!(format!("{:?}", first), format!("{:?}", second));
+    }
+}
@@ -98,7 +98,10 @@ pub struct DagState<E: 'static, R: 'static> {
     generations: VecDeque<Generation<E, R>>,
     root: u32,
     gens_passed: u32,
+    pruned_nodes: u32,
     use_hold: bool,
+    exploration: f32,
+    rng: StdRng,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -123,6 +126,12 @@ pub struct MoveCandidate<E> {
     pub evaluation: E,
     pub hold: bool,
     pub original_rank: u32,
+    /// The placements that have been found for the piece after this one, best first. Empty if
+    /// the next piece isn't known or this move hasn't been expanded yet.
+    pub followups: Vec<FallingPiece>,
+    /// The number of placements in the best line after this move that depend on pieces which
+    /// aren't known yet.
+    pub speculated_depth: u32,
 }
 
 #[self_referencing]
@@ -160,6 +169,55 @@ struct Child<R> {
     node: u32,
 }
 
+/// A copy of a `DagState` with the arena-allocated parts flattened out so that it can be
+/// serialized. Loading it rebuilds the arenas.
+#[derive(Serialize, Deserialize)]
+pub struct SavedDag<E, R> {
+    board: Board,
+    root: u32,
+    use_hold: bool,
+    exploration: f32,
+    generations: Vec<SavedGeneration<E, R>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedGeneration<E, R> {
+    nodes: Vec<SavedNode<E>>,
+    children: SavedChildren<R>,
+    deduplicator: Vec<(SavedBoard, u32)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedNode<E> {
+    parents: Vec<u32>,
+    evaluation: E,
+    death: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedChildren<R> {
+    Known(Piece, Vec<Option<Vec<SavedChild<R>>>>),
+    Speculated(Vec<Option<EnumMap<Piece, Option<Vec<SavedChild<R>>>>>>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedChild<R> {
+    placement: FallingPiece,
+    reward: R,
+    original_rank: u32,
+    node: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedBoard {
+    grid: Vec<u16>,
+    combo: u32,
+    bag: EnumSet<Piece>,
+    reserve: Piece,
+    back_to_back: bool,
+    reserve_is_hold: bool,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 struct SimplifiedBoard<'c> {
     grid: &'c [u16],
@@ -171,13 +229,19 @@ struct SimplifiedBoard<'c> {
 }
 
 impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
-    pub fn new(board: Board, use_hold: bool) -> Self {
+    pub fn new(board: Board, use_hold: bool, exploration: f32, seed: Option<u64>) -> Self {
         let mut this = DagState {
             board,
             generations: VecDeque::new(),
             root: 0,
             gens_passed: 0,
+            pruned_nodes: 0,
             use_hold,
+            exploration: valid_exploration(exploration),
+            rng: match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
         };
         this.init_generations();
         this
@@ -223,7 +287,7 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
             // Attempt to search forced lines first
             let mut path = &*forced_analysis_lines[i];
             let mut done = false;
-            let choice = self.find_and_mark_leaf_with_chooser(|_, children| {
+            let choice = self.find_and_mark_leaf_with_chooser(|_, _, children| {
                 if let &[next, ref rest @ ..] = path {
                     for child in children {
                         if next.same_location(&child.placement) {
@@ -243,14 +307,16 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                 None
             });
             if done {
-                forced_analysis_lines.swap_remove(i);
+                // keep the remaining lines in the order they were added
+                forced_analysis_lines.remove(i);
             }
             if choice.is_some() {
                 return choice;
             }
         }
 
-        self.find_and_mark_leaf_with_chooser(|next_gen_nodes, children| {
+        let exploration = self.exploration;
+        self.find_and_mark_leaf_with_chooser(|rng, next_gen_nodes, children| {
             // Since children is sorted best-to-worst, the minimum evaluation will be the last item
             // in the iterator. filter_map allows us to ignore death nodes.
             let evaluation = &child_eval_fn(next_gen_nodes);
@@ -258,16 +324,32 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
             let weights = children
                 .iter()
                 .enumerate()
-                .map(|(i, c)| evaluation(c).map_or(0, |e| e.weight(&min_eval, i)));
+                .map(|(i, c)| evaluation(c).map_or(0, |e| e.weight(&min_eval, i, exploration)));
             // Choose a node randomly (the Monte-Carlo part)
             let sampler = rand::distributions::WeightedIndex::new(weights).ok()?;
-            Some(&children[thread_rng().sample(sampler)])
+            Some(&children[rng.sample(sampler)])
         })
     }
 
     fn find_and_mark_leaf_with_chooser(
         &mut self,
-        mut chooser: impl for<'a> FnMut(&[Node<E>], &'a [Child<R>]) -> Option<&'a Child<R>>,
+        mut chooser: impl for<'a> FnMut(&mut StdRng, &[Node<E>], &'a [Child<R>]) -> Option<&'a Child<R>>,
+    ) -> Option<(NodeId, Board)> {
+        // the generations are borrowed while choosing, so the rng is moved out for the duration
+        let mut rng = std::mem::replace(&mut self.rng, StdRng::from_seed([0; 32]));
+        let result = self.find_leaf_with_rng(&mut rng, &mut chooser);
+        self.rng = rng;
+        result
+    }
+
+    fn find_leaf_with_rng(
+        &mut self,
+        rng: &mut StdRng,
+        chooser: &mut impl for<'a> FnMut(
+            &mut StdRng,
+            &[Node<E>],
+            &'a [Child<R>],
+        ) -> Option<&'a Child<R>>,
     ) -> Option<(NodeId, Board)> {
         let mut board = self.board.clone();
         let mut gen_index = 0;
@@ -290,7 +372,7 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                                     pick_from.push((p, &**c));
                                 }
                             }
-                            let (piece, children) = *pick_from.choose(&mut thread_rng()).unwrap();
+                            let (piece, children) = *pick_from.choose(rng).unwrap();
                             board.add_next_piece(piece);
                             children
                         })
@@ -300,7 +382,7 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                 if let Some(children) = children {
                     // Branch case. Call the chooser to pick the branch to take.
                     match next.with_data(|gen| {
-                        let child = chooser(&gen.nodes, children)?;
+                        let child = chooser(rng, &gen.nodes, children)?;
                         advance(&mut board, child.placement);
                         gen_index += 1;
                         node_key = child.node as usize;
@@ -588,7 +670,9 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         self.generations.push_back(Generation::known(piece));
     }
 
-    pub fn get_plan(&self) -> Vec<(FallingPiece, LockResult)> {
+    /// Returns the best sequence of placements found, along with the result of each placement and
+    /// whether hold is used before it.
+    pub fn get_plan(&self) -> Vec<(FallingPiece, LockResult, bool)> {
         let mut node = self.root;
         let mut plan = vec![];
         let mut board = self.board.clone();
@@ -596,7 +680,9 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
             let done = gen.with_data(|gen| match &gen.children {
                 Children::Known(_, c) => match c[node as usize].as_ref().and_then(|c| c.first()) {
                     Some(child) => {
-                        plan.push((child.placement, advance(&mut board, child.placement)));
+                        let hold = board.hold_piece;
+                        let lock = advance(&mut board, child.placement);
+                        plan.push((child.placement, lock, hold != board.hold_piece));
                         node = child.node;
                         false
                     }
@@ -611,6 +697,32 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         plan
     }
 
+    /// The evaluation after each placement of the plan returned by `get_plan`, the same way
+    /// candidates are evaluated.
+    pub fn get_plan_evaluations(&self) -> Vec<E> {
+        let mut node = self.root;
+        let mut evaluations = vec![];
+        for (gen, child_gen) in self.generations.iter().zip(self.generations.iter().skip(1)) {
+            let next = gen.with_data(|gen| match &gen.children {
+                Children::Known(_, c) => c[node as usize]
+                    .as_ref()
+                    .and_then(|c| c.first())
+                    .map(|child| (child.node, child.reward.clone())),
+                _ => None,
+            });
+            match next {
+                Some((child, reward)) => {
+                    let eval =
+                        child_gen.with_data(|gen| gen.nodes[child as usize].evaluation.clone());
+                    evaluations.push(eval + reward);
+                    node = child;
+                }
+                None => break,
+            }
+        }
+        evaluations
+    }
+
     pub fn reset(&mut self, field: [[bool; 10]; 40], b2b: bool, combo: u32) -> Option<i32> {
         let garbage_lines;
         if b2b == self.board.b2b_bonus && combo == self.board.combo {
@@ -645,6 +757,8 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         self.board.b2b_bonus = b2b;
 
         self.gens_passed += self.generations.len() as u32 + 1;
+
+        self.pruned_nodes = 0;
         self.root = 0;
         self.generations.clear();
         self.init_generations();
@@ -668,13 +782,21 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
                             let mut board = self.board.clone();
                             let lock = advance(&mut board, child.placement);
                             let eval = child_gen.nodes[child.node as usize].evaluation.clone();
+                            let followups = match &child_gen.children {
+                                Children::Known(_, c) => c[child.node as usize]
+                                    .as_ref()
+                                    .map_or(vec![], |c| c.iter().map(|c| c.placement).collect()),
+                                _ => vec![],
+                            };
                             candidates.push(MoveCandidate {
+                                speculated_depth: self.speculated_depth(child.node),
                                 mv: child.placement,
                                 hold: self.board.hold_piece != board.hold_piece,
                                 evaluation: eval + child.reward.clone(),
                                 original_rank: i as u32,
                                 lock,
                                 board,
+                                followups,
                             });
                         }
                     }
@@ -684,11 +806,49 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         })
     }
 
+    /// Follows the best line from the specified node of generation 1 and counts how many of its
+    /// placements are of speculated pieces.
+    fn speculated_depth(&self, mut node: u32) -> u32 {
+        let mut depth = 0;
+        for gen in self.generations.iter().skip(1) {
+            let next = gen.with_data(|gen| match &gen.children {
+                Children::Known(_, c) => c[node as usize]
+                    .as_ref()
+                    .and_then(|c| c.first())
+                    .map(|c| c.node),
+                Children::Speculated(c) => c[node as usize].as_ref().and_then(|c| {
+                    depth += 1;
+                    c.values()
+                        .filter_map(|c| c.as_ref().and_then(|c| c.first()))
+                        .next()
+                        .map(|c| c.node)
+                }),
+            });
+            match next {
+                Some(n) => node = n,
+                None => break,
+            }
+        }
+        depth
+    }
+
+    /// The number of moves possible from the current position, including those known to be deaths.
+    pub fn root_children(&self) -> usize {
+        self.generations[0].with_data(|gen| match &gen.children {
+            Children::Known(_, children) => {
+                children[self.root as usize].as_ref().map_or(0, |c| c.len())
+            }
+            _ => 0,
+        })
+    }
+
     pub fn advance_move(&mut self, mv: FallingPiece) {
         if self.try_advance_move(mv).is_none() {
             self.board.lock_piece(mv);
 
             self.gens_passed += self.generations.len() as u32 + 1;
+
+            self.pruned_nodes = 0;
             self.root = 0;
             self.generations.clear();
             self.init_generations();
@@ -700,7 +860,13 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
             if let Children::Known(_, children) = &gen.children {
                 children[self.root as usize]
                     .as_ref()
-                    .and_then(|children| children.iter().find(|c| c.placement == mv))
+                    .and_then(|children| {
+                        // The move may have been given in a different but equivalent orientation
+                        // than the one we found (e.g. by a human player), so compare the cells.
+                        children.iter().find(|c| {
+                            c.placement.same_location(&mv) && c.placement.tspin == mv.tspin
+                        })
+                    })
                     .map(|c| c.node)
             } else {
                 None
@@ -711,17 +877,176 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         advance(&mut self.board, mv);
         self.generations.pop_front();
         self.gens_passed += 1;
+        self.pruned_nodes = 0;
 
         Some(())
     }
 
+    /// The number of nodes in the DAG, plus the nodes thrown away by `prune` since the last move
+    /// so that pruning doesn't keep the search from reaching its node limits.
     pub fn nodes(&self) -> u32 {
         self.generations
             .iter()
             .map(|gen| gen.with_data(|gen| gen.nodes.len() as u32))
+            .sum::<u32>()
+            + self.pruned_nodes
+    }
+
+    /// Estimates the number of bytes used by the nodes, children lists, and boards of the DAG.
+    /// Space reserved ahead of time for nodes that haven't been created yet isn't counted.
+    pub fn memory_usage(&self) -> usize {
+        use std::mem::size_of;
+        self.generations
+            .iter()
+            .map(|gen| {
+                gen.with(|gen| {
+                    let children = match &gen.data.children {
+                        Children::Known(_, c) => c.len() * size_of::<Option<&mut [Child<R>]>>(),
+                        Children::Speculated(c) => {
+                            c.len() * size_of::<Option<EnumMap<Piece, Option<&mut [Child<R>]>>>>()
+                        }
+                    };
+                    gen.arena.allocated_bytes()
+                        + gen.data.nodes.len() * size_of::<Node<E>>()
+                        + gen.data.deduplicator.len() * size_of::<(SimplifiedBoard, u32)>()
+                        + children
+                })
+            })
             .sum()
     }
 
+    /// Throws away everything except the moves from the root and the best line of play to free up
+    /// memory. Nodes which lose their children become leaves, so the search can expand them again.
+    ///
+    /// Thoughts that are in progress are forgotten.
+    pub fn prune(&mut self) {
+        // the nodes to keep in each generation, and the node on the best line
+        let mut kept = vec![vec![self.root]];
+        let mut best_line = vec![];
+        let mut best = Some(self.root);
+        for gen in &self.generations {
+            best_line.push(best);
+            let mut next = vec![];
+            best = best.and_then(|node| {
+                gen.with_data(|gen| match &gen.children {
+                    Children::Known(_, c) => {
+                        let children = c[node as usize].as_deref()?;
+                        next.extend(children.iter().map(|c| c.node));
+                        children.first().map(|c| c.node)
+                    }
+                    Children::Speculated(c) => {
+                        let cases = c[node as usize].iter().flat_map(|cases| cases.values());
+                        for children in cases.flatten() {
+                            next.extend(children.iter().map(|c| c.node));
+                        }
+                        None
+                    }
+                })
+            });
+            next.sort_unstable();
+            next.dedup();
+            kept.push(next);
+        }
+
+        let total = self.nodes() - self.pruned_nodes;
+        let remaining = kept.iter().map(|k| k.len() as u32).sum::<u32>();
+        if remaining >= total {
+            return;
+        }
+
+        let mut saved = Vec::with_capacity(self.generations.len());
+        let mut parents = vec![vec![]];
+        for (i, gen) in self.generations.iter().enumerate() {
+            let next_keys: HashMap<_, _> = kept[i + 1]
+                .iter()
+                .enumerate()
+                .map(|(new, &old)| (old, new as u32))
+                .collect();
+            let mut next_parents = vec![vec![]; kept[i + 1].len()];
+            let mut keep_children = |parent: u32, children: &[Child<R>]| -> Vec<_> {
+                children
+                    .iter()
+                    .map(|c| {
+                        let node = next_keys[&c.node];
+                        next_parents[node as usize].push(parent);
+                        SavedChild {
+                            placement: c.placement,
+                            reward: c.reward.clone(),
+                            original_rank: c.original_rank,
+                            node,
+                        }
+                    })
+                    .collect()
+            };
+
+            saved.push(gen.with_data(|gen| {
+                let mut nodes = vec![];
+                let mut known = vec![];
+                let mut speculated = vec![];
+                let parents = std::mem::take(&mut parents);
+                for (new, (&old, parents)) in kept[i].iter().zip(parents).enumerate() {
+                    let node = &gen.nodes[old as usize];
+                    // dead nodes keep their empty children lists so they aren't expanded again
+                    let keep = best_line[i] == Some(old) || node.death;
+                    match &gen.children {
+                        Children::Known(_, c) => known.push(
+                            c[old as usize]
+                                .as_deref()
+                                .filter(|_| keep)
+                                .map(|c| keep_children(new as u32, c)),
+                        ),
+                        Children::Speculated(c) => speculated.push(
+                            c[old as usize].as_ref().filter(|_| keep).map(|cases| {
+                                let mut saved = EnumMap::new();
+                                for (p, c) in cases {
+                                    saved[p] = c.as_deref().map(|c| keep_children(new as u32, c));
+                                }
+                                saved
+                            }),
+                        ),
+                    }
+                    nodes.push(SavedNode {
+                        parents,
+                        evaluation: node.evaluation.clone(),
+                        death: node.death,
+                    });
+                }
+
+                let keys: HashMap<_, _> = kept[i]
+                    .iter()
+                    .enumerate()
+                    .map(|(new, &old)| (old, new as u32))
+                    .collect();
+                SavedGeneration {
+                    nodes,
+                    children: match &gen.children {
+                        Children::Known(piece, _) => SavedChildren::Known(*piece, known),
+                        Children::Speculated(_) => SavedChildren::Speculated(speculated),
+                    },
+                    deduplicator: gen
+                        .deduplicator
+                        .iter()
+                        .filter_map(|(b, node)| Some((SavedBoard::from(b), *keys.get(node)?)))
+                        .collect(),
+                }
+            }));
+            parents = next_parents;
+        }
+
+        self.generations = saved.into_iter().map(Generation::load).collect();
+        self.root = 0;
+        // the kept nodes have new slab keys, so thoughts in progress can't be applied anymore
+        self.gens_passed += self.generations.len() as u32;
+        self.pruned_nodes += total - remaining;
+    }
+
+    pub fn nodes_per_generation(&self) -> Vec<u32> {
+        self.generations
+            .iter()
+            .map(|gen| gen.with_data(|gen| gen.nodes.len() as u32))
+            .collect()
+    }
+
     pub fn depth(&self) -> u32 {
         let mut depth = self.generations.len() as u32 - 1;
         for gen in self.generations.iter().rev() {
@@ -741,6 +1066,38 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
         &self.board
     }
 
+    pub fn save(&self) -> SavedDag<E, R> {
+        SavedDag {
+            board: self.board.clone(),
+            root: self.root,
+            use_hold: self.use_hold,
+            exploration: self.exploration,
+            generations: self.generations.iter().map(Generation::save).collect(),
+        }
+    }
+
+    /// Rebuilds a saved DAG. Nodes that were being thought about when it was saved can be
+    /// picked by `find_and_mark_leaf` again.
+    pub fn load(saved: SavedDag<E, R>, seed: Option<u64>) -> Self {
+        DagState {
+            board: saved.board,
+            generations: saved
+                .generations
+                .into_iter()
+                .map(Generation::load)
+                .collect(),
+            root: saved.root,
+            gens_passed: 0,
+            pruned_nodes: 0,
+            use_hold: saved.use_hold,
+            exploration: valid_exploration(saved.exploration),
+            rng: match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+        }
+    }
+
     pub fn is_dead(&self) -> bool {
         self.generations[0].with_data(|gen| match &gen.children {
             Children::Known(_, childrens) => childrens[self.root as usize]
@@ -753,6 +1110,17 @@ impl<E: Evaluation<R> + 'static, R: Clone + 'static> DagState<E, R> {
     }
 }
 
+/// Replaces exploration values that aren't positive, which would make every weight zero or
+/// negative and stall the search, with a very small positive value.
+fn valid_exploration(exploration: f32) -> f32 {
+    const MIN_EXPLORATION: f32 = 0.001;
+    if exploration >= MIN_EXPLORATION {
+        exploration
+    } else {
+        MIN_EXPLORATION
+    }
+}
+
 fn child_eval_fn<'a, E, R>(child_gen_nodes: &'a [Node<E>]) -> impl Fn(&Child<R>) -> Option<E> + 'a
 where
     E: Evaluation<R>,
@@ -769,7 +1137,7 @@ where
 }
 
 /// keeps queue state consistent while arbitrarily placing pieces
-fn advance(board: &mut Board, placement: FallingPiece) -> LockResult {
+pub fn advance(board: &mut Board, placement: FallingPiece) -> LockResult {
     let result = board.lock_piece(placement);
     let next = board.advance_queue().unwrap();
     if next != placement.kind.0 {
@@ -872,8 +1240,323 @@ impl<E: 'static, R: 'static> Generation<E, R> {
     }
 }
 
+impl<E: Clone + 'static, R: Clone + 'static> Generation<E, R> {
+    fn save(&self) -> SavedGeneration<E, R> {
+        fn save_children<R: Clone>(children: &[Child<R>]) -> Vec<SavedChild<R>> {
+            children
+                .iter()
+                .map(|c| SavedChild {
+                    placement: c.placement,
+                    reward: c.reward.clone(),
+                    original_rank: c.original_rank,
+                    node: c.node,
+                })
+                .collect()
+        }
+
+        self.with_data(|gen| SavedGeneration {
+            nodes: gen
+                .nodes
+                .iter()
+                .map(|n| SavedNode {
+                    parents: n.parents.to_vec(),
+                    evaluation: n.evaluation.clone(),
+                    death: n.death,
+                })
+                .collect(),
+            children: match &gen.children {
+                Children::Known(piece, childrens) => SavedChildren::Known(
+                    *piece,
+                    childrens
+                        .iter()
+                        .map(|c| c.as_deref().map(save_children))
+                        .collect(),
+                ),
+                Children::Speculated(childrens) => SavedChildren::Speculated(
+                    childrens
+                        .iter()
+                        .map(|c| {
+                            c.as_ref().map(|cases| {
+                                let mut saved = EnumMap::new();
+                                for (p, c) in cases {
+                                    saved[p] = c.as_deref().map(save_children);
+                                }
+                                saved
+                            })
+                        })
+                        .collect(),
+                ),
+            },
+            deduplicator: gen
+                .deduplicator
+                .iter()
+                .map(|(b, &node)| (SavedBoard::from(b), node))
+                .collect(),
+        })
+    }
+
+    fn load(saved: SavedGeneration<E, R>) -> Self {
+        fn load_children<'c, R>(
+            arena: &'c bumpalo::Bump,
+            children: Vec<SavedChild<R>>,
+        ) -> &'c mut [Child<R>] {
+            arena.alloc_slice_fill_iter(children.into_iter().map(|c| Child {
+                placement: c.placement,
+                reward: c.reward,
+                original_rank: c.original_rank,
+                node: c.node,
+            }))
+        }
+
+        Generation::new(Box::new(bumpalo::Bump::new()), |arena| GenerationData {
+            nodes: saved
+                .nodes
+                .into_iter()
+                .map(|n| {
+                    let mut parents = BumpVec::with_capacity_in(n.parents.len(), arena);
+                    parents.extend(n.parents);
+                    Node {
+                        parents,
+                        evaluation: n.evaluation,
+                        marked: false,
+                        death: n.death,
+                    }
+                })
+                .collect(),
+            children: match saved.children {
+                SavedChildren::Known(piece, childrens) => Children::Known(
+                    piece,
+                    childrens
+                        .into_iter()
+                        .map(|c| c.map(|c| load_children(arena, c)))
+                        .collect(),
+                ),
+                SavedChildren::Speculated(childrens) => Children::Speculated(
+                    childrens
+                        .into_iter()
+                        .map(|c| {
+                            c.map(|cases| {
+                                let mut loaded = EnumMap::new();
+                                for (p, c) in cases {
+                                    loaded[p] = c.map(|c| load_children(arena, c));
+                                }
+                                loaded
+                            })
+                        })
+                        .collect(),
+                ),
+            },
+            deduplicator: saved
+                .deduplicator
+                .into_iter()
+                .map(|(b, node)| {
+                    let board = SimplifiedBoard {
+                        grid: arena.alloc_slice_copy(&b.grid),
+                        combo: b.combo,
+                        bag: b.bag,
+                        reserve: b.reserve,
+                        back_to_back: b.back_to_back,
+                        reserve_is_hold: b.reserve_is_hold,
+                    };
+                    (board, node)
+                })
+                .collect(),
+        })
+    }
+}
+
+impl From<&SimplifiedBoard<'_>> for SavedBoard {
+    fn from(b: &SimplifiedBoard) -> Self {
+        SavedBoard {
+            grid: b.grid.to_vec(),
+            combo: b.combo,
+            bag: b.bag,
+            reserve: b.reserve,
+            back_to_back: b.back_to_back,
+            reserve_is_hold: b.reserve_is_hold,
+        }
+    }
+}
+
 fn remove_last<T>(slice: &mut &mut [T]) {
     let data = std::mem::replace(slice, &mut []);
     let (_, rest) = data.split_last_mut().expect("Slice is empty");
     *slice = rest;
 }
+
+#[cfg(test)]
+mod tests {
+    use libtetris::*;
+
+    use super::*;
+    use crate::evaluation::{Evaluator, Standard};
+
+    type StandardDag = DagState<<Standard as Evaluator>::Value, <Standard as Evaluator>::Reward>;
+
+    /// Expands the root of a DAG for a T piece on an empty board without hold.
+    fn expanded_root(exploration: f32) -> StandardDag {
+        let mut board = Board::new();
+        for &piece in &[Piece::T, Piece::I, Piece::O] {
+            board.add_next_piece(piece);
+        }
+        let mut dag = DagState::new(board, false, exploration, Some(0));
+        let (node, board) = dag.find_and_mark_leaf(&mut vec![]).unwrap();
+        expand(&mut dag, node, board);
+        dag
+    }
+
+    /// Gives the leaf every placement of the next piece as children.
+    fn expand(dag: &mut StandardDag, node: NodeId, mut board: Board) {
+        let eval = Standard::default();
+        let next = board.advance_queue().unwrap();
+        let spawned = SpawnRule::Row19Or20.spawn(next, &board).unwrap();
+        let moves = find_moves(
+            &board,
+            spawned,
+            MovementMode::ZeroG,
+            false,
+            KickTable::Srs,
+            SpinDetectionMode::TSpinOnly,
+        );
+        let children = moves
+            .into_iter()
+            .map(|mv| {
+                let mut result = board.clone();
+                let lock = result.lock_piece(mv.location);
+                let (evaluation, reward) =
+                    eval.evaluate(&lock, &result, mv.inputs.time, 0, next, &board);
+                ChildData {
+                    mv: mv.location,
+                    board: result,
+                    evaluation,
+                    reward,
+                }
+            })
+            .collect();
+        dag.update_known(node, children);
+    }
+
+    /// Counts how many of `samples` leaf selections go through each child of the root.
+    pub(crate) fn root_visits(exploration: f32, samples: usize) -> Vec<usize> {
+        let mut dag = expanded_root(exploration);
+        let mut visits = vec![0; dag.root_children()];
+        for _ in 0..samples {
+            let (node, _) = dag.find_and_mark_leaf(&mut vec![]).unwrap();
+            assert_eq!(node.generation, 1);
+            visits[node.slab_key as usize] += 1;
+            dag.unmark(node);
+        }
+        visits
+    }
+
+    /// The fraction of visits that went to the most visited child.
+    pub(crate) fn top_share(visits: &[usize]) -> f64 {
+        *visits.iter().max().unwrap() as f64 / visits.iter().sum::<usize>() as f64
+    }
+
+    #[test]
+    fn higher_exploration_spreads_visits() {
+        let narrow = root_visits(1.0, 2000);
+        let wide = root_visits(8.0, 2000);
+        let visited = |visits: &[usize]| visits.iter().filter(|&&v| v != 0).count();
+        assert!(top_share(&wide) < top_share(&narrow));
+        assert!(visited(&wide) > visited(&narrow));
+    }
+
+    #[test]
+    fn very_high_exploration_flattens_visits() {
+        let visits = root_visits(1000.0, 4000);
+        let even = 1.0 / visits.len() as f64;
+        assert!(visits.iter().all(|&v| v != 0));
+        assert!(top_share(&visits) < 2.0 * even);
+    }
+
+    #[test]
+    fn non_positive_exploration_still_searches() {
+        for &exploration in &[0.0, -1.0, f32::NAN] {
+            let visits = root_visits(exploration, 10);
+            assert_eq!(visits.iter().sum::<usize>(), 10);
+        }
+    }
+
+    #[test]
+    fn advancing_to_lower_ranked_child_keeps_its_subtree() {
+        let mut dag = expanded_root(1.0);
+        let mv = dag.get_next_candidates()[3].mv;
+        let (node, board) = dag.find_and_mark_leaf(&mut vec![vec![mv]]).unwrap();
+        expand(&mut dag, node, board);
+        let subtree = dag.nodes_per_generation()[2];
+        assert!(subtree > 0);
+
+        dag.advance_move(mv);
+        assert_eq!(dag.root_children() as u32, subtree);
+    }
+
+    #[test]
+    fn advancing_with_equivalent_placement_keeps_its_subtree() {
+        let mut dag = expanded_root(1.0);
+        let t = dag.get_next_candidates()[0].mv;
+        let (node, board) = dag.find_and_mark_leaf(&mut vec![vec![t]]).unwrap();
+        expand(&mut dag, node, board);
+        dag.advance_move(t);
+
+        // a vertical I that wasn't the best move, played in the other vertical orientation
+        let candidates = dag.get_next_candidates();
+        let mv = candidates[1..]
+            .iter()
+            .map(|c| c.mv)
+            .find(|mv| matches!(mv.kind.1, RotationState::East | RotationState::West))
+            .unwrap();
+        let (node, board) = dag.find_and_mark_leaf(&mut vec![vec![mv]]).unwrap();
+        expand(&mut dag, node, board);
+        let subtree = dag.nodes_per_generation()[2];
+        assert!(subtree > 0);
+
+        let rotation = match mv.kind.1 {
+            RotationState::East => RotationState::West,
+            _ => RotationState::East,
+        };
+        let played = (-2..12)
+            .flat_map(|x| (-2..42).map(move |y| (x, y)))
+            .map(|(x, y)| FallingPiece {
+                kind: PieceState(Piece::I, rotation),
+                x,
+                y,
+                tspin: TspinStatus::None,
+            })
+            .find(|p| p.same_location(&mv))
+            .unwrap();
+        assert!(candidates.iter().all(|c| c.mv != played));
+
+        dag.advance_move(played);
+        assert_eq!(dag.root_children() as u32, subtree);
+    }
+
+    #[test]
+    fn with_placement_matches_advance() {
+        let mut board = Board::new();
+        for &piece in &[Piece::T, Piece::I, Piece::O, Piece::L, Piece::J] {
+            board.add_next_piece(piece);
+        }
+        let place = |board: &Board, piece: Piece, hold: bool| {
+            let mut placement = SpawnRule::Row19Or20.spawn(piece, board).unwrap();
+            placement.sonic_drop(board);
+            let (placed, lock) = board.with_placement(placement, hold);
+            let mut advanced = board.clone();
+            assert_eq!(advance(&mut advanced, placement), lock);
+            assert_eq!(placed.get_field(), advanced.get_field());
+            assert_eq!(placed.hold_piece, advanced.hold_piece);
+            assert!(placed.next_queue().eq(advanced.next_queue()));
+            placed
+        };
+
+        // the T is placed, then the I is held while the O is placed, then the held I is swapped
+        // back out for the L
+        let board = place(&board, Piece::T, false);
+        let board = place(&board, Piece::O, true);
+        assert_eq!(board.hold_piece, Some(Piece::I));
+        let board = place(&board, Piece::I, true);
+        assert_eq!(board.hold_piece, Some(Piece::L));
+        assert!(board.next_queue().eq([Piece::J].iter().copied()));
+    }
+}
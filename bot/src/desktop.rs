@@ -1,31 +1,205 @@
-use std::sync::Arc;
+use std::any::{Any, TypeId};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use crossbeam_channel::{select, unbounded, Receiver, Sender, TryRecvError};
 use libtetris::*;
 use opening_book::Book;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 use crate::evaluation::Evaluator;
-use crate::modes::ModeSwitchedBot;
-use crate::{BotMsg, BotPollState, Info, Options};
+use crate::modes::{ModeSwitchedBot, SavedBot};
+use crate::{
+    BoardDiff, BotMsg, BotPollState, BotStateSnapshot, BotStats, Candidate, DeathReason, Info,
+    MoveTrace, Options, SearchProgress, ShutdownReport,
+};
+
+/// The least amount of time between reports of the moves the bot is considering.
+const CANDIDATES_INTERVAL: Duration = Duration::from_millis(100);
+/// The most time that passes between updates of the progress read by `Interface::poll_status`
+/// while the bot is searching.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(10);
+/// The shortest span of time the node rate reported by `Interface::stats` is measured over.
+const STATS_WINDOW: Duration = Duration::from_secs(1);
+
+/// Counters updated by the bot and worker threads and read by `Interface::stats` and
+/// `Interface::poll_status`.
+#[derive(Default)]
+struct SharedStats {
+    total_nodes: AtomicU64,
+    tree_size: AtomicU32,
+    peak_tree_size: AtomicU32,
+    depth: AtomicU32,
+    progress: Mutex<Option<SearchProgress>>,
+}
+
+/// The reason `Interface::save_state` couldn't save the bot's state.
+#[derive(Debug)]
+pub enum SaveStateError {
+    /// The bot is dead.
+    Dead,
+    /// The bot isn't running with an evaluator of the requested type.
+    WrongEvaluator,
+    /// The state couldn't be serialized.
+    Serialize(bincode::Error),
+}
+
+impl std::fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SaveStateError::Dead => write!(f, "the bot is dead"),
+            SaveStateError::WrongEvaluator => {
+                write!(f, "the bot is running with a different type of evaluator")
+            }
+            SaveStateError::Serialize(e) => write!(f, "failed to serialize the bot's state: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SaveStateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SaveStateError::Serialize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+type CandidatesCallback = Box<dyn FnMut(Vec<Candidate>) + Send>;
 
 pub struct Interface {
     send: Sender<BotMsg>,
+    eval_send: Sender<Box<dyn Any + Send>>,
+    evaluator_type: TypeId,
     recv: Receiver<(Move, Info)>,
+    board_recv: Receiver<Board>,
+    plan_recv: Receiver<Vec<(FallingPiece, LockResult, bool)>>,
+    candidates_recv: Receiver<Vec<Candidate>>,
+    candidates_callbacks: Arc<Mutex<Vec<CandidatesCallback>>>,
+    candidates_thread: Mutex<Option<JoinHandle<()>>>,
+    death_recv: Receiver<(DeathReason, Board)>,
+    trace_recv: Receiver<MoveTrace>,
+    state_recv: Receiver<Box<dyn Any + Send>>,
+    ranked_recv: Receiver<Vec<Candidate>>,
+    death: Mutex<Option<(DeathReason, Board)>>,
+    options: Mutex<Options>,
+    stats: Arc<SharedStats>,
+    node_samples: Mutex<VecDeque<(Instant, u64)>>,
+    thread: JoinHandle<()>,
+    launched: Instant,
 }
 
 impl Interface {
     /// Launches a bot thread with the specified starting board and options.
-    pub fn launch(
+    pub fn launch<E>(board: Board, options: Options, evaluator: E, book: Option<Arc<Book>>) -> Self
+    where
+        E: Evaluator + Send + 'static,
+    {
+        Self::launch_with_pool(board, options, evaluator, book, None, None)
+    }
+
+    /// Launches a bot thread which continues from a state saved with `save_state`.
+    ///
+    /// The evaluator must be of the same type as the one the saved bot used. The search picks up
+    /// where it left off, except that any move which was requested but not yet provided when the
+    /// state was saved must be requested again.
+    pub fn launch_from_state<E>(
+        state: &[u8],
+        evaluator: E,
+        book: Option<Arc<Book>>,
+    ) -> bincode::Result<Self>
+    where
+        E: Evaluator + Send + 'static,
+        E::Value: Serialize + DeserializeOwned,
+        E::Reward: Serialize + DeserializeOwned,
+    {
+        let saved: SavedBot<E::Value, E::Reward> = bincode::deserialize(state)?;
+        let board = saved.board().clone();
+        let options = saved.options();
+        let saved = Some(saved).filter(SavedBot::can_load);
+        Ok(Self::launch_with_pool(
+            board, options, evaluator, book, None, saved,
+        ))
+    }
+
+    fn launch_with_pool<E>(
         board: Board,
-        options: Options,
-        evaluator: impl Evaluator + Send + 'static,
+        mut options: Options,
+        evaluator: E,
         book: Option<Arc<Book>>,
-    ) -> Self {
+        pool: Option<Arc<rayon::ThreadPool>>,
+        saved: Option<SavedBot<E::Value, E::Reward>>,
+    ) -> Self
+    where
+        E: Evaluator + Send + 'static,
+    {
+        // resolved here rather than on the bot thread so that `update_options` sees the same
+        // number of threads the bot is running with
+        if options.threads == 0 {
+            options.threads = num_cpus::get() as u32;
+        }
+
         let (bot_send, recv) = unbounded();
         let (send, bot_recv) = unbounded();
-        std::thread::spawn(move || run(bot_recv, bot_send, board, evaluator, options, book));
+        let (eval_send, eval_recv) = unbounded();
+        let (board_send, board_recv) = unbounded();
+        let (plan_send, plan_recv) = unbounded();
+        let (candidates_send, candidates_recv) = unbounded();
+        let (death_send, death_recv) = unbounded();
+        let (trace_send, trace_recv) = unbounded();
+        let (state_send, state_recv) = unbounded();
+        let (ranked_send, ranked_recv) = unbounded();
+        let stats = Arc::new(SharedStats::default());
+        let bot_stats = stats.clone();
+        let launched = Instant::now();
+        let bot_options = options.clone();
+        let thread = std::thread::spawn(move || {
+            run(
+                bot_recv,
+                eval_recv,
+                bot_send,
+                board_send,
+                plan_send,
+                candidates_send,
+                death_send,
+                trace_send,
+                state_send,
+                ranked_send,
+                bot_stats,
+                board,
+                evaluator,
+                bot_options,
+                book,
+                pool,
+                saved,
+            )
+        });
 
-        Interface { send, recv }
+        Interface {
+            send,
+            eval_send,
+            evaluator_type: TypeId::of::<E>(),
+            recv,
+            board_recv,
+            plan_recv,
+            candidates_recv,
+            candidates_callbacks: Arc::new(Mutex::new(vec![])),
+            candidates_thread: Mutex::new(None),
+            death_recv,
+            trace_recv,
+            state_recv,
+            ranked_recv,
+            death: Mutex::new(None),
+            options: Mutex::new(options),
+            stats,
+            node_samples: Mutex::new(VecDeque::new()),
+            thread,
+            launched,
+        }
     }
 
     /// Request the bot to provide a move as soon as possible.
@@ -45,10 +219,33 @@ impl Interface {
     ///
     /// Once a move is chosen, the move will become available by calling `poll_next_move` or
     /// `block_next_move`. To update the bot state according to this move, call `play_next_move`.
+    ///
+    /// `incoming` is the number of lines of garbage the bot expects to receive. Besides affecting
+    /// which move is picked, it is used when evaluating placements found from now on.
     pub fn suggest_next_move(&self, incoming: u32) {
         self.send.send(BotMsg::SuggestMove(incoming)).ok();
     }
 
+    /// Tells the bot where the holes of incoming garbage are likely to be.
+    ///
+    /// Each entry is the relative likelihood of the hole being in that column, with index 0 being
+    /// the leftmost column. When garbage is incoming, the bot will try to keep the columns where
+    /// the holes are expected low enough to dig out. Passing all zeros means nothing is known
+    /// about where the holes will be, which is the default.
+    pub fn expect_garbage_holes(&self, columns: [u32; 10]) {
+        self.send.send(BotMsg::ExpectGarbageHoles(columns)).ok();
+    }
+
+    /// Tells the bot what the opponent's field looks like.
+    ///
+    /// The field is in the same format as for `reset`. The bot only uses the height of the
+    /// opponent's stack, and will send garbage when it can safely top out the opponent.
+    pub fn set_opponent_board(&self, field: &[[bool; 10]; 40]) {
+        self.send
+            .send(BotMsg::OpponentHeight(crate::field_height(field)))
+            .ok();
+    }
+
     /// Checks to see if the bot has provided the previously requested move yet.
     ///
     /// The returned move contains both a path and the expected location of the placed piece. The
@@ -64,6 +261,83 @@ impl Interface {
         })
     }
 
+    /// Checks on the progress of the bot's search without waiting for it.
+    ///
+    /// The bot updates its progress regularly while it searches, so the report is at most a few
+    /// milliseconds old. This returns `None` if the bot hasn't started searching yet or is looking
+    /// for a perfect clear.
+    pub fn poll_status(&self) -> Option<SearchProgress> {
+        self.stats.progress.lock().unwrap().clone()
+    }
+
+    /// Reports how hard the bot is working.
+    ///
+    /// This doesn't wait for the bot. The node rate is measured between calls to this function
+    /// over at least the last second, so it is 0 until this has been called twice a second apart,
+    /// and is intended to be called regularly (e.g. once per frame).
+    pub fn stats(&self) -> BotStats {
+        let total_nodes = self.stats.total_nodes.load(Ordering::Relaxed);
+        let now = Instant::now();
+        let mut samples = self.node_samples.lock().unwrap();
+        samples.push_back((now, total_nodes));
+        // keep the most recent sample that is at least a window old to measure from
+        while samples.len() > 1 && now - samples[1].0 >= STATS_WINDOW {
+            samples.pop_front();
+        }
+        let (then, then_nodes) = samples[0];
+        let elapsed = (now - then).as_secs_f64();
+        BotStats {
+            nodes_per_second: if now - then >= STATS_WINDOW {
+                (total_nodes - then_nodes) as f64 / elapsed
+            } else {
+                0.0
+            },
+            total_nodes,
+            tree_size: self.stats.tree_size.load(Ordering::Relaxed),
+            depth: self.stats.depth.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Calls `callback` with the moves the bot is considering for the current piece, best first,
+    /// whenever they change as the search progresses.
+    ///
+    /// Each update is given to every callback registered so far. The callbacks are called one
+    /// after the other from a separate thread, at most once every 100ms. They stop being called
+    /// once the bot is dead.
+    pub fn on_candidates_update(&self, callback: impl FnMut(Vec<Candidate>) + Send + 'static) {
+        if self.send.send(BotMsg::WatchCandidates).is_err() {
+            return;
+        }
+        self.candidates_callbacks
+            .lock()
+            .unwrap()
+            .push(Box::new(callback));
+        let mut thread = self.candidates_thread.lock().unwrap();
+        if thread.is_none() {
+            let recv = self.candidates_recv.clone();
+            let callbacks = self.candidates_callbacks.clone();
+            *thread = Some(std::thread::spawn(move || {
+                for candidates in recv {
+                    for callback in callbacks.lock().unwrap().iter_mut() {
+                        callback(candidates.clone());
+                    }
+                }
+            }));
+        }
+    }
+
+    /// Explains why the bot died, along with the board it died on.
+    ///
+    /// `None` is returned if the bot isn't dead, or if it died for some other reason, such as the
+    /// bot thread panicking.
+    pub fn death_reason(&self) -> Option<(DeathReason, Board)> {
+        let mut death = self.death.lock().unwrap();
+        if let Ok(reason) = self.death_recv.try_recv() {
+            *death = Some(reason);
+        }
+        death.clone()
+    }
+
     /// Waits until the bot provides the previously requested move.
     ///
     /// `None` is returned if the bot is dead.
@@ -71,6 +345,21 @@ impl Interface {
         self.recv.recv().ok()
     }
 
+    /// Records how the bot chooses the next move it suggests, for debugging.
+    ///
+    /// The trace becomes available from `block_move_trace` once the move has been provided. Only
+    /// the next move is traced; call this again to trace another.
+    pub fn trace_next_move(&self) {
+        self.send.send(BotMsg::TraceNextMove).ok();
+    }
+
+    /// Waits until the bot provides the trace requested with `trace_next_move`.
+    ///
+    /// `None` is returned if the bot is dead.
+    pub fn block_move_trace(&self) -> Option<MoveTrace> {
+        self.trace_recv.recv().ok()
+    }
+
     /// Updates the internal bot state according to the move played.
     pub fn play_next_move(&self, mv: FallingPiece) {
         self.send.send(BotMsg::PlayMove(mv)).ok();
@@ -104,25 +393,237 @@ impl Interface {
             .ok();
     }
 
+    /// Compares the bot's internal board with the specified board.
+    ///
+    /// This is useful to detect when the bot's idea of the game state has drifted from the actual
+    /// game state, which can then be fixed using `reset`. Only moves that have been passed to
+    /// `play_next_move` are reflected in the bot's board.
+    ///
+    /// `None` is returned if the bot is dead.
+    pub fn diff_board(&self, board: &Board) -> Option<BoardDiff> {
+        self.send.send(BotMsg::RequestBoard).ok()?;
+        let ours = self.board_recv.recv().ok()?;
+        Some(BoardDiff::new(&ours, board))
+    }
+
+    /// Adds up the garbage sent by the first `pieces` moves of the bot's current plan.
+    ///
+    /// This is how much attack the bot expects to be able to send soon. If the plan is shorter
+    /// than `pieces` moves, only the moves in the plan are counted. `None` is returned if the bot
+    /// is dead.
+    pub fn projected_attack(&self, pieces: usize) -> Option<u32> {
+        self.send.send(BotMsg::RequestPlan).ok()?;
+        let plan = self.plan_recv.recv().ok()?;
+        Some(
+            plan.iter()
+                .take(pieces)
+                .map(|(_, lock, _)| lock.garbage_sent)
+                .sum(),
+        )
+    }
+
+    /// Lists up to `n` of the placements the bot is considering for the current piece, best first.
+    ///
+    /// The ranking is the one the search currently has, so the move provided by `poll_next_move`
+    /// may not be the first candidate if the evaluator picks moves differently (e.g. to deal with
+    /// incoming garbage). `None` is returned if the bot is dead.
+    pub fn suggest_candidates(&self, n: usize) -> Option<Vec<Candidate>> {
+        self.send.send(BotMsg::RequestCandidates).ok()?;
+        let mut candidates = self.ranked_recv.recv().ok()?;
+        candidates.truncate(n);
+        Some(candidates)
+    }
+
+    /// Captures the bot's current position: its field, bag, hold piece, combo, back-to-back
+    /// status, and next queue.
+    ///
+    /// Like `diff_board`, only moves that have been passed to `play_next_move` are reflected in
+    /// the snapshot. `None` is returned if the bot is dead.
+    pub fn snapshot_state(&self) -> Option<BotStateSnapshot> {
+        self.send.send(BotMsg::RequestBoard).ok()?;
+        let ours = self.board_recv.recv().ok()?;
+        Some(BotStateSnapshot::new(&ours))
+    }
+
+    /// Returns the bot to a position previously captured with `snapshot_state`.
+    ///
+    /// This throws away all previous computations, so the bot starts analyzing the position from
+    /// scratch. Any move that was requested but not yet provided is cancelled.
+    pub fn restore_state(&self, snapshot: BotStateSnapshot) {
+        self.send.send(BotMsg::RestoreState(snapshot)).ok();
+    }
+
+    /// Saves the bot's position, options, and analysis so that it can be continued later, even in
+    /// another process, with `launch_from_state`.
+    ///
+    /// `E` must be the type of the evaluator the bot is running with. The saved state can be
+    /// large, since it includes everything the bot has found.
+    pub fn save_state<E>(&self) -> Result<Vec<u8>, SaveStateError>
+    where
+        E: Evaluator,
+        E::Value: Serialize,
+        E::Reward: Serialize,
+    {
+        self.send
+            .send(BotMsg::RequestState)
+            .map_err(|_| SaveStateError::Dead)?;
+        let saved = self.state_recv.recv().map_err(|_| SaveStateError::Dead)?;
+        let saved = saved
+            .downcast::<SavedBot<E::Value, E::Reward>>()
+            .map_err(|_| SaveStateError::WrongEvaluator)?;
+        bincode::serialize(&*saved).map_err(SaveStateError::Serialize)
+    }
+
+    /// Stops the bot, waiting for its threads to finish their work, and reports what it did.
+    pub fn shutdown(self) -> ShutdownReport {
+        let Interface {
+            send,
+            eval_send,
+            stats,
+            thread,
+            candidates_thread,
+            launched,
+            ..
+        } = self;
+        // the bot thread stops once it can't receive any more messages
+        drop(send);
+        drop(eval_send);
+        thread.join().ok();
+        // and the candidates callbacks stop once the bot thread is gone
+        if let Some(candidates_thread) = candidates_thread.into_inner().unwrap() {
+            candidates_thread.join().ok();
+        }
+        ShutdownReport {
+            total_nodes: stats.total_nodes.load(Ordering::Relaxed),
+            peak_tree_size: stats.peak_tree_size.load(Ordering::Relaxed),
+            wall_time: launched.elapsed(),
+        }
+    }
+
+    /// Changes the bot's options without throwing away its analysis.
+    ///
+    /// `f` is given the bot's current options to modify. The thinking budgets (`min_nodes`,
+    /// `max_nodes`), `speculate`, `speculation_replan`, `speculation_penalty`, `report_pc`,
+    /// `report_missed_tspins`, `pcloop_time_limit`, `book_transition`, `max_forced_lines`,
+    /// `garbage_cap`, and `prefer_fast_inputs` can be changed. The other options describe the
+    /// game or the shape of the search tree, so if `f` changes any of them nothing is updated and
+    /// `false` is returned.
+    pub fn update_options(&self, f: impl FnOnce(&mut Options)) -> bool {
+        let mut options = self.options.lock().unwrap();
+        let mut new = options.clone();
+        f(&mut new);
+        if !options.can_change_to(&new) {
+            return false;
+        }
+        *options = new.clone();
+        self.send.send(BotMsg::UpdateOptions(new)).ok();
+        true
+    }
+
+    /// Replaces the evaluator the bot uses, such as to switch to more defensive weights.
+    ///
+    /// The evaluator must be of the same type as the one the bot was launched with. Returns false
+    /// without changing anything if it isn't. The search is not restarted, so positions the bot
+    /// has already analyzed keep their evaluations from the old evaluator and only newly analyzed
+    /// positions use the new one. Use `reset` as well if the old analysis should be thrown away.
+    pub fn update_evaluator<E: Evaluator + 'static>(&self, evaluator: E) -> bool {
+        if TypeId::of::<E>() != self.evaluator_type {
+            return false;
+        }
+        self.eval_send.send(Box::new(evaluator)).ok();
+        true
+    }
+
     /// Specifies a line that Cold Clear should analyze before making any moves.
     pub fn force_analysis_line(&self, path: Vec<FallingPiece>) {
         self.send.send(BotMsg::ForceAnalysisLine(path)).ok();
     }
+
+    /// Removes all of the lines specified by `force_analysis_line` that haven't been analyzed yet.
+    pub fn clear_forced_lines(&self) {
+        self.send.send(BotMsg::ClearForcedLines).ok();
+    }
+
+    /// Specifies moves that Cold Clear should play in order before it starts picking moves itself.
+    ///
+    /// If one of the moves can't be played or a different move is played, Cold Clear stops
+    /// following the opener.
+    pub fn force_opener(&self, moves: Vec<FallingPiece>) {
+        self.send.send(BotMsg::ForceOpener(moves)).ok();
+    }
+}
+
+/// Launches bots which share an opening book and a thread pool.
+///
+/// This is useful when running many bots at once, such as when tuning weights, since each bot
+/// would otherwise create its own threads. `Options::threads` still limits how many threads each
+/// bot uses at once.
+pub struct BotFactory {
+    book: Option<Arc<Book>>,
+    pool: Arc<rayon::ThreadPool>,
 }
 
-fn run(
+impl BotFactory {
+    /// Creates a factory with a thread pool of the specified size, or one thread per CPU core if
+    /// `threads` is 0.
+    pub fn new(book: Option<Arc<Book>>, threads: u32) -> Self {
+        BotFactory {
+            book,
+            pool: Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads as usize)
+                    .build()
+                    .unwrap(),
+            ),
+        }
+    }
+
+    /// Launches a bot thread with the specified starting board and options.
+    pub fn launch<E>(&self, board: Board, options: Options, evaluator: E) -> Interface
+    where
+        E: Evaluator + Send + 'static,
+    {
+        Interface::launch_with_pool(
+            board,
+            options,
+            evaluator,
+            self.book.clone(),
+            Some(self.pool.clone()),
+            None,
+        )
+    }
+
+    pub fn book(&self) -> Option<&Arc<Book>> {
+        self.book.as_ref()
+    }
+}
+
+fn run<E>(
     recv: Receiver<BotMsg>,
+    eval_recv: Receiver<Box<dyn Any + Send>>,
     send: Sender<(Move, Info)>,
+    board_send: Sender<Board>,
+    plan_send: Sender<Vec<(FallingPiece, LockResult, bool)>>,
+    candidates_send: Sender<Vec<Candidate>>,
+    death_send: Sender<(DeathReason, Board)>,
+    trace_send: Sender<MoveTrace>,
+    state_send: Sender<Box<dyn Any + Send>>,
+    ranked_send: Sender<Vec<Candidate>>,
+    stats: Arc<SharedStats>,
     mut board: Board,
-    eval: impl Evaluator + 'static,
-    options: Options,
+    eval: E,
+    mut options: Options,
     book: Option<Arc<Book>>,
-) {
-    if options.threads == 0 {
-        panic!("Invalid number of threads: 0");
-    }
-
-    while board.next_queue().next().is_none() {
+    pool: Option<Arc<rayon::ThreadPool>>,
+    saved: Option<SavedBot<E::Value, E::Reward>>,
+) where
+    E: Evaluator + 'static,
+{
+    let mut opener = None;
+    let mut forced_lines = vec![];
+    let mut trace = false;
+    let mut watch_candidates = false;
+    while saved.is_none() && board.next_queue().next().is_none() {
         match recv.recv() {
             Err(_) => return,
             Ok(BotMsg::NewPiece(piece)) => board.add_next_piece(piece),
@@ -132,43 +633,486 @@ fn run(
                 board.b2b_bonus = b2b;
             }
             Ok(BotMsg::SuggestMove(_)) => {}
-            Ok(BotMsg::ForceAnalysisLine(_)) => {}
+            Ok(BotMsg::ExpectGarbageHoles(_)) => {}
+            Ok(BotMsg::OpponentHeight(_)) => {}
+            Ok(BotMsg::ForceAnalysisLine(path)) => forced_lines.push(path),
+            Ok(BotMsg::ClearForcedLines) => forced_lines.clear(),
+            Ok(BotMsg::ForceOpener(moves)) => opener = Some(moves),
             Ok(BotMsg::PlayMove(_)) => {}
+            Ok(BotMsg::RestoreState(snapshot)) => board = snapshot.to_board(),
+            Ok(BotMsg::UpdateOptions(new)) => options = new,
+            Ok(BotMsg::TraceNextMove) => trace = true,
+            Ok(BotMsg::RequestBoard) => {
+                board_send.send(board.clone()).ok();
+            }
+            Ok(BotMsg::RequestPlan) => {
+                plan_send.send(vec![]).ok();
+            }
+            Ok(BotMsg::WatchCandidates) => watch_candidates = true,
+            Ok(BotMsg::RequestState) => {
+                let saved =
+                    SavedBot::<E::Value, E::Reward>::unstarted(board.clone(), options.clone());
+                state_send.send(Box::new(saved)).ok();
+            }
+            Ok(BotMsg::RequestCandidates) => {
+                ranked_send.send(vec![]).ok();
+            }
         }
     }
 
-    let mut bot = ModeSwitchedBot::new(board, options, book.as_deref());
+    let mut bot = match saved {
+        Some(saved) => ModeSwitchedBot::load(saved, book.as_deref()),
+        None => ModeSwitchedBot::new(board, options.clone(), book.as_deref()),
+    };
+    if let Some(moves) = opener {
+        bot.message(BotMsg::ForceOpener(moves));
+    }
+    for path in forced_lines {
+        bot.message(BotMsg::ForceAnalysisLine(path));
+    }
+    if trace {
+        bot.message(BotMsg::TraceNextMove);
+    }
 
-    let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(options.threads as usize)
-        .build()
-        .unwrap();
+    let pool = pool.unwrap_or_else(|| {
+        Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(options.threads as usize)
+                .build()
+                .unwrap(),
+        )
+    });
 
     let (result_send, result_recv) = unbounded();
 
-    let eval = Arc::new(eval);
+    let mut eval = Arc::new(eval);
+    let mut last_candidates = vec![];
+    let mut last_report = Instant::now();
+    let mut last_progress = None;
+    let mut outstanding = 0;
     loop {
+        let (tree_size, depth) = bot.search_size();
+        stats.tree_size.store(tree_size, Ordering::Relaxed);
+        stats.peak_tree_size.fetch_max(tree_size, Ordering::Relaxed);
+        stats.depth.store(depth, Ordering::Relaxed);
+
         let new_tasks = bot.think(&eval, |result| {
             send.send(result).ok();
         });
+        if let Some(trace) = bot.take_trace() {
+            trace_send.send(trace).ok();
+        }
         for task in new_tasks {
+            outstanding += 1;
             let result_send = result_send.clone();
             let eval = eval.clone();
+            let stats = stats.clone();
             pool.spawn_fifo(move || {
-                result_send.send(task.execute(&eval)).ok();
+                let result = task.execute(&eval);
+                stats
+                    .total_nodes
+                    .fetch_add(result.nodes(), Ordering::Relaxed);
+                result_send.send(result).ok();
             });
         }
 
+        // also published whenever the bot is about to wait, so that it is up to date while idle
+        if outstanding == 0
+            || last_progress.map_or(true, |t: Instant| t.elapsed() >= PROGRESS_INTERVAL)
+        {
+            last_progress = Some(Instant::now());
+            *stats.progress.lock().unwrap() = bot.progress(&eval);
+        }
+
         select! {
-            recv(result_recv) -> result => bot.task_complete(result.unwrap()),
+            recv(result_recv) -> result => {
+                outstanding -= 1;
+                bot.task_complete(result.unwrap());
+            }
+            recv(eval_recv) -> new_eval => match new_eval {
+                Ok(new_eval) => {
+                    // evaluators of a different type than the bot was launched with are ignored
+                    if let Ok(new_eval) = new_eval.downcast::<E>() {
+                        eval = Arc::new(*new_eval);
+                    }
+                }
+                Err(_) => break
+            },
             recv(recv) -> msg => match msg {
+                Ok(BotMsg::RequestBoard) => {
+                    board_send.send(bot.board().clone()).ok();
+                }
+                Ok(BotMsg::RequestPlan) => {
+                    plan_send.send(bot.plan()).ok();
+                }
+                Ok(BotMsg::WatchCandidates) => watch_candidates = true,
+                Ok(BotMsg::RequestState) => {
+                    state_send.send(Box::new(bot.save())).ok();
+                }
+                Ok(BotMsg::RequestCandidates) => {
+                    ranked_send.send(bot.candidates()).ok();
+                }
                 Ok(msg) => bot.message(msg),
                 Err(_) => break
             }
         }
 
+        if watch_candidates && last_report.elapsed() >= CANDIDATES_INTERVAL {
+            last_report = Instant::now();
+            let candidates = bot.candidates();
+            if candidates != last_candidates {
+                candidates_send.send(candidates.clone()).ok();
+                last_candidates = candidates;
+            }
+        }
+
         if bot.is_dead() {
+            if let Some(reason) = bot.death_reason(&eval) {
+                death_send.send((reason, bot.board().clone())).ok();
+            }
             break;
         }
     }
+
+    // wait for the workers so that their nodes are counted by the time the thread finishes
+    for _ in 0..outstanding {
+        result_recv.recv().ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluation::Standard;
+    use crate::MoveReason;
+
+    use super::*;
+
+    #[test]
+    fn diff_board_reports_altered_cell() {
+        let mut board = Board::new();
+        for &piece in &[Piece::T, Piece::O, Piece::I] {
+            board.add_next_piece(piece);
+        }
+        let bot = Interface::launch(board.clone(), Options::default(), Standard::default(), None);
+        assert!(bot.diff_board(&board).unwrap().is_empty());
+
+        let mut field = [[false; 10]; 40];
+        field[0][3] = true;
+        board.set_field(field);
+        let diff = bot.diff_board(&board).unwrap();
+        assert_eq!(diff.cells, vec![(3, 0)]);
+        assert!(!diff.queue && !diff.hold && !diff.bag);
+    }
+
+    #[test]
+    fn restore_state_returns_to_snapshot() {
+        let mut board = Board::new();
+        for &piece in &[Piece::T, Piece::O, Piece::I, Piece::L] {
+            board.add_next_piece(piece);
+        }
+        let bot = Interface::launch(board.clone(), Options::default(), Standard::default(), None);
+        let snapshot = bot.snapshot_state().unwrap();
+        assert_eq!(snapshot.to_board().get_field(), board.get_field());
+
+        bot.suggest_next_move(0);
+        let (mv, _) = bot.block_next_move().unwrap();
+        bot.play_next_move(mv.expected_location);
+        let diff = bot.diff_board(&board).unwrap();
+        assert!(!diff.cells.is_empty() && diff.queue);
+
+        bot.restore_state(snapshot);
+        assert!(bot.diff_board(&board).unwrap().is_empty());
+    }
+
+    #[test]
+    fn saved_state_keeps_thinking_after_reload() {
+        let mut board = Board::new();
+        for &piece in &[Piece::T, Piece::O, Piece::I, Piece::L, Piece::J] {
+            board.add_next_piece(piece);
+        }
+        let bot = Interface::launch(board, Options::default(), Standard::default(), None);
+        let grown = |bot: &Interface, size: u32| {
+            let started = Instant::now();
+            while bot.stats().tree_size < size && started.elapsed() < Duration::from_secs(10) {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            bot.stats().tree_size >= size
+        };
+        assert!(grown(&bot, 500));
+        assert!(matches!(
+            bot.save_state::<crate::evaluation::changed::Standard>(),
+            Err(SaveStateError::WrongEvaluator)
+        ));
+        let state = bot.save_state::<Standard>().unwrap();
+        bot.shutdown();
+
+        let bot = Interface::launch_from_state(&state, Standard::default(), None).unwrap();
+        // the saved tree is picked up again, and the search continues from there
+        assert!(grown(&bot, 1));
+        assert!(bot.stats().tree_size >= 500);
+        assert!(grown(&bot, 1000));
+        bot.suggest_next_move(0);
+        assert!(bot.block_next_move().is_some());
+    }
+
+    #[test]
+    fn update_evaluator_rejects_other_evaluator_types() {
+        let mut board = Board::new();
+        for &piece in &[Piece::T, Piece::O, Piece::I, Piece::L, Piece::J] {
+            board.add_next_piece(piece);
+        }
+        let bot = Interface::launch(board, Options::default(), Standard::default(), None);
+        assert!(!bot.update_evaluator(crate::evaluation::changed::Standard::default()));
+        assert!(bot.update_evaluator(Standard::fast_config()));
+        bot.suggest_next_move(0);
+        assert!(bot.block_next_move().is_some());
+    }
+
+    #[test]
+    fn zero_threads_uses_every_core() {
+        let mut board = Board::new();
+        for &piece in &[Piece::T, Piece::O, Piece::I, Piece::L] {
+            board.add_next_piece(piece);
+        }
+        let options = Options {
+            threads: 0,
+            ..Options::default()
+        };
+        let bot = Interface::launch(board, options, Standard::default(), None);
+        let threads = bot.options.lock().unwrap().threads;
+        assert_eq!(threads, num_cpus::get() as u32);
+        assert!(threads >= 1);
+
+        // the bot keeps thinking with the resolved number of threads after options change
+        assert!(bot.update_options(|options| options.min_nodes = 100));
+        bot.suggest_next_move(0);
+        assert!(bot.block_next_move().is_some());
+    }
+
+    #[test]
+    fn poll_status_reports_progress_while_waiting() {
+        let mut board = Board::new();
+        for &piece in &[Piece::T, Piece::O, Piece::I, Piece::L, Piece::J] {
+            board.add_next_piece(piece);
+        }
+        let bot = Interface::launch(board, Options::default(), Standard::default(), None);
+        let started = Instant::now();
+        while bot.stats().tree_size < 100 && started.elapsed() < Duration::from_secs(10) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        // the progress is already there the first time it is asked for
+        assert!(bot.poll_status().is_some());
+
+        let mut reports = vec![];
+        let started = Instant::now();
+        let done = |reports: &[SearchProgress]| {
+            reports.len() >= 2 && reports.last().unwrap().best_so_far.is_some()
+        };
+        while !done(&reports) && started.elapsed() < Duration::from_secs(10) {
+            std::thread::sleep(Duration::from_millis(50));
+            if let Some(progress) = bot.poll_status() {
+                reports.push(progress);
+            }
+        }
+        assert!(done(&reports));
+        assert!(reports.windows(2).all(|w| w[1].nodes > w[0].nodes));
+        // polling doesn't consume or request a move
+        assert!(matches!(bot.poll_next_move(), Err(BotPollState::Waiting)));
+    }
+
+    #[test]
+    fn factory_bots_share_book() {
+        let book = Arc::new(Book::from(opening_book::BookBuilder::new().compile(&[])));
+        let factory = BotFactory::new(Some(book.clone()), 2);
+        assert!(Arc::ptr_eq(factory.book().unwrap(), &book));
+
+        // the bots wait for pieces before starting, each holding on to its handle to the book
+        let bots: Vec<_> = (0..8)
+            .map(|_| factory.launch(Board::new(), Options::default(), Standard::default()))
+            .collect();
+        assert_eq!(Arc::strong_count(&book), 2 + bots.len());
+    }
+
+    #[test]
+    fn candidate_updates_end_with_chosen_move() {
+        let mut board = Board::new();
+        for &piece in &[Piece::T, Piece::O, Piece::I, Piece::L, Piece::J] {
+            board.add_next_piece(piece);
+        }
+        let options = Options {
+            max_nodes: 2000,
+            ..Options::default()
+        };
+        let bot = Interface::launch(board, options, Standard::default(), None);
+        let snapshots = Arc::new(Mutex::new(vec![]));
+        let recorded = snapshots.clone();
+        bot.on_candidates_update(move |candidates| recorded.lock().unwrap().push(candidates));
+        let updates = Arc::new(AtomicU32::new(0));
+        let counted = updates.clone();
+        bot.on_candidates_update(move |_| {
+            counted.fetch_add(1, Ordering::Relaxed);
+        });
+
+        // wait for the search to stop at its node limit, waking the bot regularly so that it
+        // reports the final candidates
+        let started = Instant::now();
+        let mut last_size = None;
+        while started.elapsed() < Duration::from_secs(10) {
+            std::thread::sleep(CANDIDATES_INTERVAL * 2);
+            bot.projected_attack(0);
+            let size = bot.stats().tree_size;
+            if last_size == Some(size) && !snapshots.lock().unwrap().is_empty() {
+                break;
+            }
+            last_size = Some(size);
+        }
+
+        bot.suggest_next_move(0);
+        let (mv, _) = bot.block_next_move().unwrap();
+        {
+            let snapshots = snapshots.lock().unwrap();
+            assert!(snapshots.iter().all(|candidates| !candidates.is_empty()));
+            let best = &snapshots.last().unwrap()[0];
+            assert_eq!(best.mv, mv.expected_location);
+            assert_eq!(best.hold, mv.hold);
+        }
+
+        // both callbacks see every update, which have all been given to them once the bot is shut
+        // down
+        bot.shutdown();
+        let seen = snapshots.lock().unwrap().len();
+        assert_eq!(updates.load(Ordering::Relaxed) as usize, seen);
+    }
+
+    #[test]
+    fn projected_attack_counts_tetris() {
+        let mut field = [[false; 10]; 40];
+        for row in &mut field[..6] {
+            for cell in &mut row[..9] {
+                *cell = true;
+            }
+        }
+        let mut board = Board::new();
+        board.set_field(field);
+        for &piece in &[Piece::I, Piece::T, Piece::O, Piece::S, Piece::Z] {
+            board.add_next_piece(piece);
+        }
+        let options = Options {
+            max_nodes: 2000,
+            ..Options::default()
+        };
+        let bot = Interface::launch(board, options, Standard::default(), None);
+
+        // the plan fills in as the bot thinks
+        let started = Instant::now();
+        while bot.projected_attack(1) != Some(4) && started.elapsed() < Duration::from_secs(10) {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        assert_eq!(bot.projected_attack(0), Some(0));
+        assert_eq!(bot.projected_attack(1), Some(4));
+        assert!(bot.projected_attack(4).unwrap() >= 4);
+    }
+
+    #[test]
+    fn blocked_spawn_reports_block_out() {
+        let mut field = [[false; 10]; 40];
+        for row in &mut field[..22] {
+            for cell in &mut row[1..] {
+                *cell = true;
+            }
+        }
+        let mut board = Board::new();
+        board.set_field(field);
+        for &piece in &[Piece::T, Piece::O] {
+            board.add_next_piece(piece);
+        }
+        let bot = Interface::launch(board.clone(), Options::default(), Standard::default(), None);
+        bot.suggest_next_move(0);
+        assert!(bot.block_next_move().is_none());
+
+        let (reason, dead_board) = bot.death_reason().unwrap();
+        assert_eq!(reason, DeathReason::BlockOut);
+        assert_eq!(dead_board.get_field(), field);
+        assert!(matches!(
+            bot.save_state::<Standard>(),
+            Err(SaveStateError::Dead)
+        ));
+    }
+
+    #[test]
+    fn move_waits_for_min_nodes() {
+        let mut board = Board::new();
+        for &piece in &[Piece::T, Piece::O, Piece::I, Piece::L, Piece::J] {
+            board.add_next_piece(piece);
+        }
+        let options = Options {
+            min_nodes: 5000,
+            ..Options::default()
+        };
+        let bot = Interface::launch(board, options, Standard::default(), None);
+        // requested right away, before the bot has had any time to think
+        bot.suggest_next_move(0);
+        match bot.block_next_move() {
+            Some((_, Info::Normal(info))) => assert!(info.nodes >= 5000, "{}", info.nodes),
+            other => panic!("expected a normal move, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn lowered_budget_returns_move_sooner() {
+        let mut board = Board::new();
+        for &piece in &[Piece::T, Piece::O, Piece::I, Piece::L, Piece::J] {
+            board.add_next_piece(piece);
+        }
+        // far more thinking than the test could wait for
+        let options = Options {
+            min_nodes: 10_000_000,
+            max_nodes: 10_000_000,
+            ..Options::default()
+        };
+        let bot = Interface::launch(board, options, Standard::default(), None);
+        bot.suggest_next_move(0);
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(matches!(bot.poll_next_move(), Err(BotPollState::Waiting)));
+
+        assert!(!bot.update_options(|options| options.use_hold = false));
+        assert!(bot.update_options(|options| {
+            options.min_nodes = 0;
+            options.max_nodes = 2000;
+        }));
+        let started = Instant::now();
+        let (_, info) = bot.block_next_move().unwrap();
+        assert!(started.elapsed() < Duration::from_secs(10));
+        match info {
+            Info::Normal(info) => assert!(info.nodes < 10_000_000),
+            other => panic!("expected a normal move, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn trace_matches_provided_move() {
+        let mut board = Board::new();
+        for &piece in &[Piece::T, Piece::O, Piece::I, Piece::L, Piece::J] {
+            board.add_next_piece(piece);
+        }
+        let options = Options {
+            min_nodes: 1000,
+            ..Options::default()
+        };
+        let bot = Interface::launch(board, options, Standard::default(), None);
+        bot.trace_next_move();
+        bot.suggest_next_move(0);
+        let (mv, _) = bot.block_next_move().unwrap();
+        let trace = bot.block_move_trace().unwrap();
+
+        assert_eq!(trace.chosen.expected_location, mv.expected_location);
+        assert_eq!(trace.chosen.hold, mv.hold);
+        assert_eq!(trace.reason, MoveReason::Search);
+        assert!(!trace.candidates.is_empty());
+        assert!(trace
+            .candidates
+            .iter()
+            .any(|c| c.mv == mv.expected_location && c.advantage.is_some()));
+        assert!(trace.nodes_per_generation.iter().sum::<u32>() > 1000);
+    }
 }
@@ -14,6 +14,11 @@ pub struct Standard {
     pub top_half: i32,
     pub top_quarter: i32,
     pub jeopardy: i32,
+    /// Multiplied by the lines of garbage still incoming after the placement and by how far
+    /// above the middle of the board the stack will be once that garbage arrives. If
+    /// `timed_jeopardy` is set, this is also scaled by the frames the placement takes, including
+    /// the spawn delay but not the line clear delay.
+    pub incoming_jeopardy: i32,
     pub cavity_cells: i32,
     pub cavity_cells_sq: i32,
     pub overhang_cells: i32,
@@ -24,6 +29,10 @@ pub struct Standard {
     pub well_depth: i32,
     pub max_well_depth: i32,
     pub well_column: [i32; 10],
+    pub sz_dependency: i32,
+    pub dig_row_transitions: i32,
+    pub surface_spikes: i32,
+    pub stack_balance: i32,
 
     pub b2b_clear: i32,
     pub clear1: i32,
@@ -39,10 +48,24 @@ pub struct Standard {
     pub combo_garbage: i32,
     pub move_time: i32,
     pub wasted_t: i32,
+    pub filled_tslot: i32,
+    pub held_t: i32,
 
     pub use_bag: bool,
     pub timed_jeopardy: bool,
     pub stack_pc_damage: bool,
+    pub lookahead_defense: bool,
+    pub flood_fill_holes: bool,
+    /// Whether combos longer than the combo table are valued by continuing the table's last step
+    /// instead of by its last entry.
+    pub combo_extrapolate: bool,
+    /// The garbage sent by each combo, starting from the first line clear. `None` means the
+    /// standard table is used.
+    pub combo_table: Option<Vec<u32>>,
+    /// The row above which the game considers the board lost. The height-based weights are
+    /// measured relative to this instead of assuming a 20 row field, and placements entirely at
+    /// or above it are treated as lock outs.
+    pub vanish_zone_top: i32,
     pub sub_name: Option<String>,
 }
 
@@ -57,6 +80,7 @@ impl Default for Standard {
             top_half: -150,
             top_quarter: -511,
             jeopardy: -11,
+            incoming_jeopardy: -4,
             cavity_cells: -173,
             cavity_cells_sq: -3,
             overhang_cells: -34,
@@ -67,9 +91,15 @@ impl Default for Standard {
             well_depth: 57,
             max_well_depth: 17,
             well_column: [20, 23, 20, 50, 59, 21, 59, 10, -10, 24],
+            sz_dependency: 0,
+            dig_row_transitions: 0,
+            surface_spikes: 0,
+            stack_balance: 0,
 
             move_time: -3,
             wasted_t: -152,
+            filled_tslot: 0,
+            held_t: 0,
             b2b_clear: 104,
             clear1: -143,
             clear2: -100,
@@ -86,6 +116,11 @@ impl Default for Standard {
             use_bag: true,
             timed_jeopardy: true,
             stack_pc_damage: false,
+            lookahead_defense: false,
+            flood_fill_holes: false,
+            combo_extrapolate: false,
+            combo_table: None,
+            vanish_zone_top: 20,
             sub_name: None,
         }
     }
@@ -102,6 +137,7 @@ impl Standard {
             top_half: -126,
             top_quarter: -493,
             jeopardy: -11,
+            incoming_jeopardy: -4,
             cavity_cells: -176,
             cavity_cells_sq: -6,
             overhang_cells: -47,
@@ -112,6 +148,10 @@ impl Standard {
             well_depth: 158,
             max_well_depth: -2,
             well_column: [31, 16, -41, 37, 49, 30, 56, 48, -27, 22],
+            sz_dependency: 0,
+            dig_row_transitions: 0,
+            surface_spikes: 0,
+            stack_balance: 0,
             b2b_clear: 74,
             clear1: -122,
             clear2: -174,
@@ -126,14 +166,43 @@ impl Standard {
             combo_garbage: 272,
             move_time: -1,
             wasted_t: -147,
+            filled_tslot: 0,
+            held_t: 0,
             use_bag: true,
             timed_jeopardy: false,
             stack_pc_damage: false,
+            lookahead_defense: false,
+            flood_fill_holes: false,
+            combo_extrapolate: false,
+            combo_table: None,
+            vanish_zone_top: 20,
             sub_name: None,
         }
     }
 }
 
+impl Standard {
+    fn combo_value(&self, combo: u32) -> i32 {
+        let table = self
+            .combo_table
+            .as_deref()
+            .unwrap_or(&libtetris::COMBO_GARBAGE);
+        let last = match table.len() {
+            0 => return 0,
+            len => len - 1,
+        };
+        let combo = combo as usize;
+        if combo <= last {
+            table[combo] as i32
+        } else if self.combo_extrapolate && last > 0 {
+            let step = table[last] as i32 - table[last - 1] as i32;
+            table[last] as i32 + step * (combo - last) as i32
+        } else {
+            table[last] as i32
+        }
+    }
+}
+
 impl Evaluator for Standard {
     type Value = Value;
     type Reward = Reward;
@@ -149,19 +218,61 @@ impl Evaluator for Standard {
 
     fn pick_move(
         &self,
-        candidates: Vec<MoveCandidate<Value>>,
+        mut candidates: Vec<MoveCandidate<Value>>,
         incoming: u32,
+        garbage_holes: &[u32; 10],
+        opponent_height: u32,
     ) -> MoveCandidate<Value> {
+        let safe = |board: &Board, rise: i32| {
+            board.column_heights()[3..6]
+                .iter()
+                .all(|h| rise + h <= self.vanish_zone_top)
+                && hole_side_height(board, garbage_holes)
+                    .map_or(true, |h| rise + h <= self.vanish_zone_top)
+        };
+
+        // If we can safely send enough garbage to top out the opponent, do it.
+        if opponent_height != 0 {
+            let lethal = candidates.iter().position(|mv| {
+                (opponent_height + mv.lock.garbage_sent) as i32 >= self.vanish_zone_top
+                    && safe(&mv.board, incoming as i32 - mv.lock.garbage_sent as i32)
+            });
+            if let Some(i) = lethal {
+                return candidates.swap_remove(i);
+            }
+        }
+
+        // When garbage is coming, favor surfaces that will be easier to dig through.
+        if incoming != 0 && self.dig_row_transitions != 0 {
+            candidates.sort_by_cached_key(|mv| {
+                std::cmp::Reverse(
+                    mv.evaluation.value + self.dig_row_transitions * row_transitions(&mv.board),
+                )
+            });
+        }
+
         let mut backup = None;
+        let mut lookahead = None;
         for mv in candidates.into_iter() {
-            if incoming == 0
-                || mv.board.column_heights()[3..6]
-                    .iter()
-                    .all(|h| incoming as i32 - mv.lock.garbage_sent as i32 + h <= 20)
-            {
+            let rise = incoming as i32 - mv.lock.garbage_sent as i32;
+            if incoming == 0 || safe(&mv.board, rise) {
                 return mv;
             }
 
+            // If this move can be followed by one which makes us safe (e.g. holding to save an I
+            // piece for next turn), prefer it over simply minimizing risk.
+            if self.lookahead_defense && lookahead.is_none() {
+                let safe_followup = mv.followups.iter().any(|&followup| {
+                    let mut board = mv.board.clone();
+                    let lock = board.lock_piece(followup);
+                    safe(&board, rise - lock.garbage_sent as i32)
+                });
+                if safe_followup {
+                    lookahead = Some(mv);
+                    continue;
+                }
+            }
+
             match backup {
                 None => backup = Some(mv),
                 Some(c) if c.evaluation.spike < mv.evaluation.spike => backup = Some(mv),
@@ -169,7 +280,19 @@ impl Evaluator for Standard {
             }
         }
 
-        return backup.unwrap();
+        return lookahead.or(backup).unwrap();
+    }
+
+    fn advantage_in_lines(&self, value: &Value) -> Option<f32> {
+        Some(super::standard::advantage_in_lines(value.value))
+    }
+
+    fn raw_evaluation(&self, value: &Value) -> Option<(i32, i32)> {
+        Some((value.value, value.spike))
+    }
+
+    fn vanish_zone_top(&self) -> i32 {
+        self.vanish_zone_top
     }
 
     fn evaluate(
@@ -177,7 +300,9 @@ impl Evaluator for Standard {
         lock: &LockResult,
         board: &Board,
         move_time: u32,
+        incoming: u32,
         placed: Piece,
+        parent: &Board,
     ) -> (Value, Reward) {
         let mut transient_eval = 0;
         let mut acc_eval = 0;
@@ -190,8 +315,7 @@ impl Evaluator for Standard {
                 acc_eval += self.b2b_clear;
             }
             if let Some(combo) = lock.combo {
-                let combo = combo.min(11) as usize;
-                acc_eval += self.combo_garbage * libtetris::COMBO_GARBAGE[combo] as i32;
+                acc_eval += self.combo_garbage * self.combo_value(combo);
             }
             match lock.placement_kind {
                 PlacementKind::Clear1 => {
@@ -232,6 +356,13 @@ impl Evaluator for Standard {
             }
         }
 
+        // garbage arrives while the next piece is placed, so the line clear delay doesn't matter
+        let placement_time = if self.timed_jeopardy {
+            move_time as i32
+        } else {
+            10
+        };
+
         // magic approximation of line clear delay
         let move_time = if lock.placement_kind.is_clear() {
             move_time as i32 + 40
@@ -245,14 +376,24 @@ impl Evaluator for Standard {
         }
 
         let highest_point = *board.column_heights().iter().max().unwrap() as i32;
-        transient_eval += self.top_quarter * (highest_point - 15).max(0);
-        transient_eval += self.top_half * (highest_point - 10).max(0);
+        let half = self.vanish_zone_top / 2;
+        let quarter = self.vanish_zone_top * 3 / 4;
+        transient_eval += self.top_quarter * (highest_point - quarter).max(0);
+        transient_eval += self.top_half * (highest_point - half).max(0);
 
         acc_eval += self.jeopardy
-            * (highest_point - 10).max(0)
+            * (highest_point - half).max(0)
             * if self.timed_jeopardy { move_time } else { 10 }
             / 10;
 
+        // garbage sent by this placement cancels out incoming garbage
+        let pending = incoming.saturating_sub(lock.garbage_sent) as i32;
+        acc_eval += self.incoming_jeopardy
+            * pending
+            * (highest_point + pending - half).max(0)
+            * placement_time
+            / 10;
+
         let ts = if self.use_bag {
             board.next_bag().contains(Piece::T) as usize
                 + (board.next_bag().len() <= 3) as usize
@@ -261,27 +402,21 @@ impl Evaluator for Standard {
             1 + (board.hold_piece == Some(Piece::T)) as usize
         };
 
+        if placed != Piece::T && ts != 0 && self.filled_tslot != 0 {
+            if let Some(slot) = find_tslot(parent) {
+                if filled_cells(board, lock, &slot) {
+                    acc_eval += self.filled_tslot;
+                }
+            }
+        }
+
+        if board.hold_piece == Some(Piece::T) && self.held_t != 0 && find_tslot(board).is_some() {
+            transient_eval += self.held_t;
+        }
+
         let mut board = board.clone();
         for _ in 0..ts {
-            let cutout_location = sky_tslot_left(&board)
-                .or_else(|| sky_tslot_right(&board))
-                .or_else(|| {
-                    let tst = tst_twist_left(&board).or_else(|| tst_twist_right(&board))?;
-                    cave_tslot(&board, tst).or_else(|| {
-                        let corners = board.occupied(tst.x - 1, tst.y - 1) as usize
-                            + board.occupied(tst.x + 1, tst.y - 1) as usize
-                            + board.occupied(tst.x - 1, tst.y + 1) as usize
-                            + board.occupied(tst.x + 1, tst.y + 1) as usize;
-                        if corners >= 3 && board.on_stack(&tst) {
-                            Some(tst)
-                        } else {
-                            None
-                        }
-                    })
-                })
-                .or_else(|| fin_left(&board))
-                .or_else(|| fin_right(&board));
-            let result = match cutout_location {
+            let result = match find_tslot(&board) {
                 Some(location) => cutout_tslot(board.clone(), location),
                 None => break,
             };
@@ -318,13 +453,20 @@ impl Evaluator for Standard {
             transient_eval += self.well_column[well];
         }
 
+        if self.sz_dependency != 0 {
+            transient_eval += self.sz_dependency * sz_dependency(&board);
+        }
+
+        if self.surface_spikes != 0 {
+            transient_eval += self.surface_spikes * surface_spikes(&board);
+        }
+
+        if self.stack_balance != 0 {
+            transient_eval += self.stack_balance * stack_imbalance(&board);
+        }
+
         if self.row_transitions != 0 {
-            transient_eval += self.row_transitions
-                * (0..40)
-                    .map(|y| *board.get_row(y))
-                    .map(|r| (r | 0b1_00000_00000) ^ (1 | r << 1))
-                    .map(|d| d.count_ones() as i32)
-                    .sum::<i32>();
+            transient_eval += self.row_transitions * row_transitions(&board);
         }
 
         if self.bumpiness | self.bumpiness_sq != 0 {
@@ -336,7 +478,11 @@ impl Evaluator for Standard {
         if self.cavity_cells | self.cavity_cells_sq | self.overhang_cells | self.overhang_cells_sq
             != 0
         {
-            let (cavity_cells, overhang_cells) = cavities_and_overhangs(&board);
+            let (cavity_cells, overhang_cells) = if self.flood_fill_holes {
+                flood_fill_cavities_and_overhangs(&board)
+            } else {
+                cavities_and_overhangs(&board)
+            };
             transient_eval += self.cavity_cells * cavity_cells;
             transient_eval += self.cavity_cells_sq * cavity_cells * cavity_cells;
             transient_eval += self.overhang_cells * overhang_cells;
@@ -366,6 +512,116 @@ impl Evaluator for Standard {
     }
 }
 
+/// Finds the T piece placement of the most apparent T-slot on the board, if any.
+fn find_tslot(board: &Board) -> Option<FallingPiece> {
+    sky_tslot_left(board)
+        .or_else(|| sky_tslot_right(board))
+        .or_else(|| {
+            let tst = tst_twist_left(board).or_else(|| tst_twist_right(board))?;
+            cave_tslot(board, tst).or_else(|| {
+                let corners = board.occupied(tst.x - 1, tst.y - 1) as usize
+                    + board.occupied(tst.x + 1, tst.y - 1) as usize
+                    + board.occupied(tst.x - 1, tst.y + 1) as usize
+                    + board.occupied(tst.x + 1, tst.y + 1) as usize;
+                if corners >= 3 && board.on_stack(&tst) {
+                    Some(tst)
+                } else {
+                    None
+                }
+            })
+        })
+        .or_else(|| fin_left(board))
+        .or_else(|| fin_right(board))
+}
+
+/// Determines whether the placement that produced `board` filled any of the cells of `piece`,
+/// where `piece` is located on the board before the placement.
+fn filled_cells(board: &Board, lock: &LockResult, piece: &FallingPiece) -> bool {
+    piece.cells().iter().any(|&(x, y)| {
+        if lock.cleared_lines.contains(&y) {
+            return true;
+        }
+        let shift = lock.cleared_lines.iter().filter(|&&l| l < y).count() as i32;
+        board.occupied(x, y - shift)
+    })
+}
+
+/// Finds the average height of the columns the incoming garbage holes are expected to be in,
+/// weighted by how likely the hole is to be in each column.
+///
+/// Returns `None` if nothing is known about where the holes will be.
+fn hole_side_height(board: &Board, garbage_holes: &[u32; 10]) -> Option<i32> {
+    let total: u32 = garbage_holes.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let weighted: u32 = garbage_holes
+        .iter()
+        .zip(board.column_heights().iter())
+        .map(|(&w, &h)| w * h as u32)
+        .sum();
+    Some((weighted / total) as i32)
+}
+
+/// Counts the filled/empty boundaries along each row, treating the walls as filled.
+fn row_transitions(board: &Board) -> i32 {
+    (0..40)
+        .map(|y| *board.get_row(y))
+        .map(|r| (r | 0b1_00000_00000) ^ (1 | r << 1))
+        .map(|d| d.count_ones() as i32)
+        .sum()
+}
+
+/// Evaluates how awkward the surface is for S and Z pieces.
+///
+/// Returns the number of S and Z pieces which can't be placed on the surface without creating a
+/// hole, so 0 if both can be placed cleanly and 2 if neither can.
+fn sz_dependency(board: &Board) -> i32 {
+    let h = board.column_heights();
+    let mut s = false;
+    let mut z = false;
+    for x in 0..9 {
+        // vertical placements need a step of one cell
+        s |= h[x] == h[x + 1] + 1;
+        z |= h[x] + 1 == h[x + 1];
+        // flat placements need two level columns next to a column one cell higher or lower
+        if x < 8 {
+            s |= h[x] == h[x + 1] && h[x + 1] + 1 == h[x + 2];
+            z |= h[x] == h[x + 1] + 1 && h[x + 1] == h[x + 2];
+        }
+    }
+    !s as i32 + !z as i32
+}
+
+/// Sums how far each lone single-column pillar rises above the lower of its neighbors, counting
+/// only pillars at least two cells tall. Bumpiness can't tell these apart from ordinary steps.
+fn surface_spikes(board: &Board) -> i32 {
+    let h = board.column_heights();
+    let mut spikes = 0;
+    for x in 1..9 {
+        let rise = (h[x] - h[x - 1]).min(h[x] - h[x + 1]);
+        if rise >= 2 {
+            spikes += rise;
+        }
+    }
+    spikes
+}
+
+/// Finds how far the stack's center of mass is from the middle of the board, in half columns.
+fn stack_imbalance(board: &Board) -> i32 {
+    let h = board.column_heights();
+    let total: i32 = h.iter().sum();
+    if total == 0 {
+        return 0;
+    }
+    let moment: i32 = h
+        .iter()
+        .enumerate()
+        .map(|(x, &h)| h * (2 * x as i32 - 9))
+        .sum();
+    (moment / total).abs()
+}
+
 /// Evaluates the bumpiness of the playfield.
 ///
 /// The first returned value is the total amount of height change outside of an apparent well. The
@@ -424,6 +680,47 @@ fn cavities_and_overhangs(board: &Board) -> (i32, i32) {
     (cavities, overhangs)
 }
 
+/// Evaluates the holes in the playfield exactly.
+///
+/// Like `cavities_and_overhangs`, but empty cells below the top of their column are overhangs if
+/// they are connected to the empty space above the stack and cavities otherwise. This is slower,
+/// but handles complex overhangs correctly.
+fn flood_fill_cavities_and_overhangs(board: &Board) -> (i32, i32) {
+    let top = *board.column_heights().iter().max().unwrap();
+    let mut reachable = [[false; 10]; 41];
+    let mut stack = vec![];
+    for x in 0..10 {
+        reachable[top as usize][x] = true;
+        stack.push((x as i32, top));
+    }
+    while let Some((x, y)) = stack.pop() {
+        for &(nx, ny) in &[(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+            if ny > top || board.occupied(nx, ny) || reachable[ny as usize][nx as usize] {
+                continue;
+            }
+            reachable[ny as usize][nx as usize] = true;
+            stack.push((nx, ny));
+        }
+    }
+
+    let mut cavities = 0;
+    let mut overhangs = 0;
+    for y in 0..top {
+        for x in 0..10 {
+            if board.occupied(x as i32, y) || y >= board.column_heights()[x] {
+                continue;
+            }
+            if reachable[y as usize][x] {
+                overhangs += 1;
+            } else {
+                cavities += 1;
+            }
+        }
+    }
+
+    (cavities, overhangs)
+}
+
 /// Evaluates how covered holes in the playfield are.
 ///
 /// The first returned value is the number of filled cells cover the topmost hole in the columns.
@@ -712,6 +1009,36 @@ impl std::ops::Add<Reward> for Value {
     }
 }
 
+impl std::ops::Add for Reward {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Reward {
+            value: self.value + rhs.value,
+            attack: self.attack + rhs.attack,
+        }
+    }
+}
+
+impl std::ops::Div<usize> for Reward {
+    type Output = Self;
+    fn div(self, rhs: usize) -> Self {
+        Reward {
+            value: self.value / rhs as i32,
+            attack: self.attack / rhs as i32,
+        }
+    }
+}
+
+impl std::ops::Mul<usize> for Reward {
+    type Output = Self;
+    fn mul(self, rhs: usize) -> Self {
+        Reward {
+            value: self.value * rhs as i32,
+            attack: self.attack * rhs as i32,
+        }
+    }
+}
+
 impl std::ops::Div<usize> for Value {
     type Output = Self;
     fn div(self, rhs: usize) -> Self {
@@ -740,9 +1067,21 @@ impl Evaluation<Reward> for Value {
         }
     }
 
-    fn weight(self, min: &Value, rank: usize) -> i64 {
+    fn weight(self, min: &Value, rank: usize, exploration: f32) -> i64 {
         let e = (self.value - min.value) as i64 + 10;
-        e * e / (rank * rank + 1) as i64
+        if exploration == 1.0 {
+            // the baseline weights, computed exactly
+            return e * e / (rank * rank + 1) as i64;
+        }
+        let rank_penalty = (rank * rank) as f32 / exploration + 1.0;
+        // above 1, differences in evaluation matter less too, so that very high values explore
+        // siblings almost evenly
+        let value_weight = if exploration > 1.0 {
+            (e as f32 / 10.0).powf(2.0 / exploration) * 100.0
+        } else {
+            (e * e) as f32
+        };
+        (value_weight / rank_penalty) as i64
     }
 
     fn improve(&mut self, new_result: Self) {
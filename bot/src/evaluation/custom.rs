@@ -0,0 +1,127 @@
+use libtetris::*;
+
+use super::standard::{Reward, Value};
+use super::*;
+
+type Feature = Box<dyn Fn(&Board, &LockResult) -> i32 + Send + Sync>;
+
+/// Extends `Standard` with extra board features provided at runtime.
+///
+/// Each feature is a function of the board after a placement and the result of that placement,
+/// and its result is multiplied by the feature's weight and added to the evaluation. This makes
+/// it possible to try out new features without changing `Standard`.
+pub struct CustomEvaluator {
+    pub base: Standard,
+    features: Vec<(Feature, i32)>,
+}
+
+impl CustomEvaluator {
+    pub fn new(base: Standard) -> Self {
+        CustomEvaluator {
+            base,
+            features: vec![],
+        }
+    }
+
+    pub fn add_feature(
+        &mut self,
+        weight: i32,
+        feature: impl Fn(&Board, &LockResult) -> i32 + Send + Sync + 'static,
+    ) {
+        self.features.push((Box::new(feature), weight));
+    }
+}
+
+impl Evaluator for CustomEvaluator {
+    type Value = Value;
+    type Reward = Reward;
+
+    fn name(&self) -> String {
+        format!(
+            "Custom ({} features)\n{}",
+            self.features.len(),
+            self.base.name()
+        )
+    }
+
+    fn pick_move(
+        &self,
+        candidates: Vec<MoveCandidate<Value>>,
+        incoming: u32,
+        garbage_holes: &[u32; 10],
+        opponent_height: u32,
+    ) -> MoveCandidate<Value> {
+        self.base
+            .pick_move(candidates, incoming, garbage_holes, opponent_height)
+    }
+
+    fn advantage_in_lines(&self, value: &Value) -> Option<f32> {
+        self.base.advantage_in_lines(value)
+    }
+
+    fn raw_evaluation(&self, value: &Value) -> Option<(i32, i32)> {
+        self.base.raw_evaluation(value)
+    }
+
+    fn evaluate(
+        &self,
+        lock: &LockResult,
+        board: &Board,
+        move_time: u32,
+        incoming: u32,
+        placed: Piece,
+        parent: &Board,
+    ) -> (Value, Reward) {
+        let (value, reward) = self
+            .base
+            .evaluate(lock, board, move_time, incoming, placed, parent);
+        let custom = self
+            .features
+            .iter()
+            .map(|(feature, weight)| weight * feature(board, lock))
+            .sum();
+        (value.add_transient(custom), reward)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The column heights after the best placement of the next piece according to `eval`.
+    fn best_heights(eval: &impl Evaluator<Value = Value, Reward = Reward>) -> [i32; 10] {
+        let mut board = Board::new();
+        board.add_next_piece(Piece::O);
+        let piece = board.advance_queue().unwrap();
+        let spawned = SpawnRule::Row19Or20.spawn(piece, &board).unwrap();
+        find_moves(
+            &board,
+            spawned,
+            MovementMode::ZeroG,
+            false,
+            KickTable::default(),
+            SpinDetectionMode::default(),
+        )
+        .into_iter()
+        .map(|p| {
+            let mut result = board.clone();
+            let lock = result.lock_piece(p.location);
+            let (value, reward) = eval.evaluate(&lock, &result, 0, 0, piece, &board);
+            let total = eval.raw_evaluation(&(value + reward)).unwrap().0;
+            (total, *result.column_heights())
+        })
+        .max_by_key(|&(total, _)| total)
+        .unwrap()
+        .1
+    }
+
+    #[test]
+    fn custom_feature_shifts_placement() {
+        let base = Standard::default();
+        assert_eq!(best_heights(&base)[0], 0);
+
+        let mut eval = CustomEvaluator::new(base);
+        eval.add_feature(1000, |board, _| board.column_heights()[0]);
+        assert_eq!(best_heights(&eval)[0], 2);
+    }
+}
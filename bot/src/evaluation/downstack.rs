@@ -0,0 +1,208 @@
+use libtetris::*;
+use serde::{Deserialize, Serialize};
+
+use super::standard::{Reward, Value};
+use super::*;
+
+/// Digs through garbage and keeps the stack low instead of building up attack, for cheese races
+/// and survival.
+///
+/// Boards are evaluated using `base`, and then `garbage_clear` is added for each garbage row
+/// cleared and `holes` for each empty cell that has a filled cell somewhere above it. Garbage
+/// rows are the rows at the bottom of the board with exactly one empty cell.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Downstack {
+    pub base: Standard,
+    pub garbage_clear: i32,
+    pub holes: i32,
+}
+
+impl Default for Downstack {
+    fn default() -> Self {
+        Downstack {
+            base: Standard {
+                back_to_back: 0,
+                height: -60,
+                tslot: [0; 4],
+                well_depth: 0,
+                max_well_depth: 0,
+                well_column: [0; 10],
+                b2b_clear: 0,
+                clear1: 0,
+                clear2: 0,
+                clear3: 0,
+                clear4: 0,
+                tspin1: 0,
+                tspin2: 0,
+                tspin3: 0,
+                mini_tspin1: 0,
+                mini_tspin2: 0,
+                combo_garbage: 0,
+                wasted_t: 0,
+                ..Standard::default()
+            },
+            garbage_clear: 250,
+            holes: -100,
+        }
+    }
+}
+
+impl Evaluator for Downstack {
+    type Value = Value;
+    type Reward = Reward;
+
+    fn name(&self) -> String {
+        format!("Downstack\n{}", self.base.name())
+    }
+
+    fn pick_move(
+        &self,
+        candidates: Vec<MoveCandidate<Value>>,
+        incoming: u32,
+        garbage_holes: &[u32; 10],
+        opponent_height: u32,
+    ) -> MoveCandidate<Value> {
+        self.base
+            .pick_move(candidates, incoming, garbage_holes, opponent_height)
+    }
+
+    fn advantage_in_lines(&self, value: &Value) -> Option<f32> {
+        self.base.advantage_in_lines(value)
+    }
+
+    fn raw_evaluation(&self, value: &Value) -> Option<(i32, i32)> {
+        self.base.raw_evaluation(value)
+    }
+
+    fn evaluate(
+        &self,
+        lock: &LockResult,
+        board: &Board,
+        move_time: u32,
+        incoming: u32,
+        placed: Piece,
+        parent: &Board,
+    ) -> (Value, Reward) {
+        let (value, reward) = self
+            .base
+            .evaluate(lock, board, move_time, incoming, placed, parent);
+
+        // cleared lines are numbered by where they were before clearing, which is the same as
+        // in the parent board
+        let garbage_rows = garbage_rows(parent);
+        let garbage_cleared = lock
+            .cleared_lines
+            .iter()
+            .filter(|&&y| y < garbage_rows)
+            .count() as i32;
+
+        (
+            value.add_transient(self.holes * holes(board)),
+            reward.add_value(self.garbage_clear * garbage_cleared),
+        )
+    }
+}
+
+/// Counts the rows at the bottom of the board with exactly one empty cell.
+fn garbage_rows(board: &Board) -> i32 {
+    (0..40)
+        .take_while(|&y| {
+            let row = board.get_row(y);
+            (0..10).filter(|&x| !row.get(x)).count() == 1
+        })
+        .count() as i32
+}
+
+fn holes(board: &Board) -> i32 {
+    let heights = board.column_heights();
+    (0..10)
+        .map(|x| {
+            (0..heights[x])
+                .filter(|&y| !board.occupied(x as i32, y))
+                .count() as i32
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use enumset::EnumSet;
+    use rand::prelude::*;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::{Options, SyncBot};
+
+    fn cheese_row(rng: &mut impl Rng) -> [bool; 10] {
+        let mut row = [true; 10];
+        row[rng.gen_range(0, 10)] = false;
+        row
+    }
+
+    /// Plays from a board with a few rows of cheese, adding another row of cheese after every
+    /// `interval` pieces, and counts how many pieces are placed before the bot tops out or
+    /// `limit` pieces have been placed.
+    fn pieces_survived(eval: impl Evaluator, seed: u64, interval: usize, limit: usize) -> usize {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut field = [[false; 10]; 40];
+        for row in &mut field[..8] {
+            *row = cheese_row(&mut rng);
+        }
+        let mut board = Board::new();
+        board.set_field(field);
+        let mut bag = vec![];
+        let mut next_piece = |rng: &mut StdRng| {
+            if bag.is_empty() {
+                bag = EnumSet::<Piece>::all().iter().collect();
+                bag.shuffle(rng);
+            }
+            bag.pop().unwrap()
+        };
+        for _ in 0..6 {
+            board.add_next_piece(next_piece(&mut rng));
+        }
+
+        let options = Options {
+            seed: Some(0),
+            ..Options::default()
+        };
+        let mut bot = SyncBot::new(board, options, eval, None);
+        for placed in 0..limit {
+            let (mv, _) = match bot.think_until(300) {
+                Some(mv) => mv,
+                None => return placed,
+            };
+            bot.play_next_move(mv.expected_location);
+            bot.add_next_piece(next_piece(&mut rng));
+
+            if placed % interval == interval - 1 {
+                let board = bot.board();
+                let mut field = board.get_field();
+                if field[39].iter().any(|&c| c) {
+                    return placed + 1;
+                }
+                field.copy_within(..39, 1);
+                field[0] = cheese_row(&mut rng);
+                let (b2b, combo) = (board.b2b_bonus, board.combo);
+                bot.reset(field, b2b, combo);
+            }
+            if bot.board().column_heights().iter().any(|&h| h > 20) {
+                return placed + 1;
+            }
+        }
+        limit
+    }
+
+    #[test]
+    fn outlasts_standard_on_cheese() {
+        // individual games are noisy, so several are played with each evaluator
+        let standard: usize = (1..=3)
+            .map(|seed| pieces_survived(Standard::default(), seed, 4, 150))
+            .sum();
+        let downstack: usize = (1..=3)
+            .map(|seed| pieces_survived(Downstack::default(), seed, 4, 150))
+            .sum();
+        assert!(downstack > standard);
+    }
+}
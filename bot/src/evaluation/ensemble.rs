@@ -0,0 +1,159 @@
+use libtetris::*;
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// Combines several evaluators by averaging their evaluations.
+///
+/// This reduces the effect of the quirks of any single set of weights. Moves are picked using the
+/// first member's `pick_move`, so the first member should be the one with the preferred defensive
+/// behavior.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Ensemble<E> {
+    pub members: Vec<E>,
+}
+
+impl<E: Evaluator> Evaluator for Ensemble<E>
+where
+    E::Reward:
+        Default + std::ops::Add<Output = E::Reward> + std::ops::Div<usize, Output = E::Reward>,
+{
+    type Value = E::Value;
+    type Reward = E::Reward;
+
+    fn name(&self) -> String {
+        let mut info = "Ensemble".to_owned();
+        for member in &self.members {
+            info.push('\n');
+            info.push_str(&member.name());
+        }
+        info
+    }
+
+    fn pick_move(
+        &self,
+        candidates: Vec<MoveCandidate<E::Value>>,
+        incoming: u32,
+        garbage_holes: &[u32; 10],
+        opponent_height: u32,
+    ) -> MoveCandidate<E::Value> {
+        self.members[0].pick_move(candidates, incoming, garbage_holes, opponent_height)
+    }
+
+    fn advantage_in_lines(&self, value: &E::Value) -> Option<f32> {
+        self.members[0].advantage_in_lines(value)
+    }
+
+    fn raw_evaluation(&self, value: &E::Value) -> Option<(i32, i32)> {
+        self.members[0].raw_evaluation(value)
+    }
+
+    fn evaluate(
+        &self,
+        lock: &LockResult,
+        board: &Board,
+        move_time: u32,
+        incoming: u32,
+        placed: Piece,
+        parent: &Board,
+    ) -> (E::Value, E::Reward) {
+        let mut value = E::Value::default();
+        let mut reward = E::Reward::default();
+        for member in &self.members {
+            let (v, r) = member.evaluate(lock, board, move_time, incoming, placed, parent);
+            value = value + v;
+            reward = reward + r;
+        }
+        (value / self.members.len(), reward / self.members.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluation::Standard;
+
+    use super::*;
+
+    /// Evaluates every placement of a T piece on a bumpy board with each evaluator, returning the
+    /// raw values in the same order for each.
+    fn evaluate_all(evaluators: &[&dyn Fn(&LockResult, &Board, &Board) -> i32]) -> Vec<Vec<i32>> {
+        let mut field = [[false; 10]; 40];
+        for (x, &h) in [2, 5, 1, 0, 3, 3, 6, 2, 0, 4].iter().enumerate() {
+            for y in 0..h {
+                field[y][x] = true;
+            }
+        }
+        let mut board = Board::new();
+        board.set_field(field);
+        board.add_next_piece(Piece::T);
+        board.add_next_piece(Piece::I);
+        let spawned = SpawnRule::Row19Or20.spawn(Piece::T, &board).unwrap();
+        let placements = find_moves(
+            &board,
+            spawned,
+            MovementMode::ZeroG,
+            false,
+            KickTable::default(),
+            SpinDetectionMode::default(),
+        );
+        evaluators
+            .iter()
+            .map(|evaluate| {
+                placements
+                    .iter()
+                    .map(|p| {
+                        let mut result = board.clone();
+                        let lock = result.lock_piece(p.location);
+                        evaluate(&lock, &result, &board)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn raw<E: Evaluator>(eval: &E) -> impl Fn(&LockResult, &Board, &Board) -> i32 + '_ {
+        move |lock, board, parent| {
+            let (value, _) = eval.evaluate(lock, board, 10, 0, Piece::T, parent);
+            eval.raw_evaluation(&value).unwrap().0
+        }
+    }
+
+    #[test]
+    fn ensemble_of_identical_members_matches_member() {
+        let single = Standard::default();
+        let ensemble = Ensemble {
+            members: vec![Standard::default(), Standard::default()],
+        };
+        let values = evaluate_all(&[&raw(&single), &raw(&ensemble)]);
+        assert_eq!(values[0], values[1]);
+    }
+
+    #[test]
+    fn ensemble_compromises_between_members() {
+        let flat = Standard {
+            bumpiness: -100,
+            ..Standard::default()
+        };
+        let bumpy = Standard {
+            bumpiness: 100,
+            ..Standard::default()
+        };
+        let ensemble = Ensemble {
+            members: vec![flat.clone(), bumpy.clone()],
+        };
+        let values = evaluate_all(&[&raw(&flat), &raw(&bumpy), &raw(&ensemble)]);
+        for i in 0..values[0].len() {
+            let (a, b) = (values[0][i], values[1][i]);
+            assert!(a.min(b) <= values[2][i] && values[2][i] <= a.max(b));
+        }
+        let best = |values: &[i32]| (0..values.len()).max_by_key(|&i| values[i]).unwrap();
+        // the members disagree, and the ensemble picks the move they like best overall
+        assert_ne!(best(&values[0]), best(&values[1]));
+        let total: Vec<_> = values[0]
+            .iter()
+            .zip(&values[1])
+            .map(|(a, b)| a + b)
+            .collect();
+        assert_eq!(best(&values[2]), best(&total));
+    }
+}
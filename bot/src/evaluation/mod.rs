@@ -2,7 +2,16 @@ use libtetris::{Board, LockResult, Piece};
 
 use crate::dag::MoveCandidate;
 
+mod custom;
+mod downstack;
+mod ensemble;
+mod ramp;
 mod standard;
+pub use self::custom::CustomEvaluator;
+pub use self::downstack::Downstack;
+pub use self::ensemble::Ensemble;
+pub use self::ramp::Ramp;
+pub(crate) use self::standard::find_tslot;
 pub use self::standard::Standard;
 pub mod changed;
 
@@ -12,21 +21,53 @@ pub trait Evaluator: Send + Sync {
 
     fn name(&self) -> String;
 
+    /// Evaluates the placement of `placed` on `parent`, which resulted in `board`.
+    ///
+    /// `move_time` is the number of frames the placement takes, including the spawn delay, and
+    /// `incoming` is the amount of garbage the bot currently expects to receive.
     fn evaluate(
         &self,
         lock: &LockResult,
         board: &Board,
         move_time: u32,
+        incoming: u32,
         placed: Piece,
+        parent: &Board,
     ) -> (Self::Value, Self::Reward);
 
+    /// Picks the move to play out of the candidates, which are ordered best first.
+    ///
+    /// `incoming` is the amount of garbage expected after the move is played, and `garbage_holes`
+    /// holds the relative likelihood of that garbage's hole being in each column. If all entries
+    /// are zero, nothing is known about where the holes will be. `opponent_height` is the height
+    /// of the opponent's stack, or 0 if it isn't known.
     fn pick_move(
         &self,
         candidates: Vec<MoveCandidate<Self::Value>>,
         _incoming: u32,
+        _garbage_holes: &[u32; 10],
+        _opponent_height: u32,
     ) -> MoveCandidate<Self::Value> {
         candidates.into_iter().next().unwrap()
     }
+
+    /// Converts an evaluation into an approximate advantage measured in lines of garbage, or
+    /// `None` if the evaluator doesn't know how to.
+    fn advantage_in_lines(&self, _value: &Self::Value) -> Option<f32> {
+        None
+    }
+
+    /// Extracts the numbers making up an evaluation as `(value, spike)`, or `None` if the
+    /// evaluator's evaluations aren't made of numbers like that.
+    fn raw_evaluation(&self, _value: &Self::Value) -> Option<(i32, i32)> {
+        None
+    }
+
+    /// The row above which the game considers the board lost. The bot treats placements entirely
+    /// at or above this row as lock outs.
+    fn vanish_zone_top(&self) -> i32 {
+        20
+    }
 }
 
 pub trait Evaluation<R>:
@@ -40,7 +81,12 @@ pub trait Evaluation<R>:
     + std::ops::Add<Output = Self>
 {
     fn modify_death(self) -> Self;
-    fn weight(self, min: &Self, rank: usize) -> i64;
+    /// Determines how likely the search is to explore this node relative to its siblings.
+    ///
+    /// Higher `exploration` values should make lower ranked nodes relatively more likely to be
+    /// explored, approaching equal weights for all siblings as it grows very large. An
+    /// `exploration` of 1 is the baseline.
+    fn weight(self, min: &Self, rank: usize, exploration: f32) -> i64;
 
     fn improve(&mut self, other: Self);
 }
@@ -58,16 +104,28 @@ impl<T: Evaluator> Evaluator for std::sync::Arc<T> {
         lock: &LockResult,
         board: &Board,
         move_time: u32,
+        incoming: u32,
         placed: Piece,
+        parent: &Board,
     ) -> (T::Value, T::Reward) {
-        (**self).evaluate(lock, board, move_time, placed)
+        (**self).evaluate(lock, board, move_time, incoming, placed, parent)
     }
 
     fn pick_move(
         &self,
         candidates: Vec<MoveCandidate<Self::Value>>,
         incoming: u32,
+        garbage_holes: &[u32; 10],
+        opponent_height: u32,
     ) -> MoveCandidate<Self::Value> {
-        (**self).pick_move(candidates, incoming)
+        (**self).pick_move(candidates, incoming, garbage_holes, opponent_height)
+    }
+
+    fn advantage_in_lines(&self, value: &Self::Value) -> Option<f32> {
+        (**self).advantage_in_lines(value)
+    }
+
+    fn raw_evaluation(&self, value: &Self::Value) -> Option<(i32, i32)> {
+        (**self).raw_evaluation(value)
     }
 }
@@ -0,0 +1,162 @@
+use libtetris::*;
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// Gradually switches from one evaluator to another over the course of a game.
+///
+/// Evaluations are interpolated between `early` and `late` based on how many pieces have been
+/// placed on the board, reaching `late` after `pieces` pieces. This makes it possible to, for
+/// example, play safely at the start of a game and more aggressively later on. Moves are picked
+/// using `early`'s `pick_move` until halfway through the ramp, and `late`'s after that.
+///
+/// Pieces are counted from the board the bot was launched with.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Ramp<E> {
+    pub early: E,
+    pub late: E,
+    pub pieces: u32,
+}
+
+impl<E: Evaluator> Ramp<E> {
+    /// Returns how much weight the early evaluator gets out of the total.
+    fn early_weight(&self, board: &Board) -> (usize, usize) {
+        let total = self.pieces.max(1) as usize;
+        (total - (board.piece_count as usize).min(total), total)
+    }
+}
+
+impl<E: Evaluator> Evaluator for Ramp<E>
+where
+    E::Reward: std::ops::Add<Output = E::Reward>
+        + std::ops::Mul<usize, Output = E::Reward>
+        + std::ops::Div<usize, Output = E::Reward>,
+{
+    type Value = E::Value;
+    type Reward = E::Reward;
+
+    fn name(&self) -> String {
+        format!(
+            "Ramp over {} pieces\n{}\n{}",
+            self.pieces,
+            self.early.name(),
+            self.late.name()
+        )
+    }
+
+    fn pick_move(
+        &self,
+        candidates: Vec<MoveCandidate<E::Value>>,
+        incoming: u32,
+        garbage_holes: &[u32; 10],
+        opponent_height: u32,
+    ) -> MoveCandidate<E::Value> {
+        let halfway = candidates
+            .first()
+            .map_or(false, |c| c.board.piece_count * 2 >= self.pieces);
+        if halfway {
+            self.late
+                .pick_move(candidates, incoming, garbage_holes, opponent_height)
+        } else {
+            self.early
+                .pick_move(candidates, incoming, garbage_holes, opponent_height)
+        }
+    }
+
+    fn advantage_in_lines(&self, value: &E::Value) -> Option<f32> {
+        self.late.advantage_in_lines(value)
+    }
+
+    fn raw_evaluation(&self, value: &E::Value) -> Option<(i32, i32)> {
+        self.late.raw_evaluation(value)
+    }
+
+    fn evaluate(
+        &self,
+        lock: &LockResult,
+        board: &Board,
+        move_time: u32,
+        incoming: u32,
+        placed: Piece,
+        parent: &Board,
+    ) -> (E::Value, E::Reward) {
+        let (early_left, total) = self.early_weight(board);
+        if early_left == 0 {
+            return self
+                .late
+                .evaluate(lock, board, move_time, incoming, placed, parent);
+        } else if early_left == total {
+            return self
+                .early
+                .evaluate(lock, board, move_time, incoming, placed, parent);
+        }
+        let (early_v, early_r) = self
+            .early
+            .evaluate(lock, board, move_time, incoming, placed, parent);
+        let (late_v, late_r) = self
+            .late
+            .evaluate(lock, board, move_time, incoming, placed, parent);
+        let late_done = total - early_left;
+        (
+            (early_v * early_left + late_v * late_done) / total,
+            (early_r * early_left + late_r * late_done) / total,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::standard::Standard;
+
+    /// The garbage sent by the best placement of an I piece in a tetris-ready well after
+    /// `piece_count` pieces have been placed.
+    fn best_attack(eval: &Ramp<Standard>, piece_count: u32) -> u32 {
+        let mut field = [[false; 10]; 40];
+        for row in &mut field[..6] {
+            for cell in &mut row[..9] {
+                *cell = true;
+            }
+        }
+        let mut board = Board::new();
+        board.set_field(field);
+        board.piece_count = piece_count;
+        board.add_next_piece(Piece::I);
+        let piece = board.advance_queue().unwrap();
+        let spawned = SpawnRule::Row19Or20.spawn(piece, &board).unwrap();
+        find_moves(
+            &board,
+            spawned,
+            MovementMode::ZeroG,
+            false,
+            KickTable::default(),
+            SpinDetectionMode::default(),
+        )
+        .into_iter()
+        .map(|p| {
+            let mut result = board.clone();
+            let lock = result.lock_piece(p.location);
+            let (value, reward) = eval.evaluate(&lock, &result, 0, 0, piece, &board);
+            let total = eval.raw_evaluation(&(value + reward)).unwrap().0;
+            (total, lock.garbage_sent)
+        })
+        .max_by_key(|&(total, _)| total)
+        .unwrap()
+        .1
+    }
+
+    #[test]
+    fn late_game_prefers_attack() {
+        let eval = Ramp {
+            early: Standard {
+                clear4: -2000,
+                ..Standard::default()
+            },
+            late: Standard::default(),
+            pieces: 100,
+        };
+        assert_eq!(best_attack(&eval, 0), 0);
+        assert_eq!(best_attack(&eval, 100), 4);
+        assert_eq!(best_attack(&eval, 500), 4);
+    }
+}
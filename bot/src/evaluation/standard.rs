@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use libtetris::*;
 use serde::{Deserialize, Serialize};
 
@@ -14,6 +16,11 @@ pub struct Standard {
     pub top_half: i32,
     pub top_quarter: i32,
     pub jeopardy: i32,
+    /// Multiplied by the lines of garbage still incoming after the placement and by how far
+    /// above the middle of the board the stack will be once that garbage arrives. If
+    /// `timed_jeopardy` is set, this is also scaled by the frames the placement takes, including
+    /// the spawn delay but not the line clear delay.
+    pub incoming_jeopardy: i32,
     pub cavity_cells: i32,
     pub cavity_cells_sq: i32,
     pub overhang_cells: i32,
@@ -24,6 +31,10 @@ pub struct Standard {
     pub well_depth: i32,
     pub max_well_depth: i32,
     pub well_column: [i32; 10],
+    pub sz_dependency: i32,
+    pub dig_row_transitions: i32,
+    pub surface_spikes: i32,
+    pub stack_balance: i32,
 
     pub b2b_clear: i32,
     pub clear1: i32,
@@ -39,10 +50,24 @@ pub struct Standard {
     pub combo_garbage: i32,
     pub move_time: i32,
     pub wasted_t: i32,
+    pub filled_tslot: i32,
+    pub held_t: i32,
 
     pub use_bag: bool,
     pub timed_jeopardy: bool,
     pub stack_pc_damage: bool,
+    pub lookahead_defense: bool,
+    pub flood_fill_holes: bool,
+    /// Whether combos longer than the combo table are valued by continuing the table's last step
+    /// instead of by its last entry.
+    pub combo_extrapolate: bool,
+    /// The garbage sent by each combo, starting from the first line clear. `None` means the
+    /// standard table is used.
+    pub combo_table: Option<Vec<u32>>,
+    /// The row above which the game considers the board lost. The height-based weights are
+    /// measured relative to this instead of assuming a 20 row field, and placements entirely at
+    /// or above it are treated as lock outs.
+    pub vanish_zone_top: i32,
     pub sub_name: Option<String>,
 }
 
@@ -57,6 +82,7 @@ impl Default for Standard {
             top_half: -150,
             top_quarter: -511,
             jeopardy: -11,
+            incoming_jeopardy: -4,
             cavity_cells: -173,
             cavity_cells_sq: -3,
             overhang_cells: -34,
@@ -67,9 +93,15 @@ impl Default for Standard {
             well_depth: 57,
             max_well_depth: 17,
             well_column: [20, 23, 20, 50, 59, 21, 59, 10, -10, 24],
+            sz_dependency: 0,
+            dig_row_transitions: 0,
+            surface_spikes: 0,
+            stack_balance: 0,
 
             move_time: -3,
             wasted_t: -152,
+            filled_tslot: 0,
+            held_t: 0,
             b2b_clear: 104,
             clear1: -143,
             clear2: -100,
@@ -86,6 +118,11 @@ impl Default for Standard {
             use_bag: true,
             timed_jeopardy: true,
             stack_pc_damage: false,
+            lookahead_defense: false,
+            flood_fill_holes: false,
+            combo_extrapolate: false,
+            combo_table: None,
+            vanish_zone_top: 20,
             sub_name: None,
         }
     }
@@ -102,6 +139,7 @@ impl Standard {
             top_half: -126,
             top_quarter: -493,
             jeopardy: -11,
+            incoming_jeopardy: -4,
             cavity_cells: -176,
             cavity_cells_sq: -6,
             overhang_cells: -47,
@@ -112,6 +150,10 @@ impl Standard {
             well_depth: 158,
             max_well_depth: -2,
             well_column: [31, 16, -41, 37, 49, 30, 56, 48, -27, 22],
+            sz_dependency: 0,
+            dig_row_transitions: 0,
+            surface_spikes: 0,
+            stack_balance: 0,
             b2b_clear: 74,
             clear1: -122,
             clear2: -174,
@@ -126,101 +168,96 @@ impl Standard {
             combo_garbage: 272,
             move_time: -1,
             wasted_t: -147,
+            filled_tslot: 0,
+            held_t: 0,
             use_bag: true,
             timed_jeopardy: false,
             stack_pc_damage: false,
+            lookahead_defense: false,
+            flood_fill_holes: false,
+            combo_extrapolate: false,
+            combo_table: None,
+            vanish_zone_top: 20,
             sub_name: None,
         }
     }
 }
 
-impl Evaluator for Standard {
-    type Value = Value;
-    type Reward = Reward;
-
-    fn name(&self) -> String {
-        let mut info = "Standard".to_owned();
-        if let Some(extra) = &self.sub_name {
-            info.push('\n');
-            info.push_str(extra);
+impl Standard {
+    fn combo_value(&self, combo: u32) -> i32 {
+        let table = self
+            .combo_table
+            .as_deref()
+            .unwrap_or(&libtetris::COMBO_GARBAGE);
+        let last = match table.len() {
+            0 => return 0,
+            len => len - 1,
+        };
+        let combo = combo as usize;
+        if combo <= last {
+            table[combo] as i32
+        } else if self.combo_extrapolate && last > 0 {
+            let step = table[last] as i32 - table[last - 1] as i32;
+            table[last] as i32 + step * (combo - last) as i32
+        } else {
+            table[last] as i32
         }
-        info
     }
 
-    fn pick_move(
+    /// Evaluates a placement the same way as `evaluate`, but returns how much each weight
+    /// contributed instead of the totals.
+    ///
+    /// The keys are the names of the weights, and weights which contributed nothing are left out.
+    /// The contributions add up to the sum of the value and reward returned by `evaluate`.
+    pub fn evaluate_breakdown(
         &self,
-        candidates: Vec<MoveCandidate<Value>>,
+        lock: &LockResult,
+        board: &Board,
+        move_time: u32,
         incoming: u32,
-    ) -> MoveCandidate<Value> {
-        let mut backup = None;
-        for mv in candidates.into_iter() {
-            if incoming == 0
-                || mv.board.column_heights()[3..6]
-                    .iter()
-                    .all(|h| incoming as i32 - mv.lock.garbage_sent as i32 + h <= 20)
-            {
-                return mv;
-            }
-
-            match backup {
-                None => backup = Some(mv),
-                Some(c) if c.evaluation.spike < mv.evaluation.spike => backup = Some(mv),
-                _ => {}
-            }
-        }
-
-        return backup.unwrap();
+        placed: Piece,
+        parent: &Board,
+    ) -> HashMap<&'static str, i32> {
+        let mut terms = Breakdown(HashMap::new());
+        self.evaluate_terms(&mut terms, lock, board, move_time, incoming, placed, parent);
+        terms.0
     }
 
-    fn evaluate(
+    /// Computes the contribution of each weight to the evaluation of a placement, sending them to
+    /// `terms`.
+    fn evaluate_terms(
         &self,
+        terms: &mut impl Terms,
         lock: &LockResult,
         board: &Board,
         move_time: u32,
+        incoming: u32,
         placed: Piece,
-    ) -> (Value, Reward) {
-        let mut transient_eval = 0;
-        let mut acc_eval = 0;
-
+        parent: &Board,
+    ) {
         if lock.perfect_clear {
-            acc_eval += self.perfect_clear;
+            terms.reward("perfect_clear", self.perfect_clear);
         }
         if self.stack_pc_damage || !lock.perfect_clear {
             if lock.b2b {
-                acc_eval += self.b2b_clear;
+                terms.reward("b2b_clear", self.b2b_clear);
             }
             if let Some(combo) = lock.combo {
-                let combo = combo.min(11) as usize;
-                acc_eval += self.combo_garbage * libtetris::COMBO_GARBAGE[combo] as i32;
+                terms.reward(
+                    "combo_garbage",
+                    self.combo_garbage * self.combo_value(combo),
+                );
             }
             match lock.placement_kind {
-                PlacementKind::Clear1 => {
-                    acc_eval += self.clear1;
-                }
-                PlacementKind::Clear2 => {
-                    acc_eval += self.clear2;
-                }
-                PlacementKind::Clear3 => {
-                    acc_eval += self.clear3;
-                }
-                PlacementKind::Clear4 => {
-                    acc_eval += self.clear4;
-                }
-                PlacementKind::Tspin1 => {
-                    acc_eval += self.tspin1;
-                }
-                PlacementKind::Tspin2 => {
-                    acc_eval += self.tspin2;
-                }
-                PlacementKind::Tspin3 => {
-                    acc_eval += self.tspin3;
-                }
-                PlacementKind::MiniTspin1 => {
-                    acc_eval += self.mini_tspin1;
-                }
-                PlacementKind::MiniTspin2 => {
-                    acc_eval += self.mini_tspin2;
-                }
+                PlacementKind::Clear1 => terms.reward("clear1", self.clear1),
+                PlacementKind::Clear2 => terms.reward("clear2", self.clear2),
+                PlacementKind::Clear3 => terms.reward("clear3", self.clear3),
+                PlacementKind::Clear4 => terms.reward("clear4", self.clear4),
+                PlacementKind::Tspin1 => terms.reward("tspin1", self.tspin1),
+                PlacementKind::Tspin2 => terms.reward("tspin2", self.tspin2),
+                PlacementKind::Tspin3 => terms.reward("tspin3", self.tspin3),
+                PlacementKind::MiniTspin1 => terms.reward("mini_tspin1", self.mini_tspin1),
+                PlacementKind::MiniTspin2 => terms.reward("mini_tspin2", self.mini_tspin2),
                 _ => {}
             }
         }
@@ -228,30 +265,56 @@ impl Evaluator for Standard {
         if placed == Piece::T {
             match lock.placement_kind {
                 PlacementKind::Tspin1 | PlacementKind::Tspin2 | PlacementKind::Tspin3 => {}
-                _ => acc_eval += self.wasted_t,
+                _ => terms.reward("wasted_t", self.wasted_t),
             }
         }
 
+        // garbage arrives while the next piece is placed, so the line clear delay doesn't matter
+        let placement_time = if self.timed_jeopardy {
+            move_time as i32
+        } else {
+            10
+        };
+
         // magic approximation of line clear delay
         let move_time = if lock.placement_kind.is_clear() {
             move_time as i32 + 40
         } else {
             move_time as i32
         };
-        acc_eval += self.move_time * move_time;
+        terms.reward("move_time", self.move_time * move_time);
 
         if board.b2b_bonus {
-            transient_eval += self.back_to_back;
+            terms.value("back_to_back", self.back_to_back);
         }
 
         let highest_point = *board.column_heights().iter().max().unwrap() as i32;
-        transient_eval += self.top_quarter * (highest_point - 15).max(0);
-        transient_eval += self.top_half * (highest_point - 10).max(0);
+        let half = self.vanish_zone_top / 2;
+        let quarter = self.vanish_zone_top * 3 / 4;
+        terms.value(
+            "top_quarter",
+            self.top_quarter * (highest_point - quarter).max(0),
+        );
+        terms.value("top_half", self.top_half * (highest_point - half).max(0));
+
+        terms.reward(
+            "jeopardy",
+            self.jeopardy
+                * (highest_point - half).max(0)
+                * if self.timed_jeopardy { move_time } else { 10 }
+                / 10,
+        );
 
-        acc_eval += self.jeopardy
-            * (highest_point - 10).max(0)
-            * if self.timed_jeopardy { move_time } else { 10 }
-            / 10;
+        // garbage sent by this placement cancels out incoming garbage
+        let pending = incoming.saturating_sub(lock.garbage_sent) as i32;
+        terms.reward(
+            "incoming_jeopardy",
+            self.incoming_jeopardy
+                * pending
+                * (highest_point + pending - half).max(0)
+                * placement_time
+                / 10,
+        );
 
         let ts = if self.use_bag {
             board.next_bag().contains(Piece::T) as usize
@@ -261,31 +324,25 @@ impl Evaluator for Standard {
             1 + (board.hold_piece == Some(Piece::T)) as usize
         };
 
+        if placed != Piece::T && ts != 0 && self.filled_tslot != 0 {
+            if let Some(slot) = find_tslot(parent) {
+                if filled_cells(board, lock, &slot) {
+                    terms.reward("filled_tslot", self.filled_tslot);
+                }
+            }
+        }
+
+        if board.hold_piece == Some(Piece::T) && self.held_t != 0 && find_tslot(board).is_some() {
+            terms.value("held_t", self.held_t);
+        }
+
         let mut board = board.clone();
         for _ in 0..ts {
-            let cutout_location = sky_tslot_left(&board)
-                .or_else(|| sky_tslot_right(&board))
-                .or_else(|| {
-                    let tst = tst_twist_left(&board).or_else(|| tst_twist_right(&board))?;
-                    cave_tslot(&board, tst).or_else(|| {
-                        let corners = board.occupied(tst.x - 1, tst.y - 1) as usize
-                            + board.occupied(tst.x + 1, tst.y - 1) as usize
-                            + board.occupied(tst.x - 1, tst.y + 1) as usize
-                            + board.occupied(tst.x + 1, tst.y + 1) as usize;
-                        if corners >= 3 && board.on_stack(&tst) {
-                            Some(tst)
-                        } else {
-                            None
-                        }
-                    })
-                })
-                .or_else(|| fin_left(&board))
-                .or_else(|| fin_right(&board));
-            let result = match cutout_location {
+            let result = match find_tslot(&board) {
                 Some(location) => cutout_tslot(board.clone(), location),
                 None => break,
             };
-            transient_eval += self.tslot[result.lines];
+            terms.value("tslot", self.tslot[result.lines]);
             if let Some(b) = result.result {
                 board = b;
             } else {
@@ -294,7 +351,7 @@ impl Evaluator for Standard {
         }
 
         let highest_point = *board.column_heights().iter().max().unwrap() as i32;
-        transient_eval += self.height * highest_point;
+        terms.value("height", self.height * highest_point);
 
         let mut well = 0;
         for x in 1..10 {
@@ -313,49 +370,229 @@ impl Evaluator for Standard {
             depth += 1;
         }
         let depth = depth.min(self.max_well_depth);
-        transient_eval += self.well_depth * depth;
+        terms.value("well_depth", self.well_depth * depth);
         if depth != 0 {
-            transient_eval += self.well_column[well];
+            terms.value("well_column", self.well_column[well]);
+        }
+
+        if self.sz_dependency != 0 {
+            terms.value("sz_dependency", self.sz_dependency * sz_dependency(&board));
+        }
+
+        if self.surface_spikes != 0 {
+            terms.value(
+                "surface_spikes",
+                self.surface_spikes * surface_spikes(&board),
+            );
+        }
+
+        if self.stack_balance != 0 {
+            terms.value(
+                "stack_balance",
+                self.stack_balance * stack_imbalance(&board),
+            );
         }
 
         if self.row_transitions != 0 {
-            transient_eval += self.row_transitions
-                * (0..40)
-                    .map(|y| *board.get_row(y))
-                    .map(|r| (r | 0b1_00000_00000) ^ (1 | r << 1))
-                    .map(|d| d.count_ones() as i32)
-                    .sum::<i32>();
+            terms.value(
+                "row_transitions",
+                self.row_transitions * row_transitions(&board),
+            );
         }
 
         if self.bumpiness | self.bumpiness_sq != 0 {
             let (bump, bump_sq) = bumpiness(&board, well);
-            transient_eval += bump * self.bumpiness;
-            transient_eval += bump_sq * self.bumpiness_sq;
+            terms.value("bumpiness", bump * self.bumpiness);
+            terms.value("bumpiness_sq", bump_sq * self.bumpiness_sq);
         }
 
         if self.cavity_cells | self.cavity_cells_sq | self.overhang_cells | self.overhang_cells_sq
             != 0
         {
-            let (cavity_cells, overhang_cells) = cavities_and_overhangs(&board);
-            transient_eval += self.cavity_cells * cavity_cells;
-            transient_eval += self.cavity_cells_sq * cavity_cells * cavity_cells;
-            transient_eval += self.overhang_cells * overhang_cells;
-            transient_eval += self.overhang_cells_sq * overhang_cells * overhang_cells;
+            let (cavity_cells, overhang_cells) = if self.flood_fill_holes {
+                flood_fill_cavities_and_overhangs(&board)
+            } else {
+                cavities_and_overhangs(&board)
+            };
+            terms.value("cavity_cells", self.cavity_cells * cavity_cells);
+            terms.value(
+                "cavity_cells_sq",
+                self.cavity_cells_sq * cavity_cells * cavity_cells,
+            );
+            terms.value("overhang_cells", self.overhang_cells * overhang_cells);
+            terms.value(
+                "overhang_cells_sq",
+                self.overhang_cells_sq * overhang_cells * overhang_cells,
+            );
         }
 
         if self.covered_cells | self.covered_cells_sq != 0 {
             let (covered_cells, covered_cells_sq) = covered_cells(&board);
-            transient_eval += self.covered_cells * covered_cells;
-            transient_eval += self.covered_cells_sq * covered_cells_sq;
+            terms.value("covered_cells", self.covered_cells * covered_cells);
+            terms.value("covered_cells_sq", self.covered_cells_sq * covered_cells_sq);
+        }
+    }
+}
+
+/// Receives the contributions of the weights of `Standard` to an evaluation.
+trait Terms {
+    /// Adds to the value, which describes the resulting board.
+    fn value(&mut self, name: &'static str, amount: i32);
+    /// Adds to the reward, which is earned by making the placement.
+    fn reward(&mut self, name: &'static str, amount: i32);
+}
+
+/// Sums up the terms, which is all `evaluate` needs.
+#[derive(Default)]
+struct Totals {
+    value: i32,
+    reward: i32,
+}
+
+impl Terms for Totals {
+    fn value(&mut self, _: &'static str, amount: i32) {
+        self.value += amount;
+    }
+
+    fn reward(&mut self, _: &'static str, amount: i32) {
+        self.reward += amount;
+    }
+}
+
+/// Records each term by the name of its weight.
+struct Breakdown(HashMap<&'static str, i32>);
+
+impl Terms for Breakdown {
+    fn value(&mut self, name: &'static str, amount: i32) {
+        self.reward(name, amount);
+    }
+
+    fn reward(&mut self, name: &'static str, amount: i32) {
+        if amount != 0 {
+            *self.0.entry(name).or_insert(0) += amount;
+        }
+    }
+}
+
+impl Evaluator for Standard {
+    type Value = Value;
+    type Reward = Reward;
+
+    fn name(&self) -> String {
+        let mut info = "Standard".to_owned();
+        if let Some(extra) = &self.sub_name {
+            info.push('\n');
+            info.push_str(extra);
         }
+        info
+    }
+
+    fn pick_move(
+        &self,
+        mut candidates: Vec<MoveCandidate<Value>>,
+        incoming: u32,
+        garbage_holes: &[u32; 10],
+        opponent_height: u32,
+    ) -> MoveCandidate<Value> {
+        let safe = |board: &Board, rise: i32| {
+            board.column_heights()[3..6]
+                .iter()
+                .all(|h| rise + h <= self.vanish_zone_top)
+                && hole_side_height(board, garbage_holes)
+                    .map_or(true, |h| rise + h <= self.vanish_zone_top)
+        };
+
+        // If we can safely send enough garbage to top out the opponent, do it.
+        if opponent_height != 0 {
+            let lethal = candidates.iter().position(|mv| {
+                (opponent_height + mv.lock.garbage_sent) as i32 >= self.vanish_zone_top
+                    && safe(&mv.board, incoming as i32 - mv.lock.garbage_sent as i32)
+            });
+            if let Some(i) = lethal {
+                return candidates.swap_remove(i);
+            }
+        }
+
+        // When garbage is coming, favor surfaces that will be easier to dig through.
+        if incoming != 0 && self.dig_row_transitions != 0 {
+            candidates.sort_by_cached_key(|mv| {
+                std::cmp::Reverse(
+                    mv.evaluation.value + self.dig_row_transitions * row_transitions(&mv.board),
+                )
+            });
+        }
+
+        let mut backup = None;
+        let mut lookahead = None;
+        for mv in candidates.into_iter() {
+            let rise = incoming as i32 - mv.lock.garbage_sent as i32;
+            if incoming == 0 || safe(&mv.board, rise) {
+                return mv;
+            }
+
+            // If this move can be followed by one which makes us safe (e.g. holding to save an I
+            // piece for next turn), prefer it over simply minimizing risk.
+            if self.lookahead_defense && lookahead.is_none() {
+                let safe_followup = mv.followups.iter().any(|&followup| {
+                    let mut board = mv.board.clone();
+                    let lock = board.lock_piece(followup);
+                    safe(&board, rise - lock.garbage_sent as i32)
+                });
+                if safe_followup {
+                    lookahead = Some(mv);
+                    continue;
+                }
+            }
+
+            match backup {
+                None => backup = Some(mv),
+                Some(c) if c.evaluation.spike < mv.evaluation.spike => backup = Some(mv),
+                _ => {}
+            }
+        }
+
+        return lookahead.or(backup).unwrap();
+    }
+
+    fn advantage_in_lines(&self, value: &Value) -> Option<f32> {
+        Some(advantage_in_lines(value.value))
+    }
+
+    fn raw_evaluation(&self, value: &Value) -> Option<(i32, i32)> {
+        Some((value.value, value.spike))
+    }
+
+    fn vanish_zone_top(&self) -> i32 {
+        self.vanish_zone_top
+    }
+
+    fn evaluate(
+        &self,
+        lock: &LockResult,
+        board: &Board,
+        move_time: u32,
+        incoming: u32,
+        placed: Piece,
+        parent: &Board,
+    ) -> (Value, Reward) {
+        let mut totals = Totals::default();
+        self.evaluate_terms(
+            &mut totals,
+            lock,
+            board,
+            move_time,
+            incoming,
+            placed,
+            parent,
+        );
 
         (
             Value {
-                value: transient_eval,
+                value: totals.value,
                 spike: 0,
             },
             Reward {
-                value: acc_eval,
+                value: totals.reward,
                 attack: if lock.placement_kind.is_clear() {
                     lock.garbage_sent as i32
                 } else {
@@ -366,6 +603,129 @@ impl Evaluator for Standard {
     }
 }
 
+/// Converts the value of an evaluation into lines of garbage ahead of an even position.
+///
+/// Both constants are rough placeholders picked by hand, not fitted to any data, so the result
+/// is only a ballpark figure, and less so for weights far from the defaults.
+pub(super) fn advantage_in_lines(value: i32) -> f32 {
+    /// How much the value changes per line of garbage received or sent.
+    const EVAL_PER_LINE: f32 = 200.0;
+    /// The value of an unremarkable midgame position, which is treated as even. Values are mostly
+    /// negative, so without this even a clean board would look like a loss.
+    const NEUTRAL_EVAL: i32 = -1000;
+    (value - NEUTRAL_EVAL) as f32 / EVAL_PER_LINE
+}
+
+/// Finds the T piece placement of the most apparent T-slot on the board, if any.
+pub(crate) fn find_tslot(board: &Board) -> Option<FallingPiece> {
+    sky_tslot_left(board)
+        .or_else(|| sky_tslot_right(board))
+        .or_else(|| {
+            let tst = tst_twist_left(board).or_else(|| tst_twist_right(board))?;
+            cave_tslot(board, tst).or_else(|| {
+                let corners = board.occupied(tst.x - 1, tst.y - 1) as usize
+                    + board.occupied(tst.x + 1, tst.y - 1) as usize
+                    + board.occupied(tst.x - 1, tst.y + 1) as usize
+                    + board.occupied(tst.x + 1, tst.y + 1) as usize;
+                if corners >= 3 && board.on_stack(&tst) {
+                    Some(tst)
+                } else {
+                    None
+                }
+            })
+        })
+        .or_else(|| fin_left(board))
+        .or_else(|| fin_right(board))
+}
+
+/// Determines whether the placement that produced `board` filled any of the cells of `piece`,
+/// where `piece` is located on the board before the placement.
+fn filled_cells(board: &Board, lock: &LockResult, piece: &FallingPiece) -> bool {
+    piece.cells().iter().any(|&(x, y)| {
+        if lock.cleared_lines.contains(&y) {
+            return true;
+        }
+        let shift = lock.cleared_lines.iter().filter(|&&l| l < y).count() as i32;
+        board.occupied(x, y - shift)
+    })
+}
+
+/// Finds the average height of the columns the incoming garbage holes are expected to be in,
+/// weighted by how likely the hole is to be in each column.
+///
+/// Returns `None` if nothing is known about where the holes will be.
+fn hole_side_height(board: &Board, garbage_holes: &[u32; 10]) -> Option<i32> {
+    let total: u32 = garbage_holes.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let weighted: u32 = garbage_holes
+        .iter()
+        .zip(board.column_heights().iter())
+        .map(|(&w, &h)| w * h as u32)
+        .sum();
+    Some((weighted / total) as i32)
+}
+
+/// Counts the filled/empty boundaries along each row, treating the walls as filled.
+fn row_transitions(board: &Board) -> i32 {
+    (0..40)
+        .map(|y| *board.get_row(y))
+        .map(|r| (r | 0b1_00000_00000) ^ (1 | r << 1))
+        .map(|d| d.count_ones() as i32)
+        .sum()
+}
+
+/// Evaluates how awkward the surface is for S and Z pieces.
+///
+/// Returns the number of S and Z pieces which can't be placed on the surface without creating a
+/// hole, so 0 if both can be placed cleanly and 2 if neither can.
+fn sz_dependency(board: &Board) -> i32 {
+    let h = board.column_heights();
+    let mut s = false;
+    let mut z = false;
+    for x in 0..9 {
+        // vertical placements need a step of one cell
+        s |= h[x] == h[x + 1] + 1;
+        z |= h[x] + 1 == h[x + 1];
+        // flat placements need two level columns next to a column one cell higher or lower
+        if x < 8 {
+            s |= h[x] == h[x + 1] && h[x + 1] + 1 == h[x + 2];
+            z |= h[x] == h[x + 1] + 1 && h[x + 1] == h[x + 2];
+        }
+    }
+    !s as i32 + !z as i32
+}
+
+/// Sums how far each lone single-column pillar rises above the lower of its neighbors, counting
+/// only pillars at least two cells tall. Bumpiness can't tell these apart from ordinary steps.
+fn surface_spikes(board: &Board) -> i32 {
+    let h = board.column_heights();
+    let mut spikes = 0;
+    for x in 1..9 {
+        let rise = (h[x] - h[x - 1]).min(h[x] - h[x + 1]);
+        if rise >= 2 {
+            spikes += rise;
+        }
+    }
+    spikes
+}
+
+/// Finds how far the stack's center of mass is from the middle of the board, in half columns.
+fn stack_imbalance(board: &Board) -> i32 {
+    let h = board.column_heights();
+    let total: i32 = h.iter().sum();
+    if total == 0 {
+        return 0;
+    }
+    let moment: i32 = h
+        .iter()
+        .enumerate()
+        .map(|(x, &h)| h * (2 * x as i32 - 9))
+        .sum();
+    (moment / total).abs()
+}
+
 /// Evaluates the bumpiness of the playfield.
 ///
 /// The first returned value is the total amount of height change outside of an apparent well. The
@@ -424,6 +784,47 @@ fn cavities_and_overhangs(board: &Board) -> (i32, i32) {
     (cavities, overhangs)
 }
 
+/// Evaluates the holes in the playfield exactly.
+///
+/// Like `cavities_and_overhangs`, but empty cells below the top of their column are overhangs if
+/// they are connected to the empty space above the stack and cavities otherwise. This is slower,
+/// but handles complex overhangs correctly.
+fn flood_fill_cavities_and_overhangs(board: &Board) -> (i32, i32) {
+    let top = *board.column_heights().iter().max().unwrap();
+    let mut reachable = [[false; 10]; 41];
+    let mut stack = vec![];
+    for x in 0..10 {
+        reachable[top as usize][x] = true;
+        stack.push((x as i32, top));
+    }
+    while let Some((x, y)) = stack.pop() {
+        for &(nx, ny) in &[(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+            if ny > top || board.occupied(nx, ny) || reachable[ny as usize][nx as usize] {
+                continue;
+            }
+            reachable[ny as usize][nx as usize] = true;
+            stack.push((nx, ny));
+        }
+    }
+
+    let mut cavities = 0;
+    let mut overhangs = 0;
+    for y in 0..top {
+        for x in 0..10 {
+            if board.occupied(x as i32, y) || y >= board.column_heights()[x] {
+                continue;
+            }
+            if reachable[y as usize][x] {
+                overhangs += 1;
+            } else {
+                cavities += 1;
+            }
+        }
+    }
+
+    (cavities, overhangs)
+}
+
 /// Evaluates how covered holes in the playfield are.
 ///
 /// The first returned value is the number of filled cells cover the topmost hole in the columns.
@@ -682,12 +1083,32 @@ pub struct Reward {
     attack: i32,
 }
 
+impl Reward {
+    /// Adds to the part of the evaluation that is kept by every board following this placement.
+    pub(super) fn add_value(self, amount: i32) -> Self {
+        Reward {
+            value: self.value + amount,
+            ..self
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Default, Serialize, Deserialize)]
 pub struct Value {
     value: i32,
     spike: i32,
 }
 
+impl Value {
+    /// Adds to the part of the evaluation that depends only on the current board.
+    pub(super) fn add_transient(self, amount: i32) -> Self {
+        Value {
+            value: self.value + amount,
+            ..self
+        }
+    }
+}
+
 impl std::ops::Add for Value {
     type Output = Self;
     fn add(self, rhs: Self) -> Self {
@@ -712,6 +1133,36 @@ impl std::ops::Add<Reward> for Value {
     }
 }
 
+impl std::ops::Add for Reward {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Reward {
+            value: self.value + rhs.value,
+            attack: self.attack + rhs.attack,
+        }
+    }
+}
+
+impl std::ops::Div<usize> for Reward {
+    type Output = Self;
+    fn div(self, rhs: usize) -> Self {
+        Reward {
+            value: self.value / rhs as i32,
+            attack: self.attack / rhs as i32,
+        }
+    }
+}
+
+impl std::ops::Mul<usize> for Reward {
+    type Output = Self;
+    fn mul(self, rhs: usize) -> Self {
+        Reward {
+            value: self.value * rhs as i32,
+            attack: self.attack * rhs as i32,
+        }
+    }
+}
+
 impl std::ops::Div<usize> for Value {
     type Output = Self;
     fn div(self, rhs: usize) -> Self {
@@ -740,9 +1191,21 @@ impl Evaluation<Reward> for Value {
         }
     }
 
-    fn weight(self, min: &Value, rank: usize) -> i64 {
+    fn weight(self, min: &Value, rank: usize, exploration: f32) -> i64 {
         let e = (self.value - min.value) as i64 + 10;
-        e * e / (rank * rank + 1) as i64
+        if exploration == 1.0 {
+            // the baseline weights, computed exactly
+            return e * e / (rank * rank + 1) as i64;
+        }
+        let rank_penalty = (rank * rank) as f32 / exploration + 1.0;
+        // above 1, differences in evaluation matter less too, so that very high values explore
+        // siblings almost evenly
+        let value_weight = if exploration > 1.0 {
+            (e as f32 / 10.0).powf(2.0 / exploration) * 100.0
+        } else {
+            (e * e) as f32
+        };
+        (value_weight / rank_penalty) as i64
     }
 
     fn improve(&mut self, new_result: Self) {
@@ -750,3 +1213,518 @@ impl Evaluation<Reward> for Value {
         self.spike = self.spike.max(new_result.spike);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board_with_heights(heights: [usize; 10]) -> Board {
+        let mut field = [[false; 10]; 40];
+        for (x, &h) in heights.iter().enumerate() {
+            for y in 0..h {
+                // leave a gap in each row so nothing clears
+                field[y][x] = x != y % 10;
+            }
+        }
+        let mut board = Board::new();
+        board.set_field(field);
+        board
+    }
+
+    /// Builds a board with columns of the specified heights and no holes.
+    fn solid_board(heights: [usize; 10]) -> Board {
+        let mut field = [[false; 10]; 40];
+        for (x, &h) in heights.iter().enumerate() {
+            for row in &mut field[..h] {
+                row[x] = true;
+            }
+        }
+        let mut board = Board::new();
+        board.set_field(field);
+        board
+    }
+
+    fn candidate(board: Board, value: i32) -> MoveCandidate<Value> {
+        MoveCandidate {
+            mv: FallingPiece {
+                kind: PieceState(Piece::O, RotationState::North),
+                x: 4,
+                y: 20,
+                tspin: TspinStatus::None,
+            },
+            lock: LockResult {
+                placement_kind: PlacementKind::None,
+                locked_out: false,
+                b2b: false,
+                perfect_clear: false,
+                combo: None,
+                garbage_sent: 0,
+                cleared_lines: Default::default(),
+            },
+            board,
+            evaluation: Value { value, spike: 0 },
+            hold: false,
+            original_rank: 0,
+            followups: vec![],
+            speculated_depth: 0,
+        }
+    }
+
+    #[test]
+    fn default_exploration_keeps_exact_weights() {
+        let min = Value {
+            value: -123_457,
+            spike: 0,
+        };
+        let value = Value {
+            value: 98_765,
+            spike: 0,
+        };
+        let e = (value.value - min.value) as i64 + 10;
+        for rank in 0..5 {
+            let expected = e * e / (rank * rank + 1) as i64;
+            assert_eq!(value.weight(&min, rank, 1.0), expected);
+        }
+    }
+
+    #[test]
+    fn expected_holes_keep_their_side_low() {
+        let eval = Standard::default();
+        let tall_right = board_with_heights([2, 2, 2, 2, 2, 2, 2, 14, 14, 14]);
+        let tall_left = board_with_heights([14, 14, 14, 2, 2, 2, 2, 2, 2, 2]);
+        let pick = |holes: &[u32; 10]| {
+            let candidates = vec![
+                candidate(tall_right.clone(), 100),
+                candidate(tall_left.clone(), 50),
+            ];
+            let mv = eval.pick_move(candidates, 8, holes, 0);
+            mv.board.column_heights()[7..]
+                .iter()
+                .copied()
+                .max()
+                .unwrap()
+        };
+
+        let right_holes = pick(&[0, 0, 0, 0, 0, 0, 0, 1, 1, 1]);
+        let left_holes = pick(&[1, 1, 1, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(right_holes < left_holes);
+    }
+
+    /// Builds a board from rows of `#` and `.` given from the top down.
+    fn board_from(rows: &[&str], queue: &[Piece]) -> Board {
+        let mut field = [[false; 10]; 40];
+        for (y, row) in rows.iter().rev().enumerate() {
+            for (x, cell) in row.bytes().enumerate() {
+                field[y][x] = cell == b'#';
+            }
+        }
+        let mut board = Board::new();
+        board.set_field(field);
+        for &piece in queue {
+            board.add_next_piece(piece);
+        }
+        board
+    }
+
+    /// Evaluates every placement of the next piece on the board, returning each placement with
+    /// the resulting board and its total value.
+    fn evaluate_placements(eval: &Standard, board: &Board) -> Vec<(FallingPiece, Board, i32)> {
+        let mut parent = board.clone();
+        let piece = parent.advance_queue().unwrap();
+        let spawned = SpawnRule::Row19Or20.spawn(piece, &parent).unwrap();
+        find_moves(
+            &parent,
+            spawned,
+            MovementMode::ZeroG,
+            false,
+            KickTable::default(),
+            SpinDetectionMode::default(),
+        )
+        .into_iter()
+        .map(|p| {
+            let mut result = parent.clone();
+            let lock = result.lock_piece(p.location);
+            let (value, reward) = eval.evaluate(&lock, &result, 10, 0, piece, &parent);
+            (p.location, result, value.value + reward.value)
+        })
+        .collect()
+    }
+
+    fn best(placements: &[(FallingPiece, Board, i32)]) -> &(FallingPiece, Board, i32) {
+        placements.iter().max_by_key(|p| p.2).unwrap()
+    }
+
+    #[test]
+    fn filled_tslot_keeps_slot_open_for_t() {
+        let board = board_from(
+            &["####......", "###...####", "####.#####"],
+            &[Piece::L, Piece::T, Piece::O],
+        );
+        let slot = find_tslot(&board).unwrap();
+        let open = |result: &Board| slot.cells().iter().all(|&(x, y)| !result.occupied(x, y));
+
+        let base = Standard::default();
+        let eval = Standard {
+            filled_tslot: -1000,
+            ..base.clone()
+        };
+        let before = evaluate_placements(&base, &board);
+        let after = evaluate_placements(&eval, &board);
+        // only the placements that fill the slot are penalized
+        let mut filled = 0;
+        for ((_, result, before), (_, _, after)) in before.iter().zip(&after) {
+            if open(result) {
+                assert_eq!(after, before);
+            } else {
+                assert_eq!(after - before, -1000);
+                filled += 1;
+            }
+        }
+        assert!(filled > 0);
+        assert!(open(&best(&after).1));
+    }
+
+    #[test]
+    fn lookahead_defense_holds_for_safe_followup() {
+        let board = board_from(&["#########."; 16], &[]);
+        let mut tetris = SpawnRule::Row19Or20.spawn(Piece::I, &board).unwrap();
+        tetris.cw(&board);
+        while tetris.shift(&board, 1, 0) {}
+        tetris.sonic_drop(&board);
+
+        let pick = |lookahead_defense| {
+            let eval = Standard {
+                lookahead_defense,
+                ..Standard::default()
+            };
+            let stay = candidate(board.clone(), 100);
+            let mut hold = candidate(board.clone(), 50);
+            hold.hold = true;
+            hold.followups = vec![tetris];
+            eval.pick_move(vec![stay, hold], 8, &[0; 10], 0).hold
+        };
+        // nothing is safe right away, but holding saves the I for a tetris next turn
+        assert!(!pick(false));
+        assert!(pick(true));
+    }
+
+    #[test]
+    fn held_t_cashes_in_open_tslot() {
+        let mut board = board_from(
+            &["####......", "###...####", "####.#####"],
+            &[Piece::O, Piece::L],
+        );
+        board.hold_piece = Some(Piece::T);
+
+        // swapping the O into hold to play the T-spin double
+        let mut parent = board.clone();
+        let next = parent.advance_queue().unwrap();
+        let t = parent.hold(next).unwrap();
+        let spawned = SpawnRule::Row19Or20.spawn(t, &parent).unwrap();
+        let tsd = |eval: &Standard| {
+            find_moves(
+                &parent,
+                spawned,
+                MovementMode::ZeroG,
+                false,
+                KickTable::default(),
+                SpinDetectionMode::default(),
+            )
+            .into_iter()
+            .filter_map(|p| {
+                let mut result = parent.clone();
+                let lock = result.lock_piece(p.location);
+                if lock.placement_kind != PlacementKind::Tspin2 {
+                    return None;
+                }
+                let (value, reward) = eval.evaluate(&lock, &result, 10, 0, t, &parent);
+                Some(value.value + reward.value)
+            })
+            .max()
+            .unwrap()
+        };
+
+        let base = Standard::default();
+        let eval = Standard {
+            held_t: -1000,
+            ..base.clone()
+        };
+        let before = evaluate_placements(&base, &board);
+        let after = evaluate_placements(&eval, &board);
+        // placing the O keeps the T in hold, which is only penalized while the slot is open
+        for ((_, result, before), (_, _, after)) in before.iter().zip(&after) {
+            if find_tslot(result).is_some() {
+                assert_eq!(after - before, -1000);
+            } else {
+                assert_eq!(after, before);
+            }
+        }
+        assert_eq!(tsd(&eval), tsd(&base));
+        assert!(tsd(&eval) > best(&after).2);
+    }
+
+    #[test]
+    fn sz_dependency_prefers_surface_s_fits() {
+        // an S stands upright against the single raised cell, but lying flat on an empty floor
+        // leaves a hole either way
+        let stepped = board_from(&["#........."], &[Piece::S]);
+        let flat = board_from(&[], &[Piece::S]);
+        assert_eq!(sz_dependency(&stepped), 0);
+        assert_eq!(sz_dependency(&flat), 2);
+
+        let value = |eval: &Standard, board: &Board| {
+            let (value, reward) =
+                eval.evaluate(&LockResult::default(), board, 0, 0, Piece::S, board);
+            value.value + reward.value
+        };
+        let base = Standard::default();
+        let eval = Standard {
+            sz_dependency: -200,
+            ..base.clone()
+        };
+        assert_eq!(value(&eval, &stepped), value(&base, &stepped));
+        assert_eq!(value(&eval, &flat), value(&base, &flat) - 400);
+        assert!(value(&eval, &stepped) > value(&eval, &flat));
+    }
+
+    #[test]
+    fn flood_fill_finds_tunnel_under_overhang() {
+        // the three cells under the left roof can be reached by sliding in from the right, but the
+        // cell under the right roof is sealed in
+        let board = board_from(&["####....##", "#......#.#"], &[]);
+        assert_eq!(cavities_and_overhangs(&board), (4, 0));
+        assert_eq!(flood_fill_cavities_and_overhangs(&board), (1, 3));
+
+        let value = |flood_fill_holes| {
+            let eval = Standard {
+                flood_fill_holes,
+                ..Standard::default()
+            };
+            let lock = LockResult::default();
+            eval.evaluate(&lock, &board, 0, 0, Piece::T, &board).0.value
+        };
+        let base = Standard::default();
+        assert_eq!(
+            value(true) - value(false),
+            base.cavity_cells * (1 - 4)
+                + base.cavity_cells_sq * (1 - 16)
+                + base.overhang_cells * 3
+                + base.overhang_cells_sq * 9
+        );
+    }
+
+    #[test]
+    fn tall_opponent_gets_topped_out() {
+        let eval = Standard::default();
+        let board = board_with_heights([2; 10]);
+        let pick = |opponent_height| {
+            let build = candidate(board.clone(), 100);
+            let mut attack = candidate(board.clone(), 50);
+            attack.lock.garbage_sent = 4;
+            eval.pick_move(vec![build, attack], 0, &[0; 10], opponent_height)
+                .lock
+                .garbage_sent
+        };
+        assert_eq!(pick(0), 0);
+        assert_eq!(pick(4), 0);
+        assert_eq!(pick(17), 4);
+    }
+
+    #[test]
+    fn incoming_garbage_prefers_flatter_surface() {
+        let messy = board_from(&["#.#.#.#.#.", "##.##.##.."], &[]);
+        let flat = board_from(&["#########.", "#########."], &[]);
+        assert!(row_transitions(&messy) > row_transitions(&flat));
+
+        let eval = Standard {
+            dig_row_transitions: -10,
+            ..Standard::default()
+        };
+        let pick = |incoming| {
+            let candidates = vec![candidate(messy.clone(), 100), candidate(flat.clone(), 90)];
+            eval.pick_move(candidates, incoming, &[0; 10], 0).board
+        };
+        assert_eq!(pick(0).get_field(), messy.get_field());
+        assert_eq!(pick(2).get_field(), flat.get_field());
+    }
+
+    #[test]
+    fn long_combo_uses_extended_table() {
+        let mut table = libtetris::COMBO_GARBAGE.to_vec();
+        table.extend(&[5, 6, 6, 7]);
+        let base = Standard::default();
+        let extended = Standard {
+            combo_table: Some(table),
+            ..base.clone()
+        };
+        assert_eq!(base.combo_value(15), 5);
+        assert_eq!(extended.combo_value(15), 7);
+        let extrapolated = Standard {
+            combo_extrapolate: true,
+            ..base.clone()
+        };
+        assert_eq!(extrapolated.combo_value(15), 9);
+
+        let board = board_with_heights([2; 10]);
+        let lock = LockResult {
+            placement_kind: PlacementKind::Clear1,
+            combo: Some(15),
+            ..LockResult::default()
+        };
+        let reward = |eval: &Standard| eval.evaluate(&lock, &board, 0, 0, Piece::I, &board).1;
+        assert_eq!(
+            reward(&extended).value - reward(&base).value,
+            base.combo_garbage * (7 - 5)
+        );
+    }
+
+    #[test]
+    fn custom_combo_table_changes_preferred_line() {
+        let board = board_with_heights([2; 10]);
+        let total = |eval: &Standard, combo: u32| {
+            let lock = LockResult {
+                placement_kind: PlacementKind::Clear1,
+                combo: Some(combo),
+                ..LockResult::default()
+            };
+            let (value, reward) = eval.evaluate(&lock, &board, 0, 0, Piece::I, &board);
+            eval.raw_evaluation(&(value + reward)).unwrap().0
+        };
+
+        // the default table gives more for the longer combo
+        let base = Standard::default();
+        assert!(total(&base, 6) > total(&base, 2));
+
+        // a game where only the third clear in a row sends anything
+        let mut table = vec![0; libtetris::COMBO_GARBAGE.len()];
+        table[2] = 4;
+        let custom = Standard {
+            combo_table: Some(table),
+            ..base
+        };
+        assert!(total(&custom, 2) > total(&custom, 6));
+    }
+
+    #[test]
+    fn breakdown_adds_up_to_evaluation() {
+        let mut tslot = board_from(
+            &["####......", "###...####", "####.#####"],
+            &[Piece::T, Piece::L],
+        );
+        tslot.hold_piece = Some(Piece::T);
+        tslot.b2b_bonus = true;
+        let tall = board_from(&["#########."; 14], &[Piece::I, Piece::S]);
+
+        for eval in &[Standard::default(), Standard::fast_config()] {
+            for board in &[tslot.clone(), tall.clone()] {
+                let mut parent = board.clone();
+                let piece = parent.advance_queue().unwrap();
+                let spawned = SpawnRule::Row19Or20.spawn(piece, &parent).unwrap();
+                let placements = find_moves(
+                    &parent,
+                    spawned,
+                    MovementMode::ZeroG,
+                    false,
+                    KickTable::default(),
+                    SpinDetectionMode::default(),
+                );
+                for p in placements {
+                    let mut result = parent.clone();
+                    let lock = result.lock_piece(p.location);
+                    let (value, reward) = eval.evaluate(&lock, &result, 20, 6, piece, &parent);
+                    let breakdown = eval.evaluate_breakdown(&lock, &result, 20, 6, piece, &parent);
+                    assert_eq!(breakdown.values().sum::<i32>(), value.value + reward.value);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn high_incoming_prefers_lower_stack() {
+        let eval = Standard::default();
+        let stack = |height| {
+            let mut heights = [height; 10];
+            heights[9] = 0;
+            solid_board(heights)
+        };
+        let (low, tall) = (stack(6), stack(10));
+        let total = |board: &Board, incoming: u32| {
+            let lock = LockResult::default();
+            let (value, reward) = eval.evaluate(&lock, board, 20, incoming, Piece::T, board);
+            eval.raw_evaluation(&(value + reward)).unwrap().0
+        };
+
+        // a deeper well is worth more with nothing incoming, but not with a lot of garbage coming
+        assert!(total(&tall, 0) > total(&low, 0));
+        assert!(total(&tall, 8) < total(&low, 8));
+    }
+
+    #[test]
+    fn higher_ceiling_tolerates_taller_stack() {
+        let tall = board_with_heights([16; 10]);
+        let low = board_with_heights([2; 10]);
+        let standard = Standard::default();
+        let raised = Standard {
+            vanish_zone_top: 30,
+            ..Standard::default()
+        };
+
+        let value = |eval: &Standard| {
+            let lock = LockResult::default();
+            eval.evaluate(&lock, &tall, 0, 0, Piece::T, &tall).0.value
+        };
+        assert!(value(&raised) > value(&standard));
+
+        // with 6 lines incoming, the tall stack only stays safe under the raised ceiling
+        let pick = |eval: &Standard| {
+            let candidates = vec![candidate(tall.clone(), 100), candidate(low.clone(), 50)];
+            eval.pick_move(candidates, 6, &[0; 10], 0).board
+        };
+        assert_eq!(pick(&standard).get_field(), low.get_field());
+        assert_eq!(pick(&raised).get_field(), tall.get_field());
+    }
+
+    #[test]
+    fn lone_spike_scores_worse_than_slope() {
+        // the same number of cells, with the well on the right keeping rows from clearing
+        let spike = solid_board([2, 2, 2, 2, 6, 2, 2, 2, 2, 0]);
+        let slope = solid_board([2, 2, 2, 3, 4, 3, 2, 2, 2, 0]);
+        assert_eq!(surface_spikes(&spike), 4);
+        assert_eq!(surface_spikes(&slope), 0);
+
+        let value = |eval: &Standard, board: &Board| {
+            let lock = LockResult::default();
+            eval.evaluate(&lock, board, 0, 0, Piece::T, board).0.value
+        };
+        let base = Standard::default();
+        let eval = Standard {
+            surface_spikes: -100,
+            ..base.clone()
+        };
+        assert_eq!(value(&eval, &slope), value(&base, &slope));
+        assert_eq!(value(&eval, &spike), value(&base, &spike) - 400);
+        assert!(value(&eval, &spike) < value(&eval, &slope));
+    }
+
+    #[test]
+    fn left_heavy_stack_scores_worse_than_centered() {
+        // the same number of cells, with an empty column keeping rows from clearing
+        let left = solid_board([6, 6, 6, 2, 2, 2, 2, 0, 0, 0]);
+        let centered = solid_board([0, 2, 3, 4, 4, 4, 4, 3, 2, 0]);
+        assert_eq!(stack_imbalance(&left), 4);
+        assert_eq!(stack_imbalance(&centered), 0);
+
+        let value = |eval: &Standard, board: &Board| {
+            let lock = LockResult::default();
+            eval.evaluate(&lock, board, 0, 0, Piece::T, board).0.value
+        };
+        let base = Standard::default();
+        let eval = Standard {
+            stack_balance: -100,
+            ..base.clone()
+        };
+        assert_eq!(value(&eval, &centered), value(&base, &centered));
+        assert_eq!(value(&eval, &left), value(&base, &left) - 400);
+        assert!(value(&eval, &left) < value(&eval, &centered));
+    }
+}
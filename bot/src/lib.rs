@@ -1,14 +1,18 @@
-pub use opening_book::{Book, MemoryBook};
+use enumset::EnumSet;
+pub use opening_book::{Book, BookError, MemoryBook};
 use serde::{Deserialize, Serialize};
 
 mod dag;
 pub mod evaluation;
 mod modes;
+mod openers;
+mod sync;
+pub mod testing;
 
 #[cfg(not(target_arch = "wasm32"))]
 mod desktop;
 #[cfg(not(target_arch = "wasm32"))]
-pub use desktop::Interface;
+pub use desktop::{BotFactory, Interface, SaveStateError};
 
 #[cfg(target_arch = "wasm32")]
 mod web;
@@ -18,18 +22,94 @@ pub use web::Interface;
 
 pub use crate::modes::normal::{BotState, ThinkResult, Thinker};
 pub use crate::modes::pcloop::PcPriority;
+pub use crate::openers::{precompute_openers, BagStart};
+pub use crate::sync::SyncBot;
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Options {
     pub mode: MovementMode,
+    /// Whether the game supports 180 degree rotations. When set, moves may use
+    /// `PieceMovement::Flip`.
+    pub allow_flip: bool,
     pub spawn_rule: SpawnRule,
+    /// The wall kicks of the game's rotation system.
+    pub kick_table: KickTable,
+    /// Which rotations count as spins. Spins of pieces other than T are evaluated as T-spins.
+    pub spin_detection: SpinDetectionMode,
     pub use_hold: bool,
     pub speculate: bool,
+    /// When a new piece changes which placements are best within this many placements of the
+    /// previous plan, the new plan is analyzed further before the next move is suggested. 0
+    /// disables this.
+    pub speculation_replan: u32,
+    /// How much worse a move is considered for each placement in its best line that depends on
+    /// pieces that aren't known yet, in hundredths of a line of garbage. This makes the bot prefer
+    /// moves whose value doesn't rest on speculation. 0 disables this. Only has an effect with
+    /// evaluators that can measure evaluations in lines of garbage.
+    pub speculation_penalty: u32,
     pub pcloop: Option<modes::pcloop::PcPriority>,
+    /// The number of milliseconds to spend looking for a perfect clear before giving up and
+    /// playing normally, or 0 for no limit.
+    pub pcloop_time_limit: u32,
+    /// The number of pieces that need to be known before the bot switches to perfect clear loop
+    /// mode and starts looking for a perfect clear, plus one more if hold is enabled. Perfect
+    /// clears from an empty field take 10 pieces, so with fewer than that the bot goes back to
+    /// playing normally if it needs to move before the rest of the pieces are revealed.
+    pub pcloop_min_pieces: u32,
+    pub report_pc: bool,
+    /// Whether to report the T-spins the bot's plan passes up in `Info::missed_tspins`.
+    pub report_missed_tspins: bool,
+    /// The number of book moves to look ahead when playing a book move. The search will analyze
+    /// this line first, which smooths the transition from book moves to search moves.
+    pub book_transition: u32,
+    /// The most lines waiting to be analyzed that the bot keeps track of, or 0 for no limit. When
+    /// there are too many, the oldest lines are dropped.
+    pub max_forced_lines: u32,
+    /// Moves the bot plays in order at the start of the game before it starts picking moves
+    /// itself, like `Interface::force_opener`. Only used when the bot is launched.
+    pub forced_opener: Option<Vec<FallingPiece>>,
+    /// The number of nodes the search must reach before the bot suggests a move. Moves from the
+    /// opening book or a forced opener are suggested without waiting for this.
     pub min_nodes: u32,
     pub max_nodes: u32,
+    /// Roughly the most memory in bytes that the search tree may use. When the tree grows past
+    /// this, everything except the moves from the current position and the best line is thrown
+    /// away. Nodes thrown away still count towards `min_nodes` and `max_nodes`. This is a soft
+    /// target: it is checked before each expansion, so the tree goes over it by whatever the last
+    /// expansion allocated. Each piece of lookahead reserves about 1 MiB up front, so limits of
+    /// only a few MiB can't be kept. `None` means no limit.
+    pub max_memory_bytes: Option<usize>,
+    /// The number of threads to search with, or 0 to use one thread per CPU core.
     pub threads: u32,
+    /// Seeds the random choices the search makes. With a seed and a single thread, the bot makes
+    /// the same moves every time it is given the same position. `None` picks a random seed.
+    pub seed: Option<u64>,
+    /// How strongly the search favors exploring lower ranked moves over the best moves. Higher
+    /// values make the search wider and shallower. Values below 0.001, including zero and
+    /// negative values, are treated as 0.001.
+    ///
+    /// The default of 1 is the baseline the default weights were tuned with. Very high values
+    /// spread the search almost evenly over all moves.
+    pub exploration: f32,
+    /// The number of ticks between the bot providing a move and its first input taking effect.
+    pub latency_ticks: u32,
+    /// The number of ticks a piece can rest on the stack before it locks, or 0 if pieces only lock
+    /// when dropped. Only used under 20G, where pieces land as soon as they spawn: if the latency
+    /// is at least the lock delay, the piece locks where it lands before any input takes effect.
+    pub lock_delay: u32,
+    /// The number of ticks it takes for a piece to fall one row, or 0 if pieces don't fall. Only
+    /// used to figure out how far pieces fall during `latency_ticks`.
+    pub gravity: u32,
+    /// The number of ticks between a piece locking and the next piece spawning. This is counted
+    /// as part of the time each placement takes.
+    pub spawn_delay: u32,
+    /// The most garbage that can be added to the board after a single placement, or 0 if there is
+    /// no limit. Incoming garbage beyond this amount is ignored when picking a move.
+    pub garbage_cap: u32,
+    /// Among placements that evaluate equally, prefer the one that takes the fewest ticks to
+    /// input.
+    pub prefer_fast_inputs: bool,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -42,8 +122,20 @@ enum BotMsg {
     },
     NewPiece(Piece),
     SuggestMove(u32),
+    ExpectGarbageHoles([u32; 10]),
+    OpponentHeight(u32),
     PlayMove(FallingPiece),
     ForceAnalysisLine(Vec<FallingPiece>),
+    ClearForcedLines,
+    ForceOpener(Vec<FallingPiece>),
+    RestoreState(BotStateSnapshot),
+    UpdateOptions(Options),
+    TraceNextMove,
+    RequestBoard,
+    RequestPlan,
+    WatchCandidates,
+    RequestState,
+    RequestCandidates,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
@@ -54,13 +146,29 @@ pub enum Info {
 }
 
 impl Info {
-    pub fn plan(&self) -> &[(FallingPiece, LockResult)] {
+    pub fn plan(&self) -> &[(FallingPiece, LockResult, bool)] {
         match self {
             Info::Normal(info) => &info.plan,
             Info::PcLoop(info) => &info.plan,
             Info::Book => &[],
         }
     }
+
+    /// The bot's evaluation after each step of `plan` as `(value, spike)`, if known.
+    pub fn plan_evaluations(&self) -> &[(i32, i32)] {
+        match self {
+            Info::Normal(info) => &info.plan_evaluations,
+            _ => &[],
+        }
+    }
+
+    /// The bot's approximate advantage in lines of garbage, if known.
+    pub fn advantage_in_lines(&self) -> Option<f32> {
+        match self {
+            Info::Normal(info) => info.advantage.map(|a| a as f32 / 100.0),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -69,18 +177,281 @@ pub enum BotPollState {
     Dead,
 }
 
+/// Why the bot decided it can't survive.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub enum DeathReason {
+    /// Neither the next piece nor the piece that could be held instead can spawn.
+    BlockOut,
+    /// Every placement of the pieces that can be played would lock entirely above the visible
+    /// field.
+    LockOut,
+    /// There are placements, but the search found that all of them lead to one of the above.
+    NoSurvivableMoves,
+}
+
+/// The height of the tallest column of the field.
+fn field_height(field: &[[bool; 10]; 40]) -> u32 {
+    field
+        .iter()
+        .rposition(|row| row.iter().any(|&c| c))
+        .map_or(0, |y| y as u32 + 1)
+}
+
+/// A snapshot of how far along the bot's search is.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SearchProgress {
+    pub nodes: u32,
+    pub depth: u32,
+    /// The move the bot would make if it was asked for one now.
+    pub best_so_far: Option<FallingPiece>,
+    /// How far ahead the bot expects to be after `best_so_far`, in lines of garbage, if the
+    /// evaluator can estimate it.
+    pub advantage: Option<f32>,
+    /// The fraction of possible moves that the bot hasn't found to lead to death.
+    pub survival: f32,
+}
+
+/// Statistics on how hard the bot is working, from `Interface::stats`.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BotStats {
+    /// The rate nodes have been added to the search tree recently.
+    pub nodes_per_second: f64,
+    /// The number of nodes added to the search tree since the bot was launched.
+    pub total_nodes: u64,
+    /// The number of nodes in the current search tree.
+    pub tree_size: u32,
+    pub depth: u32,
+}
+
+/// What a bot did over its lifetime, from `Interface::shutdown`.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ShutdownReport {
+    /// The number of nodes added to the search tree since the bot was launched.
+    pub total_nodes: u64,
+    /// The most nodes the search tree had at once.
+    pub peak_tree_size: u32,
+    /// The time between launching and shutting down the bot.
+    pub wall_time: std::time::Duration,
+}
+
+/// A placement the bot is considering making with the current piece.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct Candidate {
+    pub mv: FallingPiece,
+    pub hold: bool,
+    pub garbage_sent: u32,
+}
+
+/// A record of how the bot decided on a single move, for debugging.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct MoveTrace {
+    /// The number of nodes in each generation of the search tree when the move was picked,
+    /// starting with the generation of the current piece.
+    pub nodes_per_generation: Vec<u32>,
+    /// The moves the bot could have made, best first.
+    pub candidates: Vec<TracedCandidate>,
+    pub chosen: Move,
+    pub reason: MoveReason,
+}
+
+/// A move the bot considered while making a traced move.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TracedCandidate {
+    pub mv: FallingPiece,
+    pub hold: bool,
+    pub garbage_sent: u32,
+    /// The evaluation of the move as an approximate advantage in lines of garbage, if the
+    /// evaluator supports it.
+    pub advantage: Option<f32>,
+}
+
+/// Why the bot chose a traced move.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub enum MoveReason {
+    /// It was the best move found by the search.
+    Search,
+    /// The evaluator picked a move other than the best one, such as to survive incoming garbage
+    /// or to top out the opponent.
+    Override,
+    /// It was the next move of the forced opener.
+    Opener,
+    /// It was suggested by the opening book.
+    Book,
+    /// It was part of a perfect clear found by the perfect clear looper.
+    PcLoop,
+}
+
+/// The differences between the bot's board and some other board.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct BoardDiff {
+    /// The `(x, y)` coordinates of the cells that differ, with `(0, 0)` being the bottom left.
+    pub cells: Vec<(usize, usize)>,
+    pub hold: bool,
+    pub bag: bool,
+    pub queue: bool,
+    pub combo: bool,
+    pub b2b: bool,
+}
+
+impl BoardDiff {
+    pub fn new(ours: &Board, theirs: &Board) -> Self {
+        let mut cells = vec![];
+        for y in 0..40 {
+            for x in 0..10 {
+                if ours.occupied(x, y) != theirs.occupied(x, y) {
+                    cells.push((x as usize, y as usize));
+                }
+            }
+        }
+        BoardDiff {
+            cells,
+            hold: ours.hold_piece != theirs.hold_piece,
+            bag: ours.bag != theirs.bag,
+            queue: !ours.next_queue().eq(theirs.next_queue()),
+            combo: ours.combo != theirs.combo,
+            b2b: ours.b2b_bonus != theirs.b2b_bonus,
+        }
+    }
+
+    /// Returns true if there are no differences.
+    pub fn is_empty(&self) -> bool {
+        *self == BoardDiff::default()
+    }
+}
+
+/// The position the bot is playing from, without any of the bot's analysis of it.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct BotStateSnapshot {
+    #[serde(with = "BigArray")]
+    pub field: [[bool; 10]; 40],
+    /// The pieces remaining in the bag after the pieces in the next queue.
+    pub bag: EnumSet<Piece>,
+    pub hold: Option<Piece>,
+    pub combo: u32,
+    pub b2b: bool,
+    pub next_queue: Vec<Piece>,
+}
+
+impl BotStateSnapshot {
+    pub fn new(board: &Board) -> Self {
+        BotStateSnapshot {
+            field: board.get_field(),
+            bag: board.bag,
+            hold: board.hold_piece,
+            combo: board.combo,
+            b2b: board.b2b_bonus,
+            next_queue: board.next_queue().collect(),
+        }
+    }
+
+    pub fn to_board(&self) -> Board {
+        let mut board =
+            Board::new_with_state(self.field, EnumSet::all(), self.hold, self.b2b, self.combo);
+        for &piece in &self.next_queue {
+            board.add_next_piece(piece);
+        }
+        board.bag = self.bag;
+        board
+    }
+}
+
 impl Default for Options {
     fn default() -> Self {
         Options {
             mode: MovementMode::ZeroG,
+            allow_flip: false,
             spawn_rule: SpawnRule::Row19Or20,
+            kick_table: KickTable::Srs,
+            spin_detection: SpinDetectionMode::TSpinOnly,
             use_hold: true,
             speculate: true,
+            speculation_replan: 0,
+            speculation_penalty: 0,
             pcloop: None,
+            pcloop_time_limit: 0,
+            pcloop_min_pieces: 10,
+            report_pc: false,
+            report_missed_tspins: false,
+            book_transition: 0,
+            max_forced_lines: 16,
+            forced_opener: None,
             min_nodes: 0,
             max_nodes: 4_000_000_000,
+            max_memory_bytes: None,
             threads: 1,
+            seed: None,
+            exploration: 1.0,
+            latency_ticks: 0,
+            lock_delay: 0,
+            gravity: 0,
+            spawn_delay: 0,
+            garbage_cap: 0,
+            prefer_fast_inputs: false,
+        }
+    }
+}
+
+impl Options {
+    /// Spawns the piece according to the spawn rule and moves it to where it will be by the time
+    /// the first input takes effect. Under 20G, `find_moves` drops the piece to where it lands.
+    fn spawn(&self, piece: Piece, board: &Board) -> Option<FallingPiece> {
+        let mut spawned = self.spawn_rule.spawn(piece, board)?;
+        if self.mode != MovementMode::TwentyG && self.gravity != 0 {
+            for _ in 0..self.latency_ticks / self.gravity {
+                if !spawned.shift(board, 0, -1) {
+                    break;
+                }
+            }
         }
+        Some(spawned)
+    }
+
+    /// Whether pieces lock where they land under 20G before the first input takes effect, so
+    /// neither moving nor holding them is possible.
+    fn locks_before_input(&self) -> bool {
+        self.mode == MovementMode::TwentyG
+            && self.lock_delay != 0
+            && self.latency_ticks >= self.lock_delay
+    }
+
+    /// Finds the placements of `spawned` that can be reached with these options.
+    fn find_moves(&self, board: &Board, mut spawned: FallingPiece) -> Vec<Placement> {
+        if self.locks_before_input() {
+            spawned.sonic_drop(board);
+            return vec![Placement {
+                inputs: InputList {
+                    movements: Default::default(),
+                    time: 0,
+                },
+                location: spawned,
+            }];
+        }
+        find_moves(
+            board,
+            spawned,
+            self.mode,
+            self.allow_flip,
+            self.kick_table,
+            self.spin_detection,
+        )
+    }
+
+    /// Whether `new` only differs from these options in fields that can be changed while the bot
+    /// is running. See `Interface::update_options`.
+    fn can_change_to(&self, new: &Options) -> bool {
+        self.mode == new.mode
+            && self.allow_flip == new.allow_flip
+            && self.spawn_rule == new.spawn_rule
+            && self.kick_table == new.kick_table
+            && self.spin_detection == new.spin_detection
+            && self.use_hold == new.use_hold
+            && self.pcloop == new.pcloop
+            && self.threads == new.threads
+            && self.exploration == new.exploration
+            && self.latency_ticks == new.latency_ticks
+            && self.lock_delay == new.lock_delay
+            && self.gravity == new.gravity
+            && self.spawn_delay == new.spawn_delay
     }
 }
 
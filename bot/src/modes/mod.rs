@@ -4,10 +4,9 @@ use opening_book::Book;
 use serde::{Deserialize, Serialize};
 
 use crate::evaluation::Evaluator;
-use crate::{BotMsg, Info, Move, Options};
+use crate::{BotMsg, Info, Move, MoveReason, MoveTrace, Options};
 
 pub mod normal;
-#[cfg(not(target_arch = "wasm32"))]
 pub mod pcloop;
 
 enum Mode<E: Evaluator> {
@@ -27,41 +26,130 @@ pub(crate) enum TaskResult<V, R> {
     PcLoopSolve(Option<ArrayVec<[FallingPiece; 10]>>),
 }
 
+/// The state of a `ModeSwitchedBot` in a form that can be serialized.
+///
+/// Perfect clear searches aren't saved; a bot in perfect clear mode starts its search over when
+/// it's loaded.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedBot<V, R> {
+    board: Board,
+    options: Options,
+    garbage_holes: [u32; 10],
+    opponent_height: u32,
+    normal: Option<normal::SavedBotState<V, R>>,
+}
+
+impl<V, R> SavedBot<V, R> {
+    /// The state of a bot that hasn't started thinking yet.
+    pub fn unstarted(board: Board, options: Options) -> Self {
+        SavedBot {
+            board,
+            options,
+            garbage_holes: [0; 10],
+            opponent_height: 0,
+            normal: None,
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn options(&self) -> Options {
+        self.options.clone()
+    }
+
+    /// Whether a bot can be loaded from this state, which requires it to have been thinking or
+    /// to know what the next piece is.
+    pub fn can_load(&self) -> bool {
+        self.normal.is_some() || self.board.next_queue().next().is_some()
+    }
+}
+
 pub(crate) struct ModeSwitchedBot<'a, E: Evaluator> {
     mode: Mode<E>,
     options: Options,
     board: Board,
     do_move: Option<u32>,
+    garbage_holes: [u32; 10],
+    opponent_height: u32,
     book: Option<&'a Book>,
+    tracing: bool,
+    trace: Option<MoveTrace>,
 }
 
 impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
-    pub fn new(board: Board, options: Options, book: Option<&'a Book>) -> Self {
-        #[cfg(target_arch = "wasm32")]
-        let mode = Mode::Normal(normal::BotState::new(board.clone(), options));
-        #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(board: Board, mut options: Options, book: Option<&'a Book>) -> Self {
+        let opener = options.forced_opener.take();
         let mode = if options.pcloop.is_some()
             && board.get_row(0).is_empty()
-            && can_pc_loop(&board, options.use_hold)
+            && can_pc_loop(&board, options.use_hold, options.pcloop_min_pieces)
         {
             Mode::PcLoop(pcloop::PcLooper::new(
                 board.clone(),
                 options.use_hold,
                 options.mode,
+                options.kick_table,
+                options.spin_detection,
                 options.pcloop.unwrap(),
+                options.pcloop_time_limit,
+                options.pcloop_min_pieces,
             ))
         } else {
-            Mode::Normal(normal::BotState::new(board.clone(), options))
+            let mut bot = normal::BotState::new(board.clone(), options.clone());
+            if let Some(moves) = opener {
+                bot.force_opener(moves);
+            }
+            Mode::Normal(bot)
         };
         ModeSwitchedBot {
             mode,
             options,
             board,
             do_move: None,
+            garbage_holes: [0; 10],
+            opponent_height: 0,
             book,
+            tracing: false,
+            trace: None,
         }
     }
 
+    pub fn save(&self) -> SavedBot<E::Value, E::Reward> {
+        SavedBot {
+            board: self.board.clone(),
+            options: self.options.clone(),
+            garbage_holes: self.garbage_holes,
+            opponent_height: self.opponent_height,
+            normal: match &self.mode {
+                Mode::Normal(bot) => Some(bot.save()),
+                Mode::PcLoop(_) => None,
+            },
+        }
+    }
+
+    /// Rebuilds a bot saved with `save`. A move that was requested but not provided when it was
+    /// saved is not provided by the loaded bot.
+    pub fn load(saved: SavedBot<E::Value, E::Reward>, book: Option<&'a Book>) -> Self {
+        let mut bot = match saved.normal {
+            Some(normal) => ModeSwitchedBot {
+                mode: Mode::Normal(normal::BotState::load(normal)),
+                options: saved.options,
+                board: saved.board,
+                do_move: None,
+                garbage_holes: [0; 10],
+                opponent_height: 0,
+                book,
+                tracing: false,
+                trace: None,
+            },
+            None => ModeSwitchedBot::new(saved.board, saved.options, book),
+        };
+        bot.garbage_holes = saved.garbage_holes;
+        bot.opponent_height = saved.opponent_height;
+        bot
+    }
+
     pub fn task_complete(&mut self, result: TaskResult<E::Value, E::Reward>) {
         match &mut self.mode {
             Mode::Normal(bot) => match result {
@@ -84,8 +172,10 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                 match &mut self.mode {
                     Mode::Normal(bot) => bot.reset(field, b2b, combo),
                     Mode::PcLoop(_) => {
-                        self.mode =
-                            Mode::Normal(normal::BotState::new(self.board.clone(), self.options))
+                        self.mode = Mode::Normal(normal::BotState::new(
+                            self.board.clone(),
+                            self.options.clone(),
+                        ))
                     }
                 }
             }
@@ -93,30 +183,38 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                 self.board.add_next_piece(piece);
                 match &mut self.mode {
                     Mode::Normal(bot) => {
-                        #[cfg(not(target_arch = "wasm32"))]
-                        {
-                            if self.options.pcloop.is_some()
-                                && can_pc_loop(&self.board, self.options.use_hold)
-                            {
-                                self.mode = Mode::PcLoop(pcloop::PcLooper::new(
-                                    self.board.clone(),
-                                    self.options.use_hold,
-                                    self.options.mode,
-                                    self.options.pcloop.unwrap(),
-                                ));
-                            } else {
-                                bot.add_next_piece(piece);
-                            }
-                        }
-                        #[cfg(target_arch = "wasm32")]
+                        if self.options.pcloop.is_some()
+                            && can_pc_loop(
+                                &self.board,
+                                self.options.use_hold,
+                                self.options.pcloop_min_pieces,
+                            )
                         {
+                            self.mode = Mode::PcLoop(pcloop::PcLooper::new(
+                                self.board.clone(),
+                                self.options.use_hold,
+                                self.options.mode,
+                                self.options.kick_table,
+                                self.options.spin_detection,
+                                self.options.pcloop.unwrap(),
+                                self.options.pcloop_time_limit,
+                                self.options.pcloop_min_pieces,
+                            ));
+                        } else {
                             bot.add_next_piece(piece);
                         }
                     }
                     Mode::PcLoop(bot) => bot.add_next_piece(piece),
                 }
             }
-            BotMsg::SuggestMove(incoming) => self.do_move = Some(incoming),
+            BotMsg::SuggestMove(incoming) => {
+                if let Mode::Normal(bot) = &mut self.mode {
+                    bot.set_incoming(incoming);
+                }
+                self.do_move = Some(incoming);
+            }
+            BotMsg::ExpectGarbageHoles(columns) => self.garbage_holes = columns,
+            BotMsg::OpponentHeight(height) => self.opponent_height = height,
             BotMsg::PlayMove(mv) => {
                 let next = self.board.advance_queue().unwrap();
                 if mv.kind.0 != next {
@@ -127,25 +225,31 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                 self.board.lock_piece(mv);
                 match &mut self.mode {
                     Mode::Normal(bot) => {
-                        #[cfg(not(target_arch = "wasm32"))]
+                        if self.options.pcloop.is_some()
+                            && can_pc_loop(
+                                &self.board,
+                                self.options.use_hold,
+                                self.options.pcloop_min_pieces,
+                            )
                         {
-                            if self.options.pcloop.is_some()
-                                && can_pc_loop(&self.board, self.options.use_hold)
-                            {
-                                self.mode = Mode::PcLoop(pcloop::PcLooper::new(
-                                    self.board.clone(),
-                                    self.options.use_hold,
-                                    self.options.mode,
-                                    self.options.pcloop.unwrap(),
-                                ));
-                                return;
-                            }
+                            self.mode = Mode::PcLoop(pcloop::PcLooper::new(
+                                self.board.clone(),
+                                self.options.use_hold,
+                                self.options.mode,
+                                self.options.kick_table,
+                                self.options.spin_detection,
+                                self.options.pcloop.unwrap(),
+                                self.options.pcloop_time_limit,
+                                self.options.pcloop_min_pieces,
+                            ));
+                            return;
                         }
                         bot.advance_move(mv);
                     }
                     Mode::PcLoop(bot) => {
                         if !bot.play_move(mv) {
-                            let bot = normal::BotState::new(self.board.clone(), self.options);
+                            let bot =
+                                normal::BotState::new(self.board.clone(), self.options.clone());
                             self.mode = Mode::Normal(bot);
                         }
                     }
@@ -155,14 +259,79 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                 Mode::Normal(bot) => bot.force_analysis_line(path),
                 _ => {}
             },
+            BotMsg::ClearForcedLines => match &mut self.mode {
+                Mode::Normal(bot) => bot.clear_forced_lines(),
+                _ => {}
+            },
+            BotMsg::ForceOpener(moves) => match &mut self.mode {
+                Mode::Normal(bot) => bot.force_opener(moves),
+                _ => {}
+            },
+            BotMsg::RestoreState(snapshot) => {
+                let bot =
+                    ModeSwitchedBot::new(snapshot.to_board(), self.options.clone(), self.book);
+                self.mode = bot.mode;
+                self.board = bot.board;
+                self.do_move = None;
+            }
+            BotMsg::UpdateOptions(options) => {
+                // the thread count may have been filled in when the bot was launched
+                self.options = Options {
+                    threads: self.options.threads,
+                    ..options
+                };
+                if let Mode::Normal(bot) = &mut self.mode {
+                    bot.set_options(self.options.clone());
+                }
+            }
+            BotMsg::TraceNextMove => self.tracing = true,
+            // the bot thread is responsible for answering requests for its state
+            BotMsg::RequestBoard
+            | BotMsg::RequestPlan
+            | BotMsg::WatchCandidates
+            | BotMsg::RequestState
+            | BotMsg::RequestCandidates => {}
         }
     }
 
+    /// Forgets about a move requested with `BotMsg::SuggestMove` that hasn't been provided yet.
+    pub fn cancel_move(&mut self) {
+        self.do_move = None;
+    }
+
+    /// Takes the trace of the last move suggested after a `BotMsg::TraceNextMove`, if it hasn't
+    /// been taken yet.
+    pub fn take_trace(&mut self) -> Option<MoveTrace> {
+        self.trace.take()
+    }
+
     pub fn think(&mut self, eval: &E, send_move: impl FnOnce((Move, Info))) -> Vec<Task> {
         match &mut self.mode {
             Mode::Normal(bot) => {
                 if let Some(incoming) = self.do_move {
-                    if let Some(result) = bot.suggest_move(eval, self.book, incoming) {
+                    if self.tracing {
+                        bot.trace_next_move();
+                    }
+                    if let Some(mut result) = bot.suggest_move(
+                        eval,
+                        self.book,
+                        incoming,
+                        &self.garbage_holes,
+                        self.opponent_height,
+                    ) {
+                        if let Info::Normal(info) = &mut result.1 {
+                            if self.options.report_pc {
+                                info.pc_available = pcloop::pieces_to_pc(
+                                    &self.board,
+                                    self.options.use_hold,
+                                    pcloop::PC_CHECK_BUDGET,
+                                );
+                            }
+                        }
+                        if self.tracing {
+                            self.trace = bot.take_trace();
+                            self.tracing = false;
+                        }
                         send_move(result);
                         self.do_move = None;
                     }
@@ -187,12 +356,22 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
                 if let Some(_) = self.do_move {
                     match bot.suggest_move() {
                         Ok((mv, info)) => {
+                            if self.tracing {
+                                self.trace = Some(MoveTrace {
+                                    nodes_per_generation: vec![],
+                                    candidates: vec![],
+                                    chosen: mv.clone(),
+                                    reason: MoveReason::PcLoop,
+                                });
+                                self.tracing = false;
+                            }
                             send_move((mv, Info::PcLoop(info)));
                             self.do_move = None;
                         }
                         Err(false) => {}
                         Err(true) => {
-                            let mut bot = normal::BotState::new(self.board.clone(), self.options);
+                            let mut bot =
+                                normal::BotState::new(self.board.clone(), self.options.clone());
                             let mut thinks = vec![];
                             if let Ok(thinker) = bot.think() {
                                 thinks.push(Task::NormalThink(thinker));
@@ -208,6 +387,48 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
         }
     }
 
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The progress of the search, or `None` if the bot isn't searching.
+    pub fn progress(&self, eval: &E) -> Option<crate::SearchProgress> {
+        match &self.mode {
+            Mode::Normal(bot) => Some(bot.progress(eval)),
+            Mode::PcLoop(_) => None,
+        }
+    }
+
+    /// The number of nodes in the search tree and its depth, or zeros if the bot isn't searching.
+    pub fn search_size(&self) -> (u32, u32) {
+        match &self.mode {
+            Mode::Normal(bot) => bot.search_size(),
+            Mode::PcLoop(_) => (0, 0),
+        }
+    }
+
+    /// The sequence of moves the bot currently expects to play.
+    pub fn plan(&self) -> Vec<(FallingPiece, LockResult, bool)> {
+        match &self.mode {
+            Mode::Normal(bot) => bot.plan(),
+            Mode::PcLoop(bot) => bot.plan(),
+        }
+    }
+
+    pub fn candidates(&self) -> Vec<crate::Candidate> {
+        match &self.mode {
+            Mode::Normal(bot) => bot.candidates(),
+            Mode::PcLoop(_) => vec![],
+        }
+    }
+
+    pub fn death_reason(&self, eval: &E) -> Option<crate::DeathReason> {
+        match &self.mode {
+            Mode::Normal(bot) => bot.death_reason(eval),
+            Mode::PcLoop(_) => None,
+        }
+    }
+
     pub fn is_dead(&self) -> bool {
         if let Mode::Normal(bot) = &self.mode {
             bot.is_dead()
@@ -217,6 +438,16 @@ impl<'a, E: Evaluator> ModeSwitchedBot<'a, E> {
     }
 }
 
+impl<V, R> TaskResult<V, R> {
+    /// The number of nodes this result adds to the search tree.
+    pub fn nodes(&self) -> u64 {
+        match self {
+            TaskResult::NormalThink(result) => result.nodes(),
+            TaskResult::PcLoopSolve(_) => 0,
+        }
+    }
+}
+
 impl Task {
     pub fn execute<E: Evaluator>(self, eval: &E) -> TaskResult<E::Value, E::Reward> {
         match self {
@@ -226,65 +457,147 @@ impl Task {
     }
 }
 
-fn can_pc_loop(board: &Board, hold_enabled: bool) -> bool {
+fn can_pc_loop(board: &Board, hold_enabled: bool, min_pieces: u32) -> bool {
     if board.get_row(0) != <u16 as Row>::EMPTY {
         return false;
     }
     let pieces = board.next_queue().count();
     if hold_enabled {
         let pieces = pieces + board.hold_piece.is_some() as usize;
-        pieces >= 11
+        pieces > min_pieces as usize
     } else {
-        pieces >= 10
+        pieces >= min_pieces as usize
     }
 }
 
-#[cfg(target_arch = "wasm32")]
-/// dummy wasm32 types because pcf can't really work on web until wasm threads come out
-pub mod pcloop {
-    use arrayvec::ArrayVec;
-    use libtetris::{FallingPiece, LockResult, Piece};
-    use serde::{Deserialize, Serialize};
-
-    use crate::Move;
+#[cfg(test)]
+mod tests {
+    use crate::evaluation::Standard;
+    use crate::SyncBot;
 
-    #[derive(Serialize, Deserialize)]
-    pub struct PcSolver;
-    #[derive(Serialize, Deserialize)]
-    pub struct PcLooper;
+    use super::*;
 
-    impl PcLooper {
-        pub fn add_next_piece(&mut self, _: Piece) {
-            unreachable!()
+    /// A board with the bottom 4 rows filled except for the rightmost column.
+    fn tetris_ready(queue: &[Piece]) -> Board {
+        let mut field = [[false; 10]; 40];
+        for row in &mut field[..4] {
+            for cell in &mut row[..9] {
+                *cell = true;
+            }
         }
-        pub fn think(&mut self) -> Option<PcSolver> {
-            unreachable!()
+        let mut board = Board::new();
+        board.set_field(field);
+        for &piece in queue {
+            board.add_next_piece(piece);
         }
-        pub fn suggest_move(&mut self) -> Result<(Move, Info), bool> {
-            unreachable!()
+        board
+    }
+
+    #[test]
+    fn reports_pc_in_normal_mode() {
+        let board = tetris_ready(&[Piece::I, Piece::T, Piece::O, Piece::S]);
+        let options = Options {
+            report_pc: true,
+            seed: Some(0),
+            ..Options::default()
+        };
+        let mut bot = SyncBot::new(board, options, Standard::default(), None);
+        match bot.think_until(100) {
+            Some((_, Info::Normal(info))) => assert_eq!(info.pc_available, Some(1)),
+            _ => panic!("expected a move from the normal search"),
         }
-        pub fn play_move(&mut self, mv: FallingPiece) -> bool {
-            unreachable!()
+    }
+
+    #[test]
+    fn gives_up_on_slow_pc_and_falls_back_to_normal_mode() {
+        // no perfect clear can be made with only S pieces
+        let mut board = Board::new();
+        for _ in 0..12 {
+            board.add_next_piece(Piece::S);
         }
-        pub fn solution(&mut self, _: Option<ArrayVec<[FallingPiece; 10]>>) {
-            unreachable!()
+        let options = Options {
+            pcloop: Some(pcloop::PcPriority::Fastest),
+            pcloop_time_limit: 100,
+            seed: Some(0),
+            ..Options::default()
+        };
+        let mut bot = ModeSwitchedBot::<Standard>::new(board.clone(), options.clone(), None);
+        match &mut bot.mode {
+            Mode::PcLoop(looper) => {
+                let solver = looper.think().unwrap();
+                looper.solution(solver.solve());
+                assert!(looper.think().is_none());
+                assert!(matches!(looper.suggest_move(), Err(true)));
+            }
+            Mode::Normal(_) => panic!("expected perfect clear loop mode"),
         }
-    }
 
-    impl PcSolver {
-        pub fn solve(&self) -> Option<ArrayVec<[FallingPiece; 10]>> {
-            unreachable!()
+        let mut bot = SyncBot::new(board, options, Standard::default(), None);
+        match bot.think_until(100) {
+            Some((_, Info::Normal(_))) => {}
+            _ => panic!("expected a move from the normal search"),
         }
     }
 
-    #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
-    pub struct Info {
-        pub plan: Vec<(FallingPiece, LockResult)>,
+    #[test]
+    fn forced_opener_option_is_played_before_search() {
+        let drop_piece = |piece, board: &Board| {
+            let mut mv = SpawnRule::Row19Or20.spawn(piece, board).unwrap();
+            mv.sonic_drop(board);
+            mv
+        };
+        let mut board = Board::new();
+        for &piece in &[Piece::O, Piece::T, Piece::I, Piece::L, Piece::J] {
+            board.add_next_piece(piece);
+        }
+        // the T covers a hole next to the O, which the search would never choose to do
+        let first = drop_piece(Piece::O, &board);
+        let mut after_first = board.clone();
+        crate::dag::advance(&mut after_first, first);
+        let second = drop_piece(Piece::T, &after_first);
+
+        let options = Options {
+            forced_opener: Some(vec![first, second]),
+            use_hold: false,
+            seed: Some(0),
+            ..Options::default()
+        };
+        let mut bot = SyncBot::new(board, options, Standard::default(), None);
+        for expected in &[first, second] {
+            let (mv, _) = bot.think_until(100).unwrap();
+            assert!(mv.expected_location.same_location(expected));
+            bot.play_next_move(mv.expected_location);
+        }
+        match bot.think_until(100) {
+            Some((mv, Info::Normal(_))) => assert_eq!(mv.expected_location.kind.0, Piece::I),
+            _ => panic!("expected a move from the normal search"),
+        }
     }
 
-    #[derive(Copy, Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
-    pub enum PcPriority {
-        Fastest,
-        HighestAttack,
+    #[test]
+    fn lower_pc_threshold_enters_pc_loop_with_9_pieces() {
+        use Piece::*;
+        let mut board = Board::new();
+        for &piece in &[I, O, T, L, J, S, Z, I, O] {
+            board.add_next_piece(piece);
+        }
+        let options = Options {
+            pcloop: Some(pcloop::PcPriority::Fastest),
+            use_hold: false,
+            ..Options::default()
+        };
+
+        let bot = ModeSwitchedBot::<Standard>::new(board.clone(), options.clone(), None);
+        assert!(matches!(bot.mode, Mode::Normal(_)));
+
+        let options = Options {
+            pcloop_min_pieces: 9,
+            ..options
+        };
+        let bot = ModeSwitchedBot::<Standard>::new(board, options, None);
+        match bot.mode {
+            Mode::PcLoop(mut looper) => assert!(looper.think().is_some()),
+            Mode::Normal(_) => panic!("expected perfect clear loop mode"),
+        }
     }
 }
@@ -1,25 +1,43 @@
+use std::collections::VecDeque;
+
+use arrayvec::ArrayVec;
 use enum_map::EnumMap;
 use libtetris::*;
 use opening_book::Book;
 use serde::{Deserialize, Serialize};
 
 // use crate::tree::{ ChildData, TreeState, NodeId };
-use crate::dag::{ChildData, DagState, NodeId};
-use crate::evaluation::Evaluator;
+use crate::dag::{advance, ChildData, DagState, MoveCandidate, NodeId, SavedDag};
+use crate::evaluation::{find_tslot, Evaluator};
 use crate::Options;
 
 pub struct BotState<E: Evaluator> {
     tree: DagState<E::Value, E::Reward>,
     options: Options,
     forced_analysis_lines: Vec<Vec<FallingPiece>>,
+    book_line: Option<(FallingPiece, Vec<FallingPiece>)>,
+    opener: VecDeque<FallingPiece>,
+    tracing: bool,
+    trace: Option<crate::MoveTrace>,
+    incoming: u32,
     pub outstanding_thinks: u32,
 }
 
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedBotState<V, R> {
+    tree: SavedDag<V, R>,
+    options: Options,
+    forced_analysis_lines: Vec<Vec<FallingPiece>>,
+    book_line: Option<(FallingPiece, Vec<FallingPiece>)>,
+    opener: VecDeque<FallingPiece>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Thinker {
     node: NodeId,
     board: Board,
     options: Options,
+    incoming: u32,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -29,12 +47,57 @@ pub enum ThinkResult<V, R> {
     Unmark(NodeId),
 }
 
+impl<V, R> ThinkResult<V, R> {
+    pub fn nodes(&self) -> u64 {
+        match self {
+            ThinkResult::Known(_, children) => children.len() as u64,
+            ThinkResult::Speculated(_, children) => children
+                .values()
+                .flatten()
+                .map(|children| children.len() as u64)
+                .sum(),
+            ThinkResult::Unmark(_) => 0,
+        }
+    }
+}
+
 impl<E: Evaluator> BotState<E> {
     pub fn new(board: Board, options: Options) -> Self {
         BotState {
-            tree: DagState::new(board, options.use_hold),
+            tree: DagState::new(board, options.use_hold, options.exploration, options.seed),
             options,
             forced_analysis_lines: vec![],
+            book_line: None,
+            opener: VecDeque::new(),
+            tracing: false,
+            trace: None,
+            incoming: 0,
+            outstanding_thinks: 0,
+        }
+    }
+
+    pub(crate) fn save(&self) -> SavedBotState<E::Value, E::Reward> {
+        SavedBotState {
+            tree: self.tree.save(),
+            options: self.options.clone(),
+            forced_analysis_lines: self.forced_analysis_lines.clone(),
+            book_line: self.book_line.clone(),
+            opener: self.opener.clone(),
+        }
+    }
+
+    /// Rebuilds a bot saved with `save`. Thinks which were outstanding when it was saved are
+    /// forgotten.
+    pub(crate) fn load(saved: SavedBotState<E::Value, E::Reward>) -> Self {
+        BotState {
+            tree: DagState::load(saved.tree, saved.options.seed),
+            options: saved.options,
+            forced_analysis_lines: saved.forced_analysis_lines,
+            book_line: saved.book_line,
+            opener: saved.opener,
+            tracing: false,
+            trace: None,
+            incoming: 0,
             outstanding_thinks: 0,
         }
     }
@@ -43,6 +106,11 @@ impl<E: Evaluator> BotState<E> {
     ///
     /// Returns `Err(true)` if a thinking cycle can be preformed, but it couldn't find
     pub fn think(&mut self) -> Result<Thinker, bool> {
+        if let Some(max) = self.options.max_memory_bytes {
+            if self.tree.memory_usage() > max {
+                self.tree.prune();
+            }
+        }
         if (!self.min_thinking_reached() || self.tree.nodes() < self.options.max_nodes)
             && !self.tree.is_dead()
         {
@@ -54,7 +122,8 @@ impl<E: Evaluator> BotState<E> {
                 return Ok(Thinker {
                     node,
                     board,
-                    options: self.options,
+                    options: self.options.clone(),
+                    incoming: self.incoming,
                 });
             } else {
                 return Err(true);
@@ -73,13 +142,90 @@ impl<E: Evaluator> BotState<E> {
         }
     }
 
+    /// Records how the next move suggested by `suggest_move` is chosen, which can then be
+    /// retrieved with `take_trace`.
+    pub fn trace_next_move(&mut self) {
+        self.tracing = true;
+    }
+
+    pub fn take_trace(&mut self) -> Option<crate::MoveTrace> {
+        self.trace.take()
+    }
+
+    /// Replaces the options. Only options which don't affect the existing search tree may differ.
+    pub(crate) fn set_options(&mut self, options: Options) {
+        self.options = options;
+    }
+
+    /// Sets the amount of garbage the bot expects to receive, which is taken into account when
+    /// evaluating placements from now on.
+    pub fn set_incoming(&mut self, incoming: u32) {
+        self.incoming = self.cap_incoming(incoming);
+    }
+
+    fn cap_incoming(&self, incoming: u32) -> u32 {
+        match self.options.garbage_cap {
+            0 => incoming,
+            cap => incoming.min(cap),
+        }
+    }
+
     pub fn is_dead(&self) -> bool {
         self.tree.is_dead()
     }
 
+    /// Figures out why the bot is dead, or returns `None` if it isn't.
+    pub fn death_reason(&self, eval: &E) -> Option<crate::DeathReason> {
+        if !self.tree.is_dead() {
+            return None;
+        }
+        let board = self.tree.board();
+        let mut pieces = ArrayVec::<[_; 2]>::new();
+        if let Ok(next) = board.get_next_piece() {
+            pieces.push(next);
+        }
+        if self.options.use_hold {
+            if let Some(held) = board.hold_piece.or(board.get_next_next_piece()) {
+                pieces.push(held);
+            }
+        }
+        let spawned: ArrayVec<[_; 2]> = pieces
+            .iter()
+            .filter_map(|&p| self.options.spawn(p, board))
+            .collect();
+        if spawned.is_empty() {
+            return Some(crate::DeathReason::BlockOut);
+        }
+        let all_lock_out = spawned.iter().all(|&spawned| {
+            self.options
+                .find_moves(board, spawned)
+                .iter()
+                .all(|mv| locked_out(&mv.location, eval.vanish_zone_top()))
+        });
+        if all_lock_out {
+            Some(crate::DeathReason::LockOut)
+        } else {
+            Some(crate::DeathReason::NoSurvivableMoves)
+        }
+    }
+
     /// Adds a new piece to the queue.
     pub fn add_next_piece(&mut self, piece: Piece) {
+        if self.options.speculation_replan == 0 {
+            self.tree.add_next_piece(piece);
+            return;
+        }
+        let plan = self.tree.get_plan();
         self.tree.add_next_piece(piece);
+        let new_plan = self.tree.get_plan();
+        let diverged = plan
+            .iter()
+            .zip(&new_plan)
+            .take(self.options.speculation_replan as usize)
+            .any(|(old, new)| old.0 != new.0);
+        if diverged {
+            self.push_forced_line(new_plan.into_iter().map(|p| p.0).collect());
+        }
     }
 
     pub fn reset(&mut self, field: [[bool; 10]; 40], b2b: bool, combo: u32) {
@@ -96,7 +242,7 @@ impl<E: Evaluator> BotState<E> {
                 mv.y += garbage_lines;
                 prev_best_path.push(mv);
             }
-            self.forced_analysis_lines.push(prev_best_path);
+            self.push_forced_line(prev_best_path);
         } else {
             self.forced_analysis_lines.clear();
         }
@@ -113,17 +259,26 @@ impl<E: Evaluator> BotState<E> {
         eval: &E,
         book: Option<&Book>,
         incoming: u32,
+        garbage_holes: &[u32; 10],
+        opponent_height: u32,
     ) -> Option<(Move, crate::Info)> {
-        if !self.min_thinking_reached() {
-            return None;
-        }
-
-        let candidates = self.tree.get_next_candidates();
+        let mut candidates = self.tree.get_next_candidates();
         if candidates.is_empty() {
             return None;
         }
+        let mut opener_move = None;
+        if let Some(next) = self.opener.front() {
+            opener_move = candidates
+                .iter()
+                .find(|c| c.mv.same_location(next))
+                .cloned();
+            if opener_move.is_none() {
+                // the opener can't be continued from here
+                self.opener.clear();
+            }
+        }
         let mut book_move = None;
-        if let Some(book) = book {
+        if let (Some(book), None) = (book, &opener_move) {
             if self.tree.board().column_heights().iter().all(|&h| h <= 10) {
                 book_move = book.suggest_move(self.tree.board());
             }
@@ -140,10 +295,81 @@ impl<E: Evaluator> BotState<E> {
         if picked.is_none() && book_move.is_some() {
             dbg!("book picked a move we can't do?");
         }
-        let child = picked.unwrap_or_else(|| eval.pick_move(candidates, incoming));
 
-        let plan = if book_move.is_none() {
-            self.tree.get_plan()
+        // Book and opener moves are played right away, but moves picked by the search have to
+        // wait for the minimum amount of thinking.
+        if opener_move.is_none() && picked.is_none() {
+            if !self.min_thinking_reached() {
+                return None;
+            }
+            if self.options.prefer_fast_inputs {
+                self.sort_by_input_time(&mut candidates);
+            }
+            if self.options.speculation_penalty != 0 {
+                self.sort_by_speculation(eval, &mut candidates);
+            }
+        }
+        let incoming = self.cap_incoming(incoming);
+        let candidate_attacks = candidates
+            .iter()
+            .map(|c| (c.mv, c.lock.garbage_sent))
+            .collect();
+        let traced_candidates = if self.tracing {
+            candidates
+                .iter()
+                .map(|c| crate::TracedCandidate {
+                    mv: c.mv,
+                    hold: c.hold,
+                    garbage_sent: c.lock.garbage_sent,
+                    advantage: eval.advantage_in_lines(&c.evaluation),
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        let reason = if opener_move.is_some() {
+            crate::MoveReason::Opener
+        } else if picked.is_some() {
+            crate::MoveReason::Book
+        } else {
+            crate::MoveReason::Search
+        };
+        let best = candidates[0].mv;
+        let child = opener_move.or(picked).unwrap_or_else(|| {
+            eval.pick_move(candidates, incoming, garbage_holes, opponent_height)
+        });
+        let reason = match reason {
+            crate::MoveReason::Search if child.mv != best => crate::MoveReason::Override,
+            reason => reason,
+        };
+
+        if let (Some(book), Some(_)) = (book, book_move) {
+            // Remember where the book would go after this move so that if the book runs out of
+            // moves soon, the search will at least consider continuing in the same style.
+            self.book_line = Some((
+                child.mv,
+                book_continuation(
+                    book,
+                    self.tree.board(),
+                    child.mv,
+                    self.options.book_transition,
+                ),
+            ));
+        }
+
+        let (plan, plan_evaluations) = if book_move.is_none() {
+            let evaluations = self.tree.get_plan_evaluations();
+            let evaluations = evaluations
+                .iter()
+                .filter_map(|e| eval.raw_evaluation(e))
+                .collect();
+            (self.tree.get_plan(), evaluations)
+        } else {
+            (vec![], vec![])
+        };
+
+        let missed_tspins = if self.options.report_missed_tspins {
+            missed_tspins(self.tree.board(), &plan)
         } else {
             vec![]
         };
@@ -164,36 +390,172 @@ impl<E: Evaluator> BotState<E> {
                 },
                 original_rank: child.original_rank,
                 plan,
+                pc_available: None,
+                candidate_attacks,
+                missed_tspins,
+                budget_exhausted: self.tree.nodes() >= self.options.max_nodes,
+                advantage: eval
+                    .advantage_in_lines(&child.evaluation)
+                    .map(|lines| (lines * 100.0) as i32),
+                evaluation: eval.raw_evaluation(&child.evaluation),
+                plan_evaluations,
             })
         };
 
-        let inputs = find_moves(
-            self.tree.board(),
-            self.options
-                .spawn_rule
-                .spawn(child.mv.kind.0, self.tree.board())
-                .unwrap(),
-            self.options.mode,
-        )
-        .into_iter()
-        .find(|p| p.location == child.mv)
-        .unwrap()
-        .inputs;
+        let spawned = self
+            .options
+            .spawn(child.mv.kind.0, self.tree.board())
+            .unwrap();
+        let inputs = self
+            .options
+            .find_moves(self.tree.board(), spawned)
+            .into_iter()
+            .find(|p| p.location == child.mv)
+            .unwrap()
+            .inputs;
         let mv = Move {
             hold: child.hold,
             inputs: inputs.movements,
             expected_location: child.mv,
         };
 
+        if self.tracing {
+            self.tracing = false;
+            self.trace = Some(crate::MoveTrace {
+                nodes_per_generation: self.tree.nodes_per_generation(),
+                candidates: traced_candidates,
+                chosen: mv.clone(),
+                reason,
+            });
+        }
+
         return Some((mv, info));
     }
 
+    /// Sorts candidates best to worst, breaking ties between equally evaluated placements by how
+    /// many ticks they take to input.
+    fn sort_by_input_time(&self, candidates: &mut Vec<MoveCandidate<E::Value>>) {
+        let board = self.tree.board();
+        let mut times = EnumMap::<Piece, Option<Vec<(FallingPiece, u32)>>>::default();
+        for c in candidates.iter() {
+            let moves = &mut times[c.mv.kind.0];
+            if moves.is_none() {
+                *moves = self.options.spawn(c.mv.kind.0, board).map(|spawned| {
+                    self.options
+                        .find_moves(board, spawned)
+                        .into_iter()
+                        .map(|p| (p.location, p.inputs.time))
+                        .collect()
+                });
+            }
+        }
+        let time_of = |c: &MoveCandidate<E::Value>| {
+            times[c.mv.kind.0]
+                .as_ref()
+                .and_then(|moves| moves.iter().find(|&&(mv, _)| mv == c.mv))
+                .map_or(u32::MAX, |&(_, time)| time + c.hold as u32)
+        };
+        candidates.sort_by_cached_key(|c| (std::cmp::Reverse(c.evaluation.clone()), time_of(c)));
+    }
+
+    /// Sorts candidates best to worst after penalizing each one by `Options::speculation_penalty`
+    /// for every speculated placement in its best line. Nothing changes if the evaluator can't
+    /// measure evaluations in lines of garbage.
+    fn sort_by_speculation(&self, eval: &E, candidates: &mut Vec<MoveCandidate<E::Value>>) {
+        let penalty = self.options.speculation_penalty as f32 / 100.0;
+        let scored: Option<Vec<_>> = candidates
+            .iter()
+            .map(|c| {
+                eval.advantage_in_lines(&c.evaluation)
+                    .map(|lines| lines - penalty * c.speculated_depth as f32)
+            })
+            .collect();
+        if let Some(scored) = scored {
+            let mut scored: Vec<_> = scored.into_iter().zip(candidates.drain(..)).collect();
+            scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+            candidates.extend(scored.into_iter().map(|(_, c)| c));
+        }
+    }
+
     pub fn advance_move(&mut self, mv: FallingPiece) {
         self.tree.advance_move(mv);
+        if self
+            .opener
+            .front()
+            .map_or(false, |next| next.same_location(&mv))
+        {
+            self.opener.pop_front();
+        } else {
+            self.opener.clear();
+        }
+        if let Some((book_move, line)) = self.book_line.take() {
+            if book_move.same_location(&mv) && !line.is_empty() {
+                self.push_forced_line(line);
+            }
+        }
     }
 
     pub fn force_analysis_line(&mut self, path: Vec<FallingPiece>) {
+        self.push_forced_line(path);
+    }
+
+    /// Adds a line to analyze, dropping the oldest lines if there are more than
+    /// `Options::max_forced_lines`.
+    fn push_forced_line(&mut self, path: Vec<FallingPiece>) {
         self.forced_analysis_lines.push(path);
+        let max = self.options.max_forced_lines as usize;
+        if max != 0 && self.forced_analysis_lines.len() > max {
+            let excess = self.forced_analysis_lines.len() - max;
+            self.forced_analysis_lines.drain(..excess);
+        }
+    }
+
+    pub fn clear_forced_lines(&mut self) {
+        self.forced_analysis_lines.clear();
+    }
+
+    pub fn progress(&self, eval: &E) -> crate::SearchProgress {
+        let candidates = self.tree.get_next_candidates();
+        let children = self.tree.root_children();
+        crate::SearchProgress {
+            nodes: self.tree.nodes(),
+            depth: self.tree.depth(),
+            best_so_far: candidates.first().map(|c| c.mv),
+            advantage: candidates
+                .first()
+                .and_then(|c| eval.advantage_in_lines(&c.evaluation)),
+            survival: if children == 0 {
+                0.0
+            } else {
+                candidates.len() as f32 / children as f32
+            },
+        }
+    }
+
+    pub fn search_size(&self) -> (u32, u32) {
+        (self.tree.nodes(), self.tree.depth())
+    }
+
+    pub fn plan(&self) -> Vec<(FallingPiece, LockResult, bool)> {
+        self.tree.get_plan()
+    }
+
+    /// The moves currently being considered for the next placement, best first.
+    pub fn candidates(&self) -> Vec<crate::Candidate> {
+        self.tree
+            .get_next_candidates()
+            .into_iter()
+            .map(|c| crate::Candidate {
+                mv: c.mv,
+                hold: c.hold,
+                garbage_sent: c.lock.garbage_sent,
+            })
+            .collect()
+    }
+
+    pub fn force_opener(&mut self, moves: Vec<FallingPiece>) {
+        self.push_forced_line(moves.clone());
+        self.opener = moves.into();
     }
 }
 
@@ -250,21 +612,21 @@ impl Thinker {
         let mut children = vec![];
 
         let next = board.advance_queue().unwrap();
-        let spawned = match self.options.spawn_rule.spawn(next, &board) {
+        let spawned = match self.options.spawn(next, &board) {
             Some(spawned) => spawned,
             None => return children,
         };
 
         self.add_children(&mut children, &board, eval, spawned, false);
 
-        if self.options.use_hold {
+        if self.options.use_hold && !self.options.locks_before_input() {
             let hold = board
                 .hold(next)
                 .unwrap_or_else(|| board.advance_queue().unwrap());
             if hold == next {
                 return children;
             }
-            let spawned = match self.options.spawn_rule.spawn(hold, &board) {
+            let spawned = match self.options.spawn(hold, &board) {
                 Some(spawned) => spawned,
                 None => return children,
             };
@@ -283,15 +645,25 @@ impl Thinker {
         spawned: FallingPiece,
         hold: bool,
     ) {
-        for mv in find_moves(&board, spawned, self.options.mode) {
+        for mv in self.options.find_moves(&board, spawned) {
             let can_be_hd =
                 board.above_stack(&mv.location) && board.column_heights().iter().all(|&y| y < 18);
             let mut result = board.clone();
             let lock = result.lock_piece(mv.location);
             // Don't add deaths by lock out, don't add useless mini tspins
-            if !lock.locked_out && !(can_be_hd && lock.placement_kind == PlacementKind::MiniTspin) {
-                let move_time = mv.inputs.time + if hold { 1 } else { 0 };
-                let (evaluation, reward) = eval.evaluate(&lock, &result, move_time, spawned.kind.0);
+            if !locked_out(&mv.location, eval.vanish_zone_top())
+                && !(can_be_hd && lock.placement_kind == PlacementKind::MiniTspin)
+            {
+                let move_time =
+                    mv.inputs.time + self.options.spawn_delay + if hold { 1 } else { 0 };
+                let (evaluation, reward) = eval.evaluate(
+                    &lock,
+                    &result,
+                    move_time,
+                    self.incoming,
+                    spawned.kind.0,
+                    board,
+                );
                 children.push(ChildData {
                     evaluation,
                     reward,
@@ -303,10 +675,723 @@ impl Thinker {
     }
 }
 
+/// Whether `placement` is entirely at or above `vanish_zone_top`, which ends the game.
+fn locked_out(placement: &FallingPiece, vanish_zone_top: i32) -> bool {
+    placement.cells().iter().all(|&(_, y)| y >= vanish_zone_top)
+}
+
+/// Walks the plan and finds the T-slots the bot could have used but didn't.
+fn missed_tspins(
+    board: &Board,
+    plan: &[(FallingPiece, LockResult, bool)],
+) -> Vec<(u32, FallingPiece)> {
+    let mut board = board.clone();
+    let mut missed = vec![];
+    for (i, &(placement, ref lock, _)) in plan.iter().enumerate() {
+        if let Some(slot) = find_tslot(&board) {
+            let mut queue = board.next_queue();
+            let current = queue.next();
+            let other = board.hold_piece.or_else(|| queue.next());
+            let t_available = current == Some(Piece::T) || other == Some(Piece::T);
+            let used = match lock.placement_kind {
+                PlacementKind::Tspin1 | PlacementKind::Tspin2 | PlacementKind::Tspin3 => true,
+                _ => false,
+            };
+            if t_available && !used {
+                missed.push((i as u32, slot));
+            }
+        }
+        advance(&mut board, placement);
+    }
+    missed
+}
+
+/// Follows the book for up to `pieces` moves after the specified move is played.
+fn book_continuation(
+    book: &Book,
+    board: &Board,
+    mv: FallingPiece,
+    pieces: u32,
+) -> Vec<FallingPiece> {
+    let mut board = board.clone();
+    advance(&mut board, mv);
+    let mut line = vec![];
+    for _ in 0..pieces {
+        match book.suggest_move(&board) {
+            Some(mv) => {
+                advance(&mut board, mv);
+                line.push(mv);
+            }
+            None => break,
+        }
+    }
+    line
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub struct Info {
     pub nodes: u32,
     pub depth: u32,
     pub original_rank: u32,
-    pub plan: Vec<(FallingPiece, LockResult)>,
+    /// The placements the bot expects to make, the result of each, and whether hold is used
+    /// before each one.
+    pub plan: Vec<(FallingPiece, LockResult, bool)>,
+    /// If `Options::report_pc` is set, the number of pieces needed to perfect clear if one can be
+    /// made with the known pieces.
+    pub pc_available: Option<u32>,
+    /// The moves the bot could have made along with the amount of garbage each would send, in
+    /// order of preference.
+    pub candidate_attacks: Vec<(FallingPiece, u32)>,
+    /// If `Options::report_missed_tspins` is set, the T-slots along the plan that a T piece was
+    /// available for but that the plan doesn't use for a T-spin, along with the index of the plan
+    /// step at which each was passed up.
+    pub missed_tspins: Vec<(u32, FallingPiece)>,
+    /// Whether thinking stopped because `Options::max_nodes` was reached rather than the bot
+    /// being asked for a move or running out of things to think about.
+    pub budget_exhausted: bool,
+    /// The bot's evaluation of the move it picked as an approximate advantage in hundredths of a
+    /// line of garbage, if the evaluator supports it.
+    pub advantage: Option<i32>,
+    /// The bot's evaluation of the move it picked as `(value, spike)` in the evaluator's own
+    /// units, if the evaluator supports it. For `Standard`, higher is better and one line of
+    /// garbage is worth roughly 200.
+    pub evaluation: Option<(i32, i32)>,
+    /// The bot's evaluation after each step of the plan as `(value, spike)`, in the same units as
+    /// `evaluation`. Empty if the evaluator doesn't support it.
+    pub plan_evaluations: Vec<(i32, i32)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluation::Standard;
+    use crate::{Info as BotInfo, Move, SyncBot};
+
+    use super::*;
+
+    /// Builds a board from rows of `#` and `.` given from the top down, so the last row is the
+    /// bottom of the playfield.
+    pub(crate) fn board_from(rows: &[&str], queue: &[Piece]) -> Board {
+        let mut field = [[false; 10]; 40];
+        for (y, row) in rows.iter().rev().enumerate() {
+            for (x, cell) in row.bytes().enumerate() {
+                field[y][x] = cell == b'#';
+            }
+        }
+        let mut board = Board::new();
+        board.set_field(field);
+        for &piece in queue {
+            board.add_next_piece(piece);
+        }
+        board
+    }
+
+    pub(crate) fn think(board: Board, options: Options, nodes: u32) -> (Move, Info) {
+        let options = Options {
+            seed: Some(0),
+            ..options
+        };
+        let mut bot = SyncBot::new(board, options, Standard::default(), None);
+        match bot.think_until(nodes) {
+            Some((mv, BotInfo::Normal(info))) => (mv, info),
+            _ => panic!("expected a move from the normal search"),
+        }
+    }
+
+    #[test]
+    fn tetris_candidate_reports_more_attack_than_single() {
+        let board = board_from(
+            &[
+                "....######",
+                ".#########",
+                ".#########",
+                ".#########",
+                ".#########",
+            ],
+            &[Piece::I, Piece::T, Piece::O],
+        );
+        let (_, info) = think(board.clone(), Options::default(), 100);
+        let attack_for_lines = |lines: usize| {
+            info.candidate_attacks
+                .iter()
+                .find(|&&(mv, _)| {
+                    mv.kind.0 == Piece::I
+                        && board.clone().lock_piece(mv).cleared_lines.len() == lines
+                })
+                .map(|&(_, attack)| attack)
+                .expect("no candidate clears that many lines")
+        };
+        assert!(attack_for_lines(4) > attack_for_lines(1));
+    }
+
+    #[test]
+    fn latency_accounts_for_falling_piece() {
+        let board = board_from(&[".#........"; 15], &[Piece::T, Piece::O, Piece::I]);
+        let leftmost = |latency_ticks| {
+            let options = Options {
+                latency_ticks,
+                gravity: 1,
+                ..Options::default()
+            };
+            let (_, info) = think(board.clone(), options, 50);
+            info.candidate_attacks
+                .iter()
+                .filter(|(mv, _)| mv.kind.0 == Piece::T)
+                .flat_map(|(mv, _)| mv.cells().iter().map(|&(x, _)| x).collect::<Vec<_>>())
+                .min()
+                .unwrap()
+        };
+        // the wall only blocks the piece once it has fallen below the top of it
+        assert_eq!(leftmost(0), 0);
+        assert!(leftmost(10) >= 2);
+    }
+
+    #[test]
+    fn latency_past_lock_delay_locks_piece_where_it_lands_in_20g() {
+        let board = board_from(&["....#....."; 4], &[Piece::T, Piece::O, Piece::I]);
+        let candidates = |latency_ticks| {
+            let options = Options {
+                mode: MovementMode::TwentyG,
+                latency_ticks,
+                lock_delay: 30,
+                ..Options::default()
+            };
+            let (_, info) = think(board.clone(), options, 50);
+            info.candidate_attacks
+                .iter()
+                .map(|&(mv, _)| mv)
+                .collect::<Vec<_>>()
+        };
+        assert!(candidates(10).len() > 1);
+
+        // the T lands on the pillar and locks there before it can be moved or held
+        let locked = candidates(30);
+        assert_eq!(locked.len(), 1);
+        assert!(locked[0].same_location(&drop_piece(Piece::T, &board)));
+    }
+
+    /// Drops the piece straight down from where it spawns.
+    pub(crate) fn drop_piece(piece: Piece, board: &Board) -> FallingPiece {
+        let mut mv = SpawnRule::Row19Or20.spawn(piece, board).unwrap();
+        mv.sonic_drop(board);
+        mv
+    }
+
+    #[test]
+    fn book_continuation_is_analyzed_after_book_move() {
+        let board = board_from(
+            &[],
+            &[
+                Piece::I,
+                Piece::O,
+                Piece::T,
+                Piece::L,
+                Piece::J,
+                Piece::S,
+                Piece::Z,
+                Piece::I,
+            ],
+        );
+        let book_move = drop_piece(Piece::I, &board);
+        let mut after_book_move = board.clone();
+        advance(&mut after_book_move, book_move);
+        let continuation = drop_piece(Piece::O, &after_book_move);
+        let mut after_continuation = after_book_move.clone();
+        advance(&mut after_continuation, continuation);
+
+        let mut builder = opening_book::BookBuilder::new();
+        builder.add_move(&board, book_move, None);
+        builder.add_move(&after_book_move, continuation, Some(1.0));
+        builder.recalculate_graph();
+        let book = Book::from(builder.compile(&[(&board).into()]));
+
+        let eval = Standard::default();
+        let options = Options {
+            book_transition: 1,
+            seed: Some(0),
+            ..Options::default()
+        };
+        let mut bot = BotState::<Standard>::new(board, options);
+        let think_once = |bot: &mut BotState<Standard>| {
+            let thinker = bot.think().unwrap();
+            let board = thinker.board.clone();
+            bot.finish_thinking(thinker.think(&eval));
+            board
+        };
+        think_once(&mut bot);
+        let (mv, _) = bot
+            .suggest_move(&eval, Some(&book), 0, &[0; 10], 0)
+            .unwrap();
+        assert!(mv.expected_location.same_location(&book_move));
+        bot.advance_move(mv.expected_location);
+
+        // the new root is expanded first, then the book's continuation is analyzed
+        think_once(&mut bot);
+        let analyzed = think_once(&mut bot);
+        assert_eq!(
+            analyzed.column_heights(),
+            after_continuation.column_heights()
+        );
+    }
+
+    /// Records the move time of every placement it evaluates.
+    struct MoveTimes(Standard, std::sync::Mutex<Vec<u32>>);
+
+    impl Evaluator for MoveTimes {
+        type Value = <Standard as Evaluator>::Value;
+        type Reward = <Standard as Evaluator>::Reward;
+
+        fn name(&self) -> String {
+            self.0.name()
+        }
+
+        fn evaluate(
+            &self,
+            lock: &LockResult,
+            board: &Board,
+            move_time: u32,
+            incoming: u32,
+            placed: Piece,
+            parent: &Board,
+        ) -> (Self::Value, Self::Reward) {
+            self.1.lock().unwrap().push(move_time);
+            self.0
+                .evaluate(lock, board, move_time, incoming, placed, parent)
+        }
+    }
+
+    #[test]
+    fn spawn_delay_counts_toward_move_time() {
+        let move_times = |spawn_delay| {
+            let board = board_from(&[], &[Piece::T, Piece::O, Piece::I]);
+            let options = Options {
+                spawn_delay,
+                seed: Some(0),
+                ..Options::default()
+            };
+            let eval = MoveTimes(Standard::default(), Default::default());
+            let mut bot = BotState::<MoveTimes>::new(board, options);
+            let thinker = bot.think().unwrap();
+            thinker.think(&eval);
+            eval.1.into_inner().unwrap()
+        };
+        let without_delay = move_times(0);
+        let with_delay = move_times(7);
+        assert!(!without_delay.is_empty());
+        assert_eq!(
+            with_delay,
+            without_delay.iter().map(|t| t + 7).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn budget_exhausted_only_when_max_nodes_reached() {
+        let board = board_from(&[], &[Piece::T, Piece::O]);
+        let options = Options {
+            max_nodes: 10,
+            ..Options::default()
+        };
+        let (_, info) = think(board.clone(), options, 1000);
+        assert!(info.budget_exhausted);
+
+        let (_, info) = think(board, Options::default(), 1000);
+        assert!(!info.budget_exhausted);
+    }
+
+    /// Records the incoming garbage it is told about when picking moves.
+    struct PickIncoming(Standard, std::sync::Arc<std::sync::Mutex<Vec<u32>>>);
+
+    impl Evaluator for PickIncoming {
+        type Value = <Standard as Evaluator>::Value;
+        type Reward = <Standard as Evaluator>::Reward;
+
+        fn name(&self) -> String {
+            self.0.name()
+        }
+
+        fn evaluate(
+            &self,
+            lock: &LockResult,
+            board: &Board,
+            move_time: u32,
+            incoming: u32,
+            placed: Piece,
+            parent: &Board,
+        ) -> (Self::Value, Self::Reward) {
+            self.0
+                .evaluate(lock, board, move_time, incoming, placed, parent)
+        }
+
+        fn pick_move(
+            &self,
+            candidates: Vec<MoveCandidate<Self::Value>>,
+            incoming: u32,
+            garbage_holes: &[u32; 10],
+            opponent_height: u32,
+        ) -> MoveCandidate<Self::Value> {
+            self.1.lock().unwrap().push(incoming);
+            self.0
+                .pick_move(candidates, incoming, garbage_holes, opponent_height)
+        }
+    }
+
+    #[test]
+    fn garbage_cap_limits_incoming_when_picking() {
+        let board = board_from(&["#########."; 12], &[Piece::T, Piece::O, Piece::L]);
+        let incoming_seen = |garbage_cap| {
+            let options = Options {
+                garbage_cap,
+                seed: Some(0),
+                ..Options::default()
+            };
+            let seen = std::sync::Arc::default();
+            let eval = PickIncoming(Standard::default(), std::sync::Arc::clone(&seen));
+            let mut bot = SyncBot::new(board.clone(), options, eval, None);
+            bot.set_incoming(10);
+            bot.think_until(100).unwrap();
+            let seen = seen.lock().unwrap().clone();
+            seen
+        };
+        assert_eq!(incoming_seen(0), vec![10]);
+        // only what can arrive after this placement is considered, so the bot doesn't panic about
+        // the rest of the queue
+        assert_eq!(incoming_seen(2), vec![2]);
+    }
+
+    #[test]
+    fn plan_reports_early_hold() {
+        let board = board_from(
+            &["#########."; 4],
+            &[Piece::O, Piece::I, Piece::T, Piece::L, Piece::J],
+        );
+        let (mv, info) = think(board, Options::default(), 500);
+        assert!(mv.hold);
+        let (first, lock, hold) = &info.plan[0];
+        assert_eq!(first.kind.0, Piece::I);
+        assert_eq!(lock.cleared_lines.len(), 4);
+        assert!(hold);
+        // the O is in hold now, so the next step either places the T or swaps it for the O
+        let (second, _, hold) = &info.plan[1];
+        assert_eq!(*hold, second.kind.0 == Piece::O);
+    }
+
+    #[test]
+    fn surprising_piece_analyzes_new_plan() {
+        let board = board_from(&[], &[Piece::T, Piece::I, Piece::O]);
+        let options = Options {
+            speculation_replan: 3,
+            seed: Some(0),
+            ..Options::default()
+        };
+        let eval = Standard::default();
+        let mut surprises = 0;
+        for piece in [Piece::S, Piece::Z, Piece::L, Piece::J] {
+            let mut bot = BotState::<Standard>::new(board.clone(), options.clone());
+            for _ in 0..200 {
+                match bot.think() {
+                    Ok(thinker) => bot.finish_thinking(thinker.think(&eval)),
+                    Err(_) => break,
+                }
+            }
+            let plan: Vec<_> = bot.tree.get_plan().into_iter().map(|p| p.0).collect();
+            bot.add_next_piece(piece);
+            let new_plan: Vec<_> = bot.tree.get_plan().into_iter().map(|p| p.0).collect();
+            if plan.iter().zip(&new_plan).take(3).any(|(a, b)| a != b) {
+                surprises += 1;
+                // the new plan is what gets expanded next
+                assert_eq!(bot.forced_analysis_lines, vec![new_plan]);
+            } else {
+                assert!(bot.forced_analysis_lines.is_empty());
+            }
+        }
+        assert!(surprises > 0);
+    }
+
+    #[test]
+    fn advantage_sign_follows_position() {
+        let queue = [Piece::T, Piece::I, Piece::O, Piece::L, Piece::J];
+        let clean = board_from(&[], &queue);
+        let messy = board_from(
+            &[
+                "####.#####",
+                "###.######",
+                "#####.####",
+                "#.########",
+                "######.###",
+                "##.#######",
+                "########.#",
+                "####.#####",
+                ".#########",
+                "#######.##",
+                "###.######",
+                "#####.####",
+                "#.########",
+                "######.###",
+            ],
+            &queue,
+        );
+        let (_, winning) = think(clean, Options::default(), 300);
+        let (_, losing) = think(messy, Options::default(), 300);
+        assert!(winning.advantage.unwrap() > 0);
+        assert!(losing.advantage.unwrap() < 0);
+    }
+
+    #[test]
+    fn forced_opener_is_played_before_search() {
+        let board = board_from(&[], &[Piece::O, Piece::T, Piece::I, Piece::L, Piece::J]);
+        // the T covers a hole next to the O, which the search would never choose to do
+        let first = drop_piece(Piece::O, &board);
+        let mut after_first = board.clone();
+        advance(&mut after_first, first);
+        let second = drop_piece(Piece::T, &after_first);
+
+        let eval = Standard::default();
+        let options = Options {
+            use_hold: false,
+            seed: Some(0),
+            ..Options::default()
+        };
+        let mut bot = BotState::<Standard>::new(board, options);
+        bot.force_opener(vec![first, second]);
+        let next_move = |bot: &mut BotState<Standard>| {
+            for _ in 0..100 {
+                match bot.think() {
+                    Ok(thinker) => bot.finish_thinking(thinker.think(&eval)),
+                    Err(_) => break,
+                }
+            }
+            bot.trace_next_move();
+            let (mv, _) = bot.suggest_move(&eval, None, 0, &[0; 10], 0).unwrap();
+            bot.advance_move(mv.expected_location);
+            (mv.expected_location, bot.take_trace().unwrap().reason)
+        };
+
+        let (mv, reason) = next_move(&mut bot);
+        assert!(mv.same_location(&first));
+        assert_eq!(reason, crate::MoveReason::Opener);
+        let (mv, reason) = next_move(&mut bot);
+        assert!(mv.same_location(&second));
+        assert_eq!(reason, crate::MoveReason::Opener);
+
+        let (mv, reason) = next_move(&mut bot);
+        assert_eq!(mv.kind.0, Piece::I);
+        assert_ne!(reason, crate::MoveReason::Opener);
+    }
+
+    #[test]
+    fn cleared_forced_line_is_not_prioritized() {
+        let board = board_from(&[], &[Piece::T, Piece::I, Piece::O, Piece::L]);
+        let eval = Standard::default();
+        let options = Options {
+            seed: Some(0),
+            ..Options::default()
+        };
+        let next_leaf = |clear: bool| {
+            let mut bot = BotState::<Standard>::new(board.clone(), options.clone());
+            let thinker = bot.think().unwrap();
+            bot.finish_thinking(thinker.think(&eval));
+            let worst = bot.tree.get_next_candidates().pop().unwrap();
+            bot.force_analysis_line(vec![worst.mv]);
+            if clear {
+                bot.clear_forced_lines();
+            }
+            let thinker = bot.think().unwrap();
+            thinker.board.get_field() == worst.board.get_field()
+        };
+        assert!(next_leaf(false));
+        assert!(!next_leaf(true));
+    }
+
+    #[test]
+    fn memory_cap_bounds_the_tree() {
+        const CAP: usize = 8 << 20;
+        let eval = Standard::default();
+        let think = |max_memory_bytes| {
+            let board = board_from(&[], &[Piece::T, Piece::I, Piece::O, Piece::L, Piece::S]);
+            let options = Options {
+                max_memory_bytes,
+                seed: Some(0),
+                ..Options::default()
+            };
+            let mut bot = BotState::<Standard>::new(board, options);
+            let mut peak = 0;
+            for _ in 0..2000 {
+                match bot.think() {
+                    Ok(thinker) => bot.finish_thinking(thinker.think(&eval)),
+                    Err(_) => break,
+                }
+                peak = peak.max(bot.tree.memory_usage());
+            }
+            (bot, peak)
+        };
+
+        let (_, uncapped) = think(None);
+        assert!(uncapped > 2 * CAP);
+
+        // the cap is checked before each expansion, so the tree only goes past it by what the last
+        // expansion allocated
+        let (mut bot, peak) = think(Some(CAP));
+        assert!(peak <= CAP + (1 << 20));
+        let board = bot.tree.board().clone();
+        let (mv, _) = bot.suggest_move(&eval, None, 0, &[0; 10], 0).unwrap();
+        assert!(board.is_placement_legal(&mv.expected_location));
+    }
+
+    #[test]
+    fn forced_line_is_visited_first() {
+        let board = board_from(&[], &[Piece::T, Piece::I, Piece::O, Piece::L]);
+        let eval = Standard::default();
+        let options = Options {
+            seed: Some(0),
+            ..Options::default()
+        };
+        let mut bot = BotState::<Standard>::new(board, options.clone());
+        let thinker = bot.think().unwrap();
+        bot.finish_thinking(thinker.think(&eval));
+
+        // the worst T placement followed by the worst I placement after it
+        let worst = bot.tree.get_next_candidates().pop().unwrap();
+        let spawned = options.spawn(Piece::I, &worst.board).unwrap();
+        let second = find_moves(
+            &worst.board,
+            spawned,
+            options.mode,
+            options.allow_flip,
+            options.kick_table,
+            options.spin_detection,
+        )
+        .pop()
+        .unwrap()
+        .location;
+        let mut after_second = worst.board.clone();
+        after_second.lock_piece(second);
+
+        bot.force_analysis_line(vec![worst.mv, second]);
+        for expected in &[&worst.board, &after_second] {
+            let thinker = bot.think().unwrap();
+            assert_eq!(thinker.board.get_field(), expected.get_field());
+            bot.finish_thinking(thinker.think(&eval));
+        }
+        assert!(bot.forced_analysis_lines.is_empty());
+    }
+
+    #[test]
+    fn forced_lines_past_cap_drop_oldest() {
+        let board = board_from(&[], &[Piece::T, Piece::I, Piece::O, Piece::L]);
+        let eval = Standard::default();
+        let options = Options {
+            max_forced_lines: 2,
+            seed: Some(0),
+            ..Options::default()
+        };
+        let mut bot = BotState::<Standard>::new(board, options);
+        let thinker = bot.think().unwrap();
+        bot.finish_thinking(thinker.think(&eval));
+
+        let mut candidates = bot.tree.get_next_candidates();
+        let lines: Vec<_> = candidates.drain(candidates.len() - 3..).collect();
+        for c in &lines {
+            bot.force_analysis_line(vec![c.mv]);
+        }
+        assert_eq!(
+            bot.forced_analysis_lines,
+            vec![vec![lines[1].mv], vec![lines[2].mv]]
+        );
+        let thinker = bot.think().unwrap();
+        let analyzed = thinker.board.get_field();
+        assert!(lines[1..].iter().any(|c| c.board.get_field() == analyzed));
+    }
+
+    #[test]
+    fn known_line_preferred_over_deep_speculation() {
+        let board = board_from(&[], &[Piece::T, Piece::I, Piece::O]);
+        let eval = Standard::default();
+        let options = Options {
+            speculation_penalty: 100,
+            seed: Some(0),
+            ..Options::default()
+        };
+        let mut bot = BotState::<Standard>::new(board, options);
+        let thinker = bot.think().unwrap();
+        bot.finish_thinking(thinker.think(&eval));
+
+        // the best move rests on a long speculated line, the runner up only on known pieces
+        let all = bot.tree.get_next_candidates();
+        let mut risky = all[0].clone();
+        let mut known = all
+            .iter()
+            .find(|c| c.evaluation < risky.evaluation)
+            .unwrap()
+            .clone();
+        risky.speculated_depth = 20;
+        known.speculated_depth = 0;
+        let lines = |c: &MoveCandidate<_>| eval.advantage_in_lines(&c.evaluation).unwrap();
+        assert!(lines(&risky) - lines(&known) < 20.0);
+        let mut candidates = vec![risky.clone(), known.clone()];
+        let (risky, known) = (risky.mv, known.mv);
+
+        bot.sort_by_speculation(&eval, &mut candidates);
+        assert_eq!(candidates[0].mv, known);
+        assert_eq!(candidates[1].mv, risky);
+    }
+
+    #[test]
+    fn skipped_tsd_is_reported() {
+        let board = board_from(
+            &["####......", "###...####", "####.#####"],
+            &[Piece::T, Piece::O],
+        );
+        let slot = find_tslot(&board).unwrap();
+
+        // the T is dropped on top of the slot instead of spinning into it
+        let mut plan = vec![];
+        let mut after = board.clone();
+        for &piece in &[Piece::T, Piece::O] {
+            let mv = drop_piece(piece, &after);
+            let mut locked = after.clone();
+            locked.advance_queue();
+            let lock = locked.lock_piece(mv);
+            assert_eq!(lock.placement_kind, PlacementKind::None);
+            plan.push((mv, lock, false));
+            advance(&mut after, mv);
+        }
+        assert_eq!(missed_tspins(&board, &plan), vec![(0, slot)]);
+    }
+
+    #[test]
+    fn tied_candidates_sorted_by_input_time() {
+        let board = board_from(&[], &[Piece::T, Piece::I, Piece::O]);
+        let eval = Standard::default();
+        let options = Options {
+            use_hold: false,
+            prefer_fast_inputs: true,
+            seed: Some(0),
+            ..Options::default()
+        };
+        let mut bot = BotState::<Standard>::new(board.clone(), options.clone());
+        let thinker = bot.think().unwrap();
+        bot.finish_thinking(thinker.think(&eval));
+
+        let mut candidates = bot.tree.get_next_candidates();
+        for c in &mut candidates {
+            c.evaluation = Default::default();
+        }
+        bot.sort_by_input_time(&mut candidates);
+
+        let mut parent = board.clone();
+        parent.advance_queue();
+        let spawned = options.spawn(Piece::T, &parent).unwrap();
+        let moves = find_moves(
+            &parent,
+            spawned,
+            options.mode,
+            false,
+            options.kick_table,
+            options.spin_detection,
+        );
+        let time_of =
+            |mv: FallingPiece| moves.iter().find(|p| p.location == mv).unwrap().inputs.time;
+        let times: Vec<_> = candidates.iter().map(|c| time_of(c.mv)).collect();
+        assert!(times.len() > 1);
+        assert!(times.windows(2).all(|w| w[0] <= w[1]));
+    }
 }
@@ -1,10 +1,14 @@
 use std::collections::VecDeque;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use arrayvec::ArrayVec;
+#[cfg(not(target_arch = "wasm32"))]
 use crossbeam_channel::{unbounded, Sender};
-use libtetris::{Board, FallingPiece, LockResult, MovementMode, Piece};
+use libtetris::{
+    Board, FallingPiece, KickTable, LockResult, MovementMode, Piece, SpinDetectionMode,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::Move;
@@ -13,36 +17,81 @@ pub struct PcLooper {
     current_pc: VecDeque<(Move, LockResult)>,
     abort: Arc<AtomicBool>,
     mode: MovementMode,
+    kick_table: KickTable,
+    spin_detection: SpinDetectionMode,
     next_pc_queue: VecDeque<Piece>,
     next_pc_hold: Option<Piece>,
+    next_pc_b2b: bool,
     hold_enabled: bool,
     solving: bool,
+    gave_up: bool,
     priority: PcPriority,
+    time_limit: Option<Duration>,
+    deadline: Option<Instant>,
+    min_pieces: usize,
 }
 
+/// A perfect clear search. Each search has its own abort flag, so aborting a search that has run
+/// out of time can't affect the next one. On the web, solvers are sent to workers and solved on a
+/// single thread, so they can't be aborted and the time limit is not enforced.
+#[derive(Serialize, Deserialize)]
 pub struct PcSolver {
+    #[serde(skip)]
     abort: Arc<AtomicBool>,
-    queue: ArrayVec<[pcf::Piece; 11]>,
+    queue: ArrayVec<[Piece; 11]>,
     hold_enabled: bool,
+    b2b: bool,
     priority: PcPriority,
+    #[serde(skip)]
+    deadline: Option<Instant>,
 }
 
 impl PcLooper {
-    pub fn new(board: Board, hold_enabled: bool, mode: MovementMode, priority: PcPriority) -> Self {
+    /// If `time_limit` is not 0, solving for a perfect clear is abandoned after that many
+    /// milliseconds, and the looper gives up if it has no perfect clear left to execute.
+    ///
+    /// A search starts once `min_pieces` pieces are known, plus one more if hold is enabled.
+    pub fn new(
+        board: Board,
+        hold_enabled: bool,
+        mode: MovementMode,
+        kick_table: KickTable,
+        spin_detection: SpinDetectionMode,
+        priority: PcPriority,
+        time_limit: u32,
+        min_pieces: u32,
+    ) -> Self {
         PcLooper {
             current_pc: VecDeque::new(),
             abort: Arc::new(AtomicBool::new(false)),
             next_pc_queue: board.next_queue().collect(),
             next_pc_hold: if hold_enabled { board.hold_piece } else { None },
+            next_pc_b2b: board.b2b_bonus,
             hold_enabled,
             solving: false,
+            gave_up: false,
             mode,
+            kick_table,
+            spin_detection,
             priority,
+            time_limit: match time_limit {
+                0 => None,
+                ms => Some(Duration::from_millis(ms as u64)),
+            },
+            deadline: None,
+            // solutions never use more than 10 pieces
+            min_pieces: (min_pieces as usize).min(10),
         }
     }
 
     pub fn think(&mut self) -> Option<PcSolver> {
         if self.solving {
+            if expired(self.deadline) {
+                self.abort.store(true, Ordering::Relaxed);
+            }
+            return None;
+        }
+        if self.gave_up {
             return None;
         }
 
@@ -53,16 +102,20 @@ impl PcLooper {
             .chain(self.next_pc_queue.iter())
             .take(11)
         {
-            queue.push(piece.into());
+            queue.push(piece);
         }
 
-        if !self.hold_enabled && queue.len() >= 10 || queue.len() >= 11 {
+        if queue.len() >= self.min_pieces + self.hold_enabled as usize {
             self.solving = true;
+            self.abort = Arc::new(AtomicBool::new(false));
+            self.deadline = deadline(self.time_limit);
             Some(PcSolver {
                 abort: self.abort.clone(),
                 queue,
                 hold_enabled: self.hold_enabled,
+                b2b: self.next_pc_b2b,
                 priority: self.priority,
+                deadline: self.deadline,
             })
         } else {
             None
@@ -71,10 +124,15 @@ impl PcLooper {
 
     pub fn solution(&mut self, soln: Option<ArrayVec<[FallingPiece; 10]>>) {
         self.solving = false;
-        self.abort.store(false, Ordering::Relaxed);
+        self.deadline = None;
+        if soln.is_none() && self.time_limit.is_some() && self.current_pc.is_empty() {
+            // Retrying with the same queue won't find anything within the limit either
+            self.gave_up = true;
+        }
 
         if let Some(soln) = soln {
             let mut b = Board::<u16>::new();
+            b.b2b_bonus = self.next_pc_b2b;
             let mut solution = ArrayVec::<[_; 10]>::new();
             let mut next_pc_hold = self.next_pc_hold;
             let mut next_pc_queue = self.next_pc_queue.clone();
@@ -85,6 +143,9 @@ impl PcLooper {
                         .spawn(placement.kind.0, &b)
                         .unwrap(),
                     self.mode,
+                    false,
+                    self.kick_table,
+                    self.spin_detection,
                 );
 
                 let mut mv = None;
@@ -118,6 +179,9 @@ impl PcLooper {
 
                     solution.push((mv, b.lock_piece(placement)));
                 } else {
+                    // pcf only knows SRS, so the solution may need kicks the configured kick
+                    // table doesn't have. Solving the same queue again would find it again.
+                    self.gave_up = true;
                     return;
                 }
             }
@@ -127,6 +191,7 @@ impl PcLooper {
             }
             self.next_pc_queue = next_pc_queue;
             self.next_pc_hold = next_pc_hold;
+            self.next_pc_b2b = b.b2b_bonus;
         }
     }
 
@@ -138,17 +203,25 @@ impl PcLooper {
                     plan: vec![],
                 };
                 for (mv, lock) in &self.current_pc {
-                    info.plan.push((mv.expected_location, lock.clone()));
+                    info.plan
+                        .push((mv.expected_location, lock.clone(), mv.hold));
                 }
                 Ok((mv.clone(), info))
             }
             None => {
                 self.abort.store(true, Ordering::Relaxed);
-                Err(!self.solving)
+                Err(!self.solving || self.gave_up)
             }
         }
     }
 
+    pub fn plan(&self) -> Vec<(FallingPiece, LockResult, bool)> {
+        self.current_pc
+            .iter()
+            .map(|(mv, lock)| (mv.expected_location, lock.clone(), mv.hold))
+            .collect()
+    }
+
     pub fn play_move(&mut self, mv: FallingPiece) -> bool {
         if let Some((mov, _)) = self.current_pc.pop_front() {
             if mov.expected_location.same_location(&mv) {
@@ -173,93 +246,253 @@ impl Drop for PcLooper {
 }
 
 impl PcSolver {
-    pub fn solve(&self) -> Option<ArrayVec<[FallingPiece; 10]>> {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn find_best(&self, queue: &[pcf::Piece]) -> Option<ScoredSolution> {
         let (send, recv) = unbounded();
 
         let mut best = SendOnDrop::new(None, send);
         pcf::solve_pc_mt(
-            &self.queue,
+            queue,
             pcf::BitBoard(0),
             self.hold_enabled,
             false,
             &self.abort,
             pcf::placeability::simple_srs_spins,
-            move |soln| {
-                let soln: ArrayVec<[_; 10]> = soln.iter().copied().collect();
-                let mut score = PcScore::default();
-                let mut b = pcf::BitBoard(0);
-                let mut prev_full = 0;
-                for &placement in &soln[..soln.len() - 1] {
-                    if !pcf::placeability::hard_drop_only(b, placement) {
-                        score.long_delays += 1;
-                    }
-                    b = b.combine(placement.board());
-                    let mut full = 0;
-                    for y in 0..4 {
-                        if b.line_filled(y) {
-                            full += 1;
-                        }
-                    }
-                    if full != prev_full {
-                        score.long_delays += 1;
-                    }
-                    let lines_cleared = full - prev_full;
-                    let tspin = check_tspin(placement, b);
-                    match (lines_cleared, tspin) {
-                        (1, true) => score.attack += 2,
-                        (2, false) => score.attack += 1,
-                        (2, true) => score.attack += 4,
-                        (3, false) => score.attack += 2,
-                        _ => {}
-                    }
-                    prev_full = full;
-                }
-                if !pcf::placeability::hard_drop_only(b, *soln.last().unwrap()) {
-                    score.last_placement_long = true;
-                }
-                match *best {
-                    None => *best = Some((soln, score)),
-                    Some((_, s)) => {
-                        if self.priority.cmp(score, s) == std::cmp::Ordering::Greater {
-                            *best = Some((soln, score));
-                        }
-                    }
-                }
-            },
+            move |soln| self.consider(&mut best, soln),
         );
 
         let mut best = None;
         for candidate in recv {
             if let Some((soln, score)) = candidate {
-                match best {
-                    None => best = Some((soln, score)),
-                    Some((_, s)) => {
-                        if self.priority.cmp(score, s) == std::cmp::Ordering::Greater {
-                            best = Some((soln, score))
-                        }
-                    }
+                self.consider_scored(&mut best, soln, score);
+            }
+        }
+        best
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn find_best(&self, queue: &[pcf::Piece]) -> Option<ScoredSolution> {
+        self.find_best_single_threaded(queue)
+    }
+
+    /// Solves on the calling thread, since the web has no threads to spread the search across.
+    #[cfg(any(target_arch = "wasm32", test))]
+    fn find_best_single_threaded(&self, queue: &[pcf::Piece]) -> Option<ScoredSolution> {
+        let mut best = None;
+        pcf::solve_pc(
+            queue,
+            pcf::BitBoard(0),
+            self.hold_enabled,
+            false,
+            &self.abort,
+            pcf::placeability::simple_srs_spins,
+            |soln| self.consider(&mut best, soln),
+        );
+        best
+    }
+
+    fn consider(&self, best: &mut Option<ScoredSolution>, soln: &[pcf::Placement]) {
+        // the search can only be checked on between solutions, so it stops after the first
+        // solution found once time is up
+        if expired(self.deadline) {
+            self.abort.store(true, Ordering::Relaxed);
+        }
+        let soln: ArrayVec<[_; 10]> = soln.iter().copied().collect();
+        let score = score_solution(&soln, self.b2b);
+        self.consider_scored(best, soln, score);
+    }
+
+    fn consider_scored(
+        &self,
+        best: &mut Option<ScoredSolution>,
+        soln: ArrayVec<[pcf::Placement; 10]>,
+        score: PcScore,
+    ) {
+        match *best {
+            None => *best = Some((soln, score)),
+            Some((_, s)) => {
+                if self.priority.cmp(score, s) == std::cmp::Ordering::Greater {
+                    *best = Some((soln, score));
                 }
             }
         }
+    }
 
-        best.map(|(soln, _)| {
-            let mut result = ArrayVec::new();
-            let mut b = pcf::BitBoard(0);
-            for &placement in &soln {
-                let piece = placement.srs_piece(b)[0];
-                result.push(piece.into());
-                b = b.combine(placement.board());
+    pub fn solve(&self) -> Option<ArrayVec<[FallingPiece; 10]>> {
+        let queue: ArrayVec<[pcf::Piece; 11]> = self.queue.iter().map(|&p| p.into()).collect();
+        self.find_best(&queue)
+            .map(|(soln, _)| falling_pieces(&soln))
+    }
+}
+
+/// Converts the placements of a solution to where the pieces are placed on the board.
+fn falling_pieces(soln: &[pcf::Placement]) -> ArrayVec<[FallingPiece; 10]> {
+    let mut result = ArrayVec::new();
+    let mut b = pcf::BitBoard(0);
+    for &placement in soln {
+        let piece = placement.srs_piece(b)[0];
+        result.push(piece.into());
+        b = b.combine(placement.board());
+    }
+    result
+}
+
+/// When a search starting now should be abandoned. The web has no clock, so searches there never
+/// run out of time.
+fn deadline(time_limit: Option<Duration>) -> Option<Instant> {
+    if cfg!(target_arch = "wasm32") {
+        None
+    } else {
+        time_limit.map(|limit| Instant::now() + limit)
+    }
+}
+
+fn expired(deadline: Option<Instant>) -> bool {
+    deadline.map_or(false, |deadline| Instant::now() >= deadline)
+}
+
+/// How many placements `pieces_to_pc` may check before giving up when it's asked while suggesting
+/// a move, so that a hard queue can't hold up the move.
+pub const PC_CHECK_BUDGET: u32 = 200_000;
+
+/// Checks whether a perfect clear can be made using the pieces in the hold slot and next queue.
+///
+/// Only perfect clears that finish within the bottom 4 rows of the field are considered. Returns
+/// the number of pieces that need to be placed to achieve it. The search is abandoned once
+/// `budget` placements have been checked without finding a perfect clear, in which case `None` is
+/// returned.
+pub fn pieces_to_pc(board: &Board, hold_enabled: bool, budget: u32) -> Option<u32> {
+    if board.column_heights().iter().any(|&h| h > 4) {
+        return None;
+    }
+    let mut bitboard = pcf::BitBoard(0);
+    for y in 0..4 {
+        bitboard.0 |= (*board.get_row(y) as u64) << (10 * y);
+    }
+    let empty_cells = 40 - bitboard.0.count_ones();
+    if empty_cells % 4 != 0 {
+        return None;
+    }
+    let pieces = empty_cells / 4;
+
+    let mut queue = ArrayVec::<[pcf::Piece; 11]>::new();
+    let hold = if hold_enabled { board.hold_piece } else { None };
+    for piece in hold
+        .into_iter()
+        .chain(board.next_queue())
+        .take(pieces as usize + hold_enabled as usize)
+    {
+        queue.push(piece.into());
+    }
+    if queue.len() < pieces as usize + hold_enabled as usize {
+        return None;
+    }
+
+    // we only care that some solution exists, so stop at the first one found
+    let abort = AtomicBool::new(false);
+    let found = AtomicBool::new(false);
+    let checked = AtomicU32::new(0);
+    let placeable = |b, placement| {
+        if checked.fetch_add(1, Ordering::Relaxed) >= budget {
+            abort.store(true, Ordering::Relaxed);
+            return false;
+        }
+        pcf::placeability::simple_srs_spins(b, placement)
+    };
+    let found_one = |_: &[pcf::Placement]| {
+        found.store(true, Ordering::Relaxed);
+        abort.store(true, Ordering::Relaxed);
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    pcf::solve_pc_mt(
+        &queue,
+        bitboard,
+        hold_enabled,
+        false,
+        &abort,
+        placeable,
+        found_one,
+    );
+    #[cfg(target_arch = "wasm32")]
+    pcf::solve_pc(
+        &queue,
+        bitboard,
+        hold_enabled,
+        false,
+        &abort,
+        placeable,
+        found_one,
+    );
+    if found.load(Ordering::Relaxed) {
+        Some(pieces)
+    } else {
+        None
+    }
+}
+
+/// Scores a perfect clear solution by how long it takes to place and how much it attacks. `b2b`
+/// is whether back-to-back is active before the first piece of the solution is placed.
+fn score_solution(soln: &[pcf::Placement], mut b2b: bool) -> PcScore {
+    let mut score = PcScore::default();
+    let mut b = pcf::BitBoard(0);
+    let mut prev_full = 0;
+    for (i, &placement) in soln.iter().enumerate() {
+        let last = i == soln.len() - 1;
+        if !pcf::placeability::hard_drop_only(b, placement) {
+            if last {
+                score.last_placement_long = true;
+            } else {
+                score.long_delays += 1;
+            }
+        }
+        b = b.combine(placement.board());
+        let mut full = 0;
+        for y in 0..4 {
+            if b.line_filled(y) {
+                full += 1;
             }
-            result
-        })
+        }
+        if full != prev_full && !last {
+            score.long_delays += 1;
+        }
+        let lines_cleared = full - prev_full;
+        let tspin = check_tspin(placement, b);
+        let attack = match (lines_cleared, tspin) {
+            (1, true) => 2,
+            (2, false) => 1,
+            (2, true) => 4,
+            (3, false) => 2,
+            (3, true) => 6,
+            (4, _) => 4,
+            _ => 0,
+        };
+        // the perfect clear bonus is the same for every solution, so only the other attack of
+        // the placement that completes it matters
+        if !last {
+            score.attack += attack;
+        }
+        if lines_cleared != 0 {
+            let difficult = tspin || lines_cleared == 4;
+            score.chain_attack += attack + (difficult && b2b) as u32;
+            b2b = difficult;
+        }
+        prev_full = full;
     }
+    score.ends_b2b = b2b;
+    score
 }
 
+type ScoredSolution = (ArrayVec<[pcf::Placement; 10]>, PcScore);
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Default)]
 struct PcScore {
     long_delays: u32,
     last_placement_long: bool,
     attack: u32,
+    /// Attack across the whole solution including back-to-back bonuses.
+    chain_attack: u32,
+    /// Whether back-to-back is active after the solution, carrying over to the next one.
+    ends_b2b: bool,
 }
 
 impl PcPriority {
@@ -284,19 +517,33 @@ impl PcPriority {
                         .cmp(&rhs.last_placement_long)
                         .reverse(),
                 ),
+            PcPriority::MaxB2bChain => {
+                let value = |s: PcScore| s.chain_attack + s.ends_b2b as u32;
+                value(lhs)
+                    .cmp(&value(rhs))
+                    .then(lhs.long_delays.cmp(&rhs.long_delays).reverse())
+                    .then(
+                        lhs.last_placement_long
+                            .cmp(&rhs.last_placement_long)
+                            .reverse(),
+                    )
+            }
         }
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 #[derive(Clone)]
 struct SendOnDrop<T>(std::mem::ManuallyDrop<T>, Sender<T>);
 
+#[cfg(not(target_arch = "wasm32"))]
 impl<T> SendOnDrop<T> {
     fn new(v: T, sender: Sender<T>) -> Self {
         SendOnDrop(std::mem::ManuallyDrop::new(v), sender)
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl<T> std::ops::Deref for SendOnDrop<T> {
     type Target = T;
     fn deref(&self) -> &T {
@@ -304,12 +551,14 @@ impl<T> std::ops::Deref for SendOnDrop<T> {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl<T> std::ops::DerefMut for SendOnDrop<T> {
     fn deref_mut(&mut self) -> &mut T {
         &mut self.0
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl<T> Drop for SendOnDrop<T> {
     fn drop(&mut self) {
         self.1
@@ -321,13 +570,21 @@ impl<T> Drop for SendOnDrop<T> {
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub struct Info {
     pub depth: u32,
-    pub plan: Vec<(FallingPiece, LockResult)>,
+    pub plan: Vec<(FallingPiece, LockResult, bool)>,
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub enum PcPriority {
+    /// Prefers perfect clears with the fewest line clear delays and soft drops.
     Fastest,
+    /// Prefers perfect clears that send the most garbage before the perfect clear itself.
     HighestAttack,
+    /// Prefers perfect clears that send the most garbage over a loop of several perfect clears.
+    ///
+    /// Every line clear counts, including back-to-back bonuses starting from the back-to-back
+    /// state left by the previous perfect clear. Solutions that leave back-to-back active are
+    /// worth one more line, since the next perfect clear can make use of it.
+    MaxB2bChain,
 }
 
 fn check_tspin(p: pcf::Placement, b: pcf::BitBoard) -> bool {
@@ -395,3 +652,142 @@ fn check_tspin(p: pcf::Placement, b: pcf::BitBoard) -> bool {
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use libtetris::{PieceState, RotationState, TspinStatus};
+
+    use super::*;
+
+    /// A field with the bottom 4 rows filled except for the given columns.
+    fn filled_except(columns: &[usize], queue: &[Piece]) -> Board {
+        let mut field = [[false; 10]; 40];
+        for row in &mut field[..4] {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = !columns.contains(&x);
+            }
+        }
+        let mut board = Board::new();
+        board.set_field(field);
+        for &piece in queue {
+            board.add_next_piece(piece);
+        }
+        board
+    }
+
+    #[test]
+    fn finds_multi_piece_pc() {
+        let board = filled_except(&[0, 1, 2], &[Piece::L, Piece::J, Piece::I, Piece::O]);
+        assert_eq!(pieces_to_pc(&board, true, PC_CHECK_BUDGET), Some(3));
+        assert_eq!(pieces_to_pc(&board, false, PC_CHECK_BUDGET), Some(3));
+    }
+
+    #[test]
+    fn pc_check_gives_up_past_budget() {
+        let board = filled_except(&[0, 1, 2], &[Piece::L, Piece::J, Piece::I, Piece::O]);
+        assert_eq!(pieces_to_pc(&board, false, 0), None);
+
+        // a perfect clear from an empty field takes 10 pieces, which is far too many to search
+        // with only 100 placement checks
+        let mut board = Board::new();
+        for &piece in &[
+            Piece::S,
+            Piece::Z,
+            Piece::T,
+            Piece::L,
+            Piece::J,
+            Piece::S,
+            Piece::Z,
+            Piece::T,
+            Piece::L,
+            Piece::J,
+        ] {
+            board.add_next_piece(piece);
+        }
+        assert_eq!(pieces_to_pc(&board, false, 100), None);
+    }
+
+    #[test]
+    fn gives_up_on_unreachable_solution() {
+        let mut board = Board::new();
+        for _ in 0..4 {
+            board.add_next_piece(Piece::T);
+        }
+        let mut looper = PcLooper::new(
+            board,
+            false,
+            MovementMode::ZeroG,
+            KickTable::Arika,
+            SpinDetectionMode::TSpinOnly,
+            PcPriority::Fastest,
+            0,
+            1,
+        );
+        assert!(looper.think().is_some());
+
+        // a T floating in the air can't be reached with any kick table
+        let mut soln = ArrayVec::new();
+        soln.push(FallingPiece {
+            kind: PieceState(Piece::T, RotationState::North),
+            x: 4,
+            y: 5,
+            tspin: TspinStatus::None,
+        });
+        looper.solution(Some(soln));
+
+        assert!(looper.think().is_none());
+        assert!(matches!(looper.suggest_move(), Err(true)));
+    }
+
+    #[test]
+    fn single_threaded_solve_finds_a_10_piece_pc() {
+        use Piece::*;
+        let solver = PcSolver {
+            abort: Arc::new(AtomicBool::new(false)),
+            queue: [I, O, O, O, O, I, O, O, O, O].iter().copied().collect(),
+            hold_enabled: false,
+            b2b: false,
+            priority: PcPriority::Fastest,
+            deadline: None,
+        };
+        let queue: ArrayVec<[pcf::Piece; 11]> = solver.queue.iter().map(|&p| p.into()).collect();
+        let (soln, _) = solver.find_best_single_threaded(&queue).unwrap();
+
+        let mut board = Board::<u16>::new();
+        let pieces = falling_pieces(&soln);
+        assert_eq!(pieces.len(), 10);
+        let mut perfect_clear = false;
+        for (&piece, &kind) in pieces.iter().zip(&solver.queue) {
+            assert_eq!(piece.kind.0, kind);
+            assert!(board.is_placement_legal(&piece));
+            perfect_clear = board.lock_piece(piece).perfect_clear;
+        }
+        assert!(perfect_clear);
+    }
+
+    #[test]
+    fn b2b_chain_prefers_keeping_back_to_back() {
+        // with back-to-back active from the last perfect clear: a T-spin double partway through,
+        // with the perfect clear made with a single that breaks back-to-back
+        let tspin_double = PcScore {
+            attack: 4,
+            chain_attack: 4 + 1,
+            ends_b2b: false,
+            ..PcScore::default()
+        };
+        // against a perfect clear made with a tetris, keeping back-to-back for the next one
+        let tetris = PcScore {
+            attack: 0,
+            chain_attack: 4 + 1,
+            ends_b2b: true,
+            ..PcScore::default()
+        };
+
+        let pick = |priority: PcPriority| match priority.cmp(tspin_double, tetris) {
+            std::cmp::Ordering::Greater => "tspin double",
+            _ => "tetris",
+        };
+        assert_eq!(pick(PcPriority::HighestAttack), "tspin double");
+        assert_eq!(pick(PcPriority::MaxB2bChain), "tetris");
+    }
+}
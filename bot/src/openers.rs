@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use enumset::EnumSet;
+use libtetris::*;
+use opening_book::Book;
+use serde::{Deserialize, Serialize};
+
+use crate::evaluation::Evaluator;
+use crate::modes::normal::BotState;
+use crate::Options;
+
+/// The pieces known at the start of a game, before anything has been placed or held.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct BagStart {
+    pub current: Piece,
+    pub next: Piece,
+}
+
+impl BagStart {
+    /// Finds the bag start of a board, or `None` if the game on the board has already started.
+    pub fn of(board: &Board) -> Option<Self> {
+        if board.piece_count != 0
+            || board.hold_piece.is_some()
+            || board.column_heights().iter().any(|&h| h != 0)
+        {
+            return None;
+        }
+        let mut queue = board.next_queue();
+        Some(BagStart {
+            current: queue.next()?,
+            next: queue.next()?,
+        })
+    }
+}
+
+/// Finds the first move the bot would make for every possible bag start.
+///
+/// Each start is searched on the calling thread until `options.max_nodes` is reached, so it
+/// should be set to something much smaller than the default. The result can be kept around and
+/// looked up with `BagStart::of` so that games which start the same way don't need to search.
+pub fn precompute_openers<E: Evaluator>(
+    eval: &E,
+    options: Options,
+    book: Option<&Book>,
+) -> HashMap<BagStart, Move> {
+    let mut openers = HashMap::new();
+    for current in EnumSet::<Piece>::all() {
+        for next in EnumSet::<Piece>::all() - current {
+            let mut board = Board::new();
+            board.add_next_piece(current);
+            board.add_next_piece(next);
+            let start = BagStart { current, next };
+            if let Some(mv) = search_opener(board, eval, options.clone(), book) {
+                openers.insert(start, mv);
+            }
+        }
+    }
+    openers
+}
+
+fn search_opener<E: Evaluator>(
+    board: Board,
+    eval: &E,
+    options: Options,
+    book: Option<&Book>,
+) -> Option<Move> {
+    let mut bot = BotState::<E>::new(board, options);
+    loop {
+        match bot.think() {
+            Ok(thinker) => bot.finish_thinking(thinker.think(eval)),
+            // with nothing outstanding, no leaf to expand means the tree is fully explored
+            Err(_) => break,
+        }
+    }
+    bot.suggest_move(eval, book, 0, &[0; 10], 0)
+        .map(|(mv, _)| mv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::Standard;
+    use crate::SyncBot;
+
+    #[test]
+    fn precomputed_opener_matches_fresh_search() {
+        let eval = Standard::default();
+        let options = Options {
+            max_nodes: 300,
+            seed: Some(0),
+            ..Options::default()
+        };
+        let openers = precompute_openers(&eval, options.clone(), None);
+        assert_eq!(openers.len(), 7 * 6);
+
+        let mut board = Board::new();
+        for &piece in &[Piece::T, Piece::O] {
+            board.add_next_piece(piece);
+        }
+        let start = BagStart::of(&board).unwrap();
+        let mut bot = SyncBot::new(board, options.clone(), eval, None);
+        let (fresh, _) = bot.think_until(options.max_nodes).unwrap();
+        let precomputed = &openers[&start];
+        assert_eq!(precomputed.expected_location, fresh.expected_location);
+        assert_eq!(precomputed.hold, fresh.hold);
+    }
+}
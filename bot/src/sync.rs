@@ -0,0 +1,162 @@
+use libtetris::*;
+use opening_book::Book;
+
+use crate::evaluation::Evaluator;
+use crate::modes::ModeSwitchedBot;
+use crate::{BotMsg, Info, Options};
+
+/// A bot which thinks on the calling thread only when asked to.
+///
+/// Unlike `Interface`, nothing happens in the background, so given the same `Options::seed` the
+/// same sequence of calls always produces the same moves. This is intended for testing and
+/// research rather than playing in real time.
+pub struct SyncBot<'a, E: Evaluator> {
+    bot: ModeSwitchedBot<'a, E>,
+    eval: E,
+    incoming: u32,
+}
+
+impl<'a, E: Evaluator> SyncBot<'a, E> {
+    /// Creates a bot with the specified starting board and options.
+    ///
+    /// The board must already have a piece in its queue, or two if hold is enabled and the hold
+    /// slot is empty.
+    ///
+    /// `Options::threads` is the number of thoughts that are prepared at once before they are
+    /// carried out one after another. 0 is treated as 1.
+    pub fn new(board: Board, mut options: Options, eval: E, book: Option<&'a Book>) -> Self {
+        options.threads = options.threads.max(1);
+        SyncBot {
+            bot: ModeSwitchedBot::new(board, options, book),
+            eval,
+            incoming: 0,
+        }
+    }
+
+    /// Thinks until the search tree has at least `node_budget` nodes, then provides a move.
+    ///
+    /// If a move can't be provided yet, the bot thinks past the budget until it can. `None` is
+    /// returned if there is nothing more to think about and no move can be provided, usually
+    /// because the bot lacks information on the next pieces or is dead.
+    ///
+    /// Like `Interface::poll_next_move`, this doesn't update the bot's state according to the
+    /// move. To do that, call `play_next_move`.
+    pub fn think_until(&mut self, node_budget: u32) -> Option<(Move, Info)> {
+        while self.bot.search_size().0 < node_budget {
+            if !self.think_once(|_| {}) {
+                break;
+            }
+        }
+
+        self.bot.message(BotMsg::SuggestMove(self.incoming));
+        let mut result = None;
+        loop {
+            let more = self.think_once(|mv| result = Some(mv));
+            if result.is_some() || !more {
+                break;
+            }
+        }
+        if result.is_none() {
+            self.bot.cancel_move();
+        }
+        result
+    }
+
+    /// Performs a single round of thinking, returning `false` if there was nothing to think about.
+    fn think_once(&mut self, send_move: impl FnOnce((Move, Info))) -> bool {
+        let tasks = self.bot.think(&self.eval, send_move);
+        let thought = !tasks.is_empty();
+        for task in tasks {
+            let result = task.execute(&self.eval);
+            self.bot.task_complete(result);
+        }
+        thought
+    }
+
+    /// Sets the number of lines of garbage the bot expects to receive after placing the moves
+    /// provided by `think_until`, as passed to `Interface::suggest_next_move`.
+    pub fn set_incoming(&mut self, incoming: u32) {
+        self.incoming = incoming;
+    }
+
+    /// Updates the internal bot state according to the move played.
+    pub fn play_next_move(&mut self, mv: FallingPiece) {
+        self.bot.message(BotMsg::PlayMove(mv));
+    }
+
+    /// Adds a new piece to the end of the queue.
+    ///
+    /// If speculation is enabled, the piece *must* be in the bag. See
+    /// `Interface::add_next_piece`.
+    pub fn add_next_piece(&mut self, piece: Piece) {
+        self.bot.message(BotMsg::NewPiece(piece));
+    }
+
+    /// Resets the playfield, back-to-back status, and combo count. See `Interface::reset`.
+    pub fn reset(&mut self, field: [[bool; 10]; 40], b2b_active: bool, combo: u32) {
+        self.bot.message(BotMsg::Reset {
+            field,
+            b2b: b2b_active,
+            combo,
+        });
+    }
+
+    /// Tells the bot where the holes of incoming garbage are likely to be. See
+    /// `Interface::expect_garbage_holes`.
+    pub fn expect_garbage_holes(&mut self, columns: [u32; 10]) {
+        self.bot.message(BotMsg::ExpectGarbageHoles(columns));
+    }
+
+    /// Specifies moves that the bot should play in order before it starts picking moves itself.
+    pub fn force_opener(&mut self, moves: Vec<FallingPiece>) {
+        self.bot.message(BotMsg::ForceOpener(moves));
+    }
+
+    /// The bot's board, reflecting the moves passed to `play_next_move`.
+    pub fn board(&self) -> &Board {
+        self.bot.board()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::Standard;
+
+    /// Plays `pieces` moves from an empty board, thinking up to `max_nodes` for each.
+    fn play(options: Options, pieces: usize) -> Vec<Move> {
+        let queue = [
+            Piece::T,
+            Piece::I,
+            Piece::O,
+            Piece::L,
+            Piece::S,
+            Piece::J,
+            Piece::Z,
+        ];
+        let mut board = Board::new();
+        board.add_next_piece(queue[0]);
+        let mut bot = SyncBot::new(board, options.clone(), Standard::default(), None);
+        for &piece in &queue[1..] {
+            bot.add_next_piece(piece);
+        }
+
+        let mut moves = vec![];
+        for _ in 0..pieces {
+            let (mv, _) = bot.think_until(options.max_nodes).unwrap();
+            bot.play_next_move(mv.expected_location);
+            moves.push(mv);
+        }
+        moves
+    }
+
+    #[test]
+    fn same_seed_gives_same_moves() {
+        let options = Options {
+            max_nodes: 500,
+            seed: Some(7),
+            ..Options::default()
+        };
+        assert_eq!(play(options.clone(), 4), play(options, 4));
+    }
+}
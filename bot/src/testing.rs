@@ -0,0 +1,126 @@
+//! Utilities for checking that changes to the evaluator or search don't change the bot's
+//! decisions unintentionally.
+
+use libtetris::*;
+
+use crate::dag::advance;
+use crate::evaluation::Standard;
+use crate::modes::normal::BotState;
+use crate::Options;
+
+/// Plays out the pieces in `queue` on `board` with the default weights, searching `budget` nodes
+/// for each move on a single thread seeded with `seed`.
+///
+/// The same arguments always produce the same moves, so the result can be compared against a
+/// stored sequence using `first_divergence`. Play stops when the bot dies or when fewer than two
+/// pieces of the queue remain.
+pub fn golden_run(seed: u64, mut board: Board, queue: &[Piece], budget: u32) -> Vec<Move> {
+    let options = Options {
+        seed: Some(seed),
+        max_nodes: budget,
+        min_nodes: 0,
+        threads: 1,
+        ..Options::default()
+    };
+    let eval = Standard::default();
+    for &piece in queue {
+        board.add_next_piece(piece);
+    }
+
+    let mut bot = BotState::<Standard>::new(board.clone(), options);
+    let mut moves = vec![];
+    while board.next_queue().nth(1).is_some() {
+        while let Ok(thinker) = bot.think() {
+            bot.finish_thinking(thinker.think(&eval));
+        }
+        let mv = match bot.suggest_move(&eval, None, 0, &[0; 10], 0) {
+            Some((mv, _)) => mv,
+            None => break,
+        };
+        bot.advance_move(mv.expected_location);
+        advance(&mut board, mv.expected_location);
+        moves.push(mv);
+    }
+    moves
+}
+
+/// Finds the index of the first move that differs from the golden placements, or `None` if the
+/// moves match. If one sequence is a prefix of the other, the length of the shorter one is
+/// returned.
+pub fn first_divergence(moves: &[Move], golden: &[FallingPiece]) -> Option<usize> {
+    let mismatch = moves.iter().zip(golden).position(|(mv, expected)| {
+        !mv.expected_location.same_location(expected)
+            || mv.expected_location.tspin != expected.tspin
+    });
+    match mismatch {
+        Some(i) => Some(i),
+        None if moves.len() != golden.len() => Some(moves.len().min(golden.len())),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The placements `golden_run` made for `STANDARD_OPENER_QUEUE` with seed 0 and a budget of
+    /// 500 nodes. If a change to the evaluator or search is meant to change these, update them.
+    const STANDARD_OPENER: &[(Piece, RotationState, i32, i32)] = &[
+        (Piece::I, RotationState::North, 5, 0),
+        (Piece::O, RotationState::North, 8, 0),
+        (Piece::L, RotationState::North, 2, 0),
+        (Piece::J, RotationState::North, 5, 1),
+        (Piece::S, RotationState::North, 2, 1),
+        (Piece::Z, RotationState::West, 2, 3),
+        (Piece::T, RotationState::West, 7, 2),
+        (Piece::I, RotationState::North, 4, 3),
+        (Piece::L, RotationState::North, 4, 4),
+        (Piece::O, RotationState::North, 6, 4),
+        (Piece::Z, RotationState::East, 8, 3),
+        (Piece::J, RotationState::North, 3, 5),
+    ];
+
+    const STANDARD_OPENER_QUEUE: &[Piece] = &[
+        Piece::I,
+        Piece::O,
+        Piece::T,
+        Piece::L,
+        Piece::J,
+        Piece::S,
+        Piece::Z,
+        Piece::T,
+        Piece::I,
+        Piece::L,
+        Piece::O,
+        Piece::Z,
+        Piece::J,
+        Piece::S,
+    ];
+
+    #[test]
+    fn standard_opener_matches_golden() {
+        let golden: Vec<_> = STANDARD_OPENER
+            .iter()
+            .map(|&(piece, rotation, x, y)| FallingPiece {
+                kind: PieceState(piece, rotation),
+                x,
+                y,
+                tspin: TspinStatus::None,
+            })
+            .collect();
+        let moves = golden_run(0, Board::new(), STANDARD_OPENER_QUEUE, 500);
+        let played: Vec<_> = moves.iter().map(|mv| mv.expected_location).collect();
+        assert_eq!(
+            first_divergence(&moves, &golden),
+            None,
+            "the bot played {:?}",
+            played
+        );
+
+        // a single different placement is caught
+        let mut changed = golden.clone();
+        changed[3].x += 1;
+        assert_eq!(first_divergence(&moves, &changed), Some(3));
+        assert_eq!(first_divergence(&moves, &golden[..5]), Some(5));
+    }
+}
@@ -20,14 +20,20 @@ pub struct Interface(Option<Worker<BotMsg, Option<(Move, Info)>>>);
 
 impl Interface {
     /// Launches a bot worker with the specified starting board and options.
-    pub async fn launch<E>(worker_uri: &str, board: Board, options: Options, evaluator: E) -> Self
+    pub async fn launch<E>(
+        worker_uri: &str,
+        board: Board,
+        mut options: Options,
+        evaluator: E,
+    ) -> Self
     where
         E: Evaluator + Clone + Serialize + DeserializeOwned + 'static,
         E::Value: Serialize + DeserializeOwned,
         E::Reward: Serialize + DeserializeOwned,
     {
         if options.threads == 0 {
-            panic!("Invalid number of threads: 0");
+            // the number of cores isn't available to us here
+            options.threads = 1;
         }
 
         let worker = Worker::new(
@@ -58,12 +64,39 @@ impl Interface {
     ///
     /// Once a move is chosen, the move will become available by calling `poll_next_move` or
     /// `block_next_move`. To update the bot state according to this move, call `play_next_move`.
+    ///
+    /// `incoming` is the number of lines of garbage the bot expects to receive. Besides affecting
+    /// which move is picked, it is used when evaluating placements found from now on.
     pub fn suggest_next_move(&self, incoming: u32) {
         if let Some(worker) = &self.0 {
             worker.send(&BotMsg::SuggestMove(incoming)).ok().unwrap();
         }
     }
 
+    /// Tells the bot where the holes of incoming garbage are likely to be.
+    ///
+    /// Each entry is the relative likelihood of the hole being in that column, with index 0 being
+    /// the leftmost column. When garbage is incoming, the bot will try to keep the columns where
+    /// the holes are expected low enough to dig out. Passing all zeros means nothing is known
+    /// about where the holes will be, which is the default.
+    pub fn expect_garbage_holes(&self, columns: [u32; 10]) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::ExpectGarbageHoles(columns)).unwrap();
+        }
+    }
+
+    /// Tells the bot what the opponent's field looks like.
+    ///
+    /// The field is in the same format as for `reset`. The bot only uses the height of the
+    /// opponent's stack, and will send garbage when it can safely top out the opponent.
+    pub fn set_opponent_board(&self, field: &[[bool; 10]; 40]) {
+        if let Some(worker) = &self.0 {
+            worker
+                .send(&BotMsg::OpponentHeight(crate::field_height(field)))
+                .unwrap();
+        }
+    }
+
     /// Checks to see if the bot has provided the previously requested move yet.
     ///
     /// The returned move contains both a path and the expected location of the placed piece. The
@@ -144,6 +177,23 @@ impl Interface {
             worker.send(&BotMsg::ForceAnalysisLine(path)).unwrap();
         }
     }
+
+    /// Removes all of the lines specified by `force_analysis_line` that haven't been analyzed yet.
+    pub fn clear_forced_lines(&self) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::ClearForcedLines).unwrap();
+        }
+    }
+
+    /// Specifies moves that Cold Clear should play in order before it starts picking moves itself.
+    ///
+    /// If one of the moves can't be played or a different move is played, Cold Clear stops
+    /// following the opener.
+    pub fn force_opener(&self, moves: Vec<FallingPiece>) {
+        if let Some(worker) = &self.0 {
+            worker.send(&BotMsg::ForceOpener(moves)).unwrap();
+        }
+    }
 }
 
 fn bot_thread<E>(
@@ -20,7 +20,9 @@ typedef enum CCMovement {
     CC_LEFT, CC_RIGHT,
     CC_CW, CC_CCW,
     /* Soft drop all the way down */
-    CC_DROP
+    CC_DROP,
+    /* Rotate 180 degrees. Only used if `allow_flip` is set in the options. */
+    CC_FLIP
 } CCMovement;
 
 typedef enum CCMovementMode {
@@ -34,6 +36,26 @@ typedef enum CCSpawnRule {
     CC_ROW_21_AND_FALL,
 } CCSpawnRule;
 
+typedef enum CCKickTable {
+    /* The Super Rotation System used by guideline games */
+    CC_SRS,
+    /* SRS with TETR.IO's I piece kicks */
+    CC_SRS_PLUS,
+    /* Arika Rotation System style kicks: one column right, then one column left, and none for
+     * the I piece */
+    CC_ARIKA
+} CCKickTable;
+
+typedef enum CCSpinDetectionMode {
+    /* Only T pieces can spin, using the 3-corner rule */
+    CC_TSPIN_ONLY,
+    /* T pieces use the 3-corner rule, and other pieces spin when they can't move left, right,
+     * up, or down after rotating */
+    CC_ALL_SPIN_IMMOBILE,
+    /* Every piece spins when it can't move left, right, up, or down after rotating */
+    CC_ALL_SPIN_4_POINT
+} CCSpinDetectionMode;
+
 typedef enum CCBotPollStatus {
     CC_MOVE_PROVIDED,
     CC_WAITING,
@@ -43,7 +65,10 @@ typedef enum CCBotPollStatus {
 typedef enum CCPcPriority {
     CC_PC_OFF,
     CC_PC_FASTEST,
-    CC_PC_ATTACK
+    CC_PC_ATTACK,
+    /* Maximize garbage over the whole loop, including back-to-back bonuses carried between
+     * perfect clears */
+    CC_PC_B2B_CHAIN
 } CCPcPriority;
 
 typedef struct CCPlanPlacement {
@@ -56,6 +81,17 @@ typedef struct CCPlanPlacement {
 
     /* Expected lines that will be cleared after placement, with -1 indicating no line */
     int32_t cleared_lines[4];
+
+    /* Whether hold is used before making this placement */
+    bool hold;
+
+    /* The bot's evaluation after this placement and the spike it expects to be in, like the
+     * `evaluation` and `spike` of `CCMove`. These are 0 if the evaluator doesn't report them. */
+    int32_t evaluation;
+    int32_t spike;
+
+    /* The lines of garbage sent by the plan up to and including this placement */
+    uint32_t attack;
 } CCPlanPlacement;
 
 typedef struct CCMove {
@@ -73,17 +109,112 @@ typedef struct CCMove {
     uint32_t nodes;
     uint32_t depth;
     uint32_t original_rank;
+    /* Number of pieces needed to perfect clear, or 0 if no perfect clear was found. This is only
+     * reported if `report_pc` is set in the options. */
+    uint32_t pc_available;
+    /* The bot's evaluation of this placement. Higher is better, and one line of garbage is worth
+     * roughly 200. This is 0 for book and perfect clear moves. */
+    int32_t evaluation;
+    /* The lines of garbage the bot expects to send in the burst of consecutive line clears
+     * following this placement. This is 0 for book and perfect clear moves. */
+    int32_t spike;
 } CCMove;
 
+typedef struct CCBotStats {
+    /* The rate nodes have been added to the search tree recently */
+    double nodes_per_second;
+    /* The number of nodes added to the search tree since the bot was launched */
+    uint64_t total_nodes;
+    /* The number of nodes in the current search tree */
+    uint32_t tree_size;
+    uint32_t depth;
+} CCBotStats;
+
+typedef struct CCShutdownReport {
+    /* The number of nodes added to the search tree since the bot was launched */
+    uint64_t total_nodes;
+    /* The most nodes the search tree had at once */
+    uint32_t peak_tree_size;
+    /* The number of seconds between launching and shutting down the bot */
+    double wall_time;
+} CCShutdownReport;
+
 typedef struct CCOptions {
     CCMovementMode mode;
     CCSpawnRule spawn_rule;
     CCPcPriority pcloop;
     uint32_t min_nodes;
     uint32_t max_nodes;
+    /* The number of threads to search with, or 0 to use one thread per CPU core. */
     uint32_t threads;
     bool use_hold;
     bool speculate;
+    /* The number of milliseconds to spend looking for a perfect clear before giving up and
+     * playing normally, or 0 for no limit. */
+    uint32_t pcloop_time_limit;
+    /* When a new piece changes which placements are best within this many placements of the
+     * previous plan, the new plan is analyzed further before the next move is suggested. 0
+     * disables this. */
+    uint32_t speculation_replan;
+    /* How much worse a move is considered for each placement in its best line that depends on
+     * pieces that aren't known yet, in hundredths of a line of garbage. 0 disables this. */
+    uint32_t speculation_penalty;
+    /* Whether to report if a perfect clear can be made with the known pieces in `CCMove` */
+    bool report_pc;
+    /* How strongly the search favors exploring lower ranked moves over the best moves. Higher
+     * values make the search wider and shallower. Values below 0.001 are treated as 0.001. The
+     * default of 1 is the baseline the default weights were tuned with. Very high values spread
+     * the search almost evenly over all moves. */
+    float exploration;
+    /* The number of book moves to look ahead when playing a book move. The search will analyze
+     * this line first, which smooths the transition from book moves to search moves. */
+    uint32_t book_transition;
+    /* The most lines waiting to be analyzed that the bot keeps track of, or 0 for no limit. When
+     * there are too many, the oldest lines are dropped. */
+    uint32_t max_forced_lines;
+    /* The number of ticks between the bot providing a move and its first input taking effect. */
+    uint32_t latency_ticks;
+    /* The number of ticks it takes for a piece to fall one row, or 0 if pieces don't fall. Only
+     * used to figure out how far pieces fall during `latency_ticks`. */
+    uint32_t gravity;
+    /* The number of ticks between a piece locking and the next piece spawning. This is counted as
+     * part of the time each placement takes. */
+    uint32_t spawn_delay;
+    /* The most garbage that can be added to the board after a single placement, or 0 if there is
+     * no limit. Incoming garbage beyond this amount is ignored when picking a move. */
+    uint32_t garbage_cap;
+    /* Among placements that evaluate equally, prefer the one that takes the fewest ticks to
+     * input. */
+    bool prefer_fast_inputs;
+    /* Seeds the random choices the search makes, or 0 to pick a random seed. With a seed and a
+     * single thread, the bot makes the same moves every time it is given the same position. */
+    uint64_t seed;
+    /* Whether the game supports 180 degree rotations. When set, moves may use `CC_FLIP`. */
+    bool allow_flip;
+    /* The wall kicks of the game's rotation system */
+    CCKickTable kick_table;
+    /* Which rotations count as spins. Spins of pieces other than T are evaluated as T-spins. */
+    CCSpinDetectionMode spin_detection;
+    /* The number of pieces that need to be known before the bot switches to perfect clear loop
+     * mode, plus one more if hold is enabled. Perfect clears from an empty field take 10 pieces. */
+    uint32_t pcloop_min_pieces;
+    /* Roughly the most memory in bytes that the search tree may use, or 0 for no limit. When the
+     * tree grows past this, everything except the moves from the current position and the best
+     * line is thrown away. This is a soft target: it is checked before each expansion, so the tree
+     * goes over it by whatever the last expansion allocated. Each piece of lookahead reserves
+     * about 1 MiB up front, so limits of only a few MiB can't be kept. */
+    uint64_t max_memory_bytes;
+    /* The number of ticks a piece can rest on the stack before it locks, or 0 if pieces only lock
+     * when dropped. Only used in 20G mode, where pieces land as soon as they spawn: if
+     * `latency_ticks` is at least the lock delay, the piece locks where it lands before any input
+     * takes effect. */
+    uint32_t lock_delay;
+    /* Moves the bot plays in order at the start of the game before it starts picking moves itself.
+     * This points to the start of an array of `forced_opener_length` placements, which are read
+     * like the placements of `cc_force_analysis_line`, and only needs to stay valid until the bot
+     * is launched. It may be NULL when the length is 0, which means there is no opener. */
+    CCPlanPlacement *forced_opener;
+    uint32_t forced_opener_length;
 } CCOptions;
 
 typedef struct CCWeights {
@@ -124,6 +255,39 @@ typedef struct CCWeights {
     bool use_bag;
     bool timed_jeopardy;
     bool stack_pc_damage;
+
+    /* Multiplied by the lines of garbage the bot expects to receive and by how far above the
+     * middle of the board the stack will be once it arrives. */
+    int32_t incoming_jeopardy;
+    int32_t sz_dependency;
+    int32_t dig_row_transitions;
+    int32_t surface_spikes;
+    int32_t stack_balance;
+    int32_t filled_tslot;
+    int32_t held_t;
+    bool lookahead_defense;
+    bool flood_fill_holes;
+    bool combo_extrapolate;
+
+    /* The row above which the game considers the board lost. The height-based weights are
+     * measured relative to this, and placements entirely at or above it are treated as lock
+     * outs. */
+    int32_t vanish_zone_top;
+
+    /* Whether to dig through garbage and keep the stack low instead of building up attack. The
+     * remaining weights are only used when this is set. */
+    bool downstack;
+    /* Added for each garbage row cleared. Garbage rows are the rows at the bottom of the board
+     * with exactly one empty cell. */
+    int32_t garbage_clear;
+    /* Added for each empty cell with a filled cell somewhere above it. */
+    int32_t holes;
+
+    /* The garbage sent by each combo, starting from the first line clear, for games with a
+     * different combo table than the guideline one. Only the first `combo_table_length` entries
+     * are used, and a length of 0 means the guideline table is used. */
+    uint32_t combo_table[24];
+    uint32_t combo_table_length;
 } CCWeights;
 
 /* Launches a bot thread with a blank board, all seven pieces in the bag, and the specified queue
@@ -136,7 +300,10 @@ typedef struct CCWeights {
  * `book` may be `NULL` to indicate that no book should be used.
  * The book may be destroyed at any time after this function returns.
  * 
- * Lifetime: The returned pointer is valid until it is passed to `cc_destroy_async`.
+ * Returns `NULL` if the forced opener of the options can't be read.
+ * 
+ * Lifetime: The returned pointer is valid until it is passed to `cc_destroy_async` or
+ * `cc_shutdown`.
  */
 CCAsyncBot *cc_launch_async(CCOptions *options, CCWeights *weights, CCBook *book, CCPiece *queue,
     uint32_t count);
@@ -159,7 +326,10 @@ CCAsyncBot *cc_launch_async(CCOptions *options, CCWeights *weights, CCBook *book
  * `book` may be `NULL` to indicate that no book should be used.
  * The book may be destroyed at any time after this function returns.
  * 
- * Lifetime: The returned pointer is valid until it is passed to `cc_destroy_async`.
+ * Returns `NULL` if the forced opener of the options can't be read.
+ * 
+ * Lifetime: The returned pointer is valid until it is passed to `cc_destroy_async` or
+ * `cc_shutdown`.
  */
 CCAsyncBot *cc_launch_with_board_async(CCOptions *options, CCWeights *weights, CCBook *book,
     bool *field, uint32_t bag_remain, CCPiece *hold, bool b2b, uint32_t combo, CCPiece *queue,
@@ -169,6 +339,13 @@ CCAsyncBot *cc_launch_with_board_async(CCOptions *options, CCWeights *weights, C
  */
 void cc_destroy_async(CCAsyncBot *bot);
 
+/* Terminates the bot thread like `cc_destroy_async`, but first waits for the bot to finish the
+ * work it has started, and then reports what the bot did over its lifetime.
+ * 
+ * The bot is freed, so it must not be passed to `cc_destroy_async` or this function afterwards.
+ */
+CCShutdownReport cc_shutdown(CCAsyncBot *bot);
+
 /* Resets the playfield, back-to-back status, and combo count.
  * 
  * This should only be used when garbage is received or when your client could not place the
@@ -192,6 +369,18 @@ void cc_reset_async(CCAsyncBot *bot, bool *field, bool b2b, uint32_t combo);
  */
 void cc_add_next_piece_async(CCAsyncBot *bot, CCPiece piece);
 
+/* Replaces the weights the bot evaluates positions with, such as to switch to more defensive
+ * weights when garbage is incoming.
+ * 
+ * The search is not restarted, so positions the bot has already analyzed keep their evaluations
+ * from the old weights and only newly analyzed positions use the new ones. Call `cc_reset_async`
+ * as well if the old analysis should be thrown away.
+ * 
+ * Returns false and leaves the weights unchanged if `weights->downstack` differs from the weights
+ * the bot was launched with, since the two kinds of weights use different evaluators.
+ */
+bool cc_update_weights(CCAsyncBot *bot, CCWeights *weights);
+
 /* Request the bot to provide a move as soon as possible.
  * 
  * In most cases, "as soon as possible" is a very short amount of time, and is only longer if
@@ -215,6 +404,38 @@ void cc_add_next_piece_async(CCAsyncBot *bot, CCPiece piece);
  */
 void cc_request_next_move(CCAsyncBot *bot, uint32_t incoming);
 
+/* Tells the bot where the holes of incoming garbage are likely to be.
+ * 
+ * The columns parameter is a pointer to the start of an array of 10 integers, each being the
+ * relative likelihood of the hole being in that column, with index 0 being the leftmost column.
+ * When garbage is incoming, the bot will try to keep the columns where the holes are expected
+ * low enough to dig out. Passing all zeros means nothing is known about where the holes will be,
+ * which is the default.
+ */
+void cc_expect_garbage_holes(CCAsyncBot *bot, uint32_t *columns);
+
+/* Tells the bot what the opponent's field looks like.
+ * 
+ * The field parameter is in the same format as for `cc_reset_async`. The bot only uses the height
+ * of the opponent's stack, and will send garbage when it can safely top out the opponent.
+ */
+void cc_set_opponent_board(CCAsyncBot *bot, bool *field);
+
+/* Specifies a line that the bot should analyze before making any moves, such as to get the bot's
+ * opinion on a specific continuation.
+ * 
+ * The placements parameter is a pointer to the start of an array of count placements, the first
+ * of which is placed on the bot's current board. Only the piece, tspin, expected_x, and
+ * expected_y fields are used. Returns false and does nothing if the expected cells of any
+ * placement aren't the shape of its piece, or if placements is NULL and count isn't 0. NULL may
+ * be passed with a count of 0.
+ */
+bool cc_force_analysis_line(CCAsyncBot *bot, CCPlanPlacement *placements, uint32_t count);
+
+/* Removes all of the lines specified by `cc_force_analysis_line` that haven't been analyzed yet.
+ */
+void cc_clear_forced_lines(CCAsyncBot *bot);
+
 /* Checks to see if the bot has provided the previously requested move yet.
  * 
  * The returned move contains both a path and the expected location of the placed piece. The
@@ -252,6 +473,14 @@ CCBotPollStatus cc_block_next_move(
     uint32_t *plan_length
 );
 
+/* Reports how hard the bot is working in the stats parameter.
+ * 
+ * This doesn't wait for the bot. The node rate is measured between calls to this function over
+ * at least the last second, so it is 0 until this has been called twice a second apart, and is
+ * intended to be called regularly (e.g. once per frame).
+ */
+void cc_get_stats(CCAsyncBot *bot, CCBotStats *stats);
+
 /* Returns the default options in the options parameter */
 void cc_default_options(CCOptions *options);
 
@@ -261,10 +490,13 @@ void cc_default_weights(CCWeights *weights);
 /* Returns the fast game config weights in the weights parameter */
 void cc_fast_weights(CCWeights *weights);
 
+/* Returns weights for digging through garbage and surviving in the weights parameter */
+void cc_downstack_weights(CCWeights *weights);
+
 /*
  * Loads an opening book from the specified file path.
  * This supports both `.ccbook` and `.ccdb` books.
- * If an error occurs, `NULL` is returned instead.
+ * If `path` is `NULL` or an error occurs, `NULL` is returned instead.
  * 
  * Lifetime: The returned pointer is valid until it is passed to `cc_destroy_book`.
  */
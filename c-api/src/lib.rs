@@ -6,7 +6,8 @@ use std::sync::Arc;
 use cold_clear::PcPriority;
 use enumset::EnumSet;
 use libtetris::{
-    Board, FallingPiece, LockResult, MovementMode, Piece, PieceMovement, SpawnRule, TspinStatus,
+    Board, FallingPiece, KickTable, LockResult, MovementMode, Piece, PieceMovement, PieceState,
+    RotationState, SpawnRule, SpinDetectionMode, TspinStatus,
 };
 
 type CCAsyncBot = cold_clear::Interface;
@@ -87,7 +88,8 @@ cenum! {
         CC_RIGHT => PieceMovement::Right,
         CC_CW => PieceMovement::Cw,
         CC_CCW => PieceMovement::Ccw,
-        CC_DROP => PieceMovement::SonicDrop
+        CC_DROP => PieceMovement::SonicDrop,
+        CC_FLIP => PieceMovement::Flip
     }
 
     enum CCSpawnRule => SpawnRule {
@@ -95,6 +97,18 @@ cenum! {
         CC_ROW_21_AND_FALL => SpawnRule::Row21AndFall
     }
 
+    enum CCKickTable => KickTable {
+        CC_SRS => KickTable::Srs,
+        CC_SRS_PLUS => KickTable::SrsPlus,
+        CC_ARIKA => KickTable::Arika
+    }
+
+    enum CCSpinDetectionMode => SpinDetectionMode {
+        CC_TSPIN_ONLY => SpinDetectionMode::TSpinOnly,
+        CC_ALL_SPIN_IMMOBILE => SpinDetectionMode::AllSpinImmobile,
+        CC_ALL_SPIN_4_POINT => SpinDetectionMode::AllSpin4Point
+    }
+
     enum CCMovementMode => MovementMode {
         CC_0G => MovementMode::ZeroG,
         CC_20G => MovementMode::TwentyG,
@@ -104,7 +118,8 @@ cenum! {
     enum CCPcPriority => Option<PcPriority> {
         CC_PC_OFF => None,
         CC_PC_FASTEST => Some(PcPriority::Fastest),
-        CC_PC_ATTACK => Some(PcPriority::HighestAttack)
+        CC_PC_ATTACK => Some(PcPriority::HighestAttack),
+        CC_PC_B2B_CHAIN => Some(PcPriority::MaxB2bChain)
     }
 }
 
@@ -128,6 +143,9 @@ struct CCMove {
     nodes: u32,
     depth: u32,
     original_rank: u32,
+    pc_available: u32,
+    evaluation: i32,
+    spike: i32,
 }
 
 #[repr(C)]
@@ -138,6 +156,27 @@ struct CCPlanPlacement {
     expected_x: [u8; 4],
     expected_y: [u8; 4],
     cleared_lines: [i32; 4],
+    hold: bool,
+    evaluation: i32,
+    spike: i32,
+    attack: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct CCBotStats {
+    nodes_per_second: f64,
+    total_nodes: u64,
+    tree_size: u32,
+    depth: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct CCShutdownReport {
+    total_nodes: u64,
+    peak_tree_size: u32,
+    wall_time: f64,
 }
 
 #[repr(C)]
@@ -150,6 +189,27 @@ struct CCOptions {
     threads: u32,
     use_hold: bool,
     speculate: bool,
+    pcloop_time_limit: u32,
+    speculation_replan: u32,
+    speculation_penalty: u32,
+    report_pc: bool,
+    exploration: f32,
+    book_transition: u32,
+    max_forced_lines: u32,
+    latency_ticks: u32,
+    gravity: u32,
+    spawn_delay: u32,
+    garbage_cap: u32,
+    prefer_fast_inputs: bool,
+    seed: u64,
+    allow_flip: bool,
+    kick_table: CCKickTable,
+    spin_detection: CCSpinDetectionMode,
+    pcloop_min_pieces: u32,
+    max_memory_bytes: u64,
+    lock_delay: u32,
+    forced_opener: *const CCPlanPlacement,
+    forced_opener_length: u32,
 }
 
 #[repr(C)]
@@ -191,8 +251,30 @@ struct CCWeights {
     use_bag: bool,
     timed_jeopardy: bool,
     stack_pc_damage: bool,
+
+    incoming_jeopardy: i32,
+    sz_dependency: i32,
+    dig_row_transitions: i32,
+    surface_spikes: i32,
+    stack_balance: i32,
+    filled_tslot: i32,
+    held_t: i32,
+    lookahead_defense: bool,
+    flood_fill_holes: bool,
+    combo_extrapolate: bool,
+    vanish_zone_top: i32,
+
+    downstack: bool,
+    garbage_clear: i32,
+    holes: i32,
+
+    combo_table: [u32; MAX_COMBO_TABLE],
+    combo_table_length: u32,
 }
 
+/// The most entries a combo table passed through `CCWeights` can have.
+const MAX_COMBO_TABLE: usize = 24;
+
 fn convert_hold(hold: *mut CCPiece) -> Option<Piece> {
     if hold.is_null() {
         None
@@ -207,10 +289,39 @@ fn convert_from_c_options(options: &CCOptions) -> cold_clear::Options {
         min_nodes: options.min_nodes,
         use_hold: options.use_hold,
         speculate: options.speculate,
+        speculation_replan: options.speculation_replan,
+        speculation_penalty: options.speculation_penalty,
         pcloop: options.pcloop.into(),
+        pcloop_time_limit: options.pcloop_time_limit,
+        report_pc: options.report_pc,
+        // CCMove has nowhere to put them
+        report_missed_tspins: false,
+        book_transition: options.book_transition,
+        max_forced_lines: options.max_forced_lines,
         mode: options.mode.into(),
+        allow_flip: options.allow_flip,
         spawn_rule: options.spawn_rule.into(),
+        kick_table: options.kick_table.into(),
+        spin_detection: options.spin_detection.into(),
+        pcloop_min_pieces: options.pcloop_min_pieces,
+        max_memory_bytes: match options.max_memory_bytes {
+            0 => None,
+            bytes => Some(bytes.min(usize::MAX as u64) as usize),
+        },
         threads: options.threads,
+        exploration: options.exploration,
+        latency_ticks: options.latency_ticks,
+        lock_delay: options.lock_delay,
+        // only read by `launch`, since the pointer doesn't have to stay valid after it
+        forced_opener: None,
+        gravity: options.gravity,
+        spawn_delay: options.spawn_delay,
+        garbage_cap: options.garbage_cap,
+        prefer_fast_inputs: options.prefer_fast_inputs,
+        seed: match options.seed {
+            0 => None,
+            seed => Some(seed),
+        },
     }
 }
 
@@ -224,6 +335,7 @@ fn convert_from_c_weights(weights: &CCWeights) -> cold_clear::evaluation::Standa
         top_half: weights.top_half,
         top_quarter: weights.top_quarter,
         jeopardy: weights.jeopardy,
+        incoming_jeopardy: weights.incoming_jeopardy,
         cavity_cells: weights.cavity_cells,
         cavity_cells_sq: weights.cavity_cells_sq,
         overhang_cells: weights.overhang_cells,
@@ -234,6 +346,10 @@ fn convert_from_c_weights(weights: &CCWeights) -> cold_clear::evaluation::Standa
         well_depth: weights.well_depth,
         max_well_depth: weights.max_well_depth,
         well_column: weights.well_column,
+        sz_dependency: weights.sz_dependency,
+        dig_row_transitions: weights.dig_row_transitions,
+        surface_spikes: weights.surface_spikes,
+        stack_balance: weights.stack_balance,
 
         b2b_clear: weights.b2b_clear,
         clear1: weights.clear1,
@@ -249,14 +365,60 @@ fn convert_from_c_weights(weights: &CCWeights) -> cold_clear::evaluation::Standa
         combo_garbage: weights.combo_garbage,
         move_time: weights.move_time,
         wasted_t: weights.wasted_t,
+        filled_tslot: weights.filled_tslot,
+        held_t: weights.held_t,
 
         use_bag: weights.use_bag,
         timed_jeopardy: weights.timed_jeopardy,
         stack_pc_damage: weights.stack_pc_damage,
+        lookahead_defense: weights.lookahead_defense,
+        flood_fill_holes: weights.flood_fill_holes,
+        combo_extrapolate: weights.combo_extrapolate,
+        combo_table: match weights.combo_table_length as usize {
+            0 => None,
+            len => Some(weights.combo_table[..len.min(MAX_COMBO_TABLE)].to_vec()),
+        },
+        vanish_zone_top: weights.vanish_zone_top,
         sub_name: None,
     }
 }
 
+fn convert_from_c_downstack_weights(weights: &CCWeights) -> cold_clear::evaluation::Downstack {
+    cold_clear::evaluation::Downstack {
+        base: convert_from_c_weights(weights),
+        garbage_clear: weights.garbage_clear,
+        holes: weights.holes,
+    }
+}
+
+unsafe fn launch(
+    board: Board,
+    c_options: &CCOptions,
+    weights: &CCWeights,
+    book: *const CCBook,
+) -> *mut CCAsyncBot {
+    let mut options = convert_from_c_options(c_options);
+    if c_options.forced_opener_length != 0 {
+        match placement_path(c_options.forced_opener, c_options.forced_opener_length) {
+            Some(opener) => options.forced_opener = Some(opener),
+            None => return std::ptr::null_mut(),
+        }
+    }
+    let book = if book.is_null() {
+        None
+    } else {
+        Arc::increment_strong_count(book);
+        Some(Arc::from_raw(book))
+    };
+    let bot = if weights.downstack {
+        let weights = convert_from_c_downstack_weights(weights);
+        cold_clear::Interface::launch(board, options, weights, book)
+    } else {
+        cold_clear::Interface::launch(board, options, convert_from_c_weights(weights), book)
+    };
+    Box::into_raw(Box::new(bot))
+}
+
 #[no_mangle]
 unsafe extern "C" fn cc_launch_with_board_async(
     options: &CCOptions,
@@ -280,18 +442,7 @@ unsafe extern "C" fn cc_launch_with_board_async(
     for i in 0..count as usize {
         board.add_next_piece((*pieces.add(i)).into());
     }
-    let book = if book.is_null() {
-        None
-    } else {
-        Arc::increment_strong_count(book);
-        Some(Arc::from_raw(book))
-    };
-    Box::into_raw(Box::new(cold_clear::Interface::launch(
-        board,
-        convert_from_c_options(options),
-        convert_from_c_weights(weights),
-        book,
-    )))
+    launch(board, options, weights, book)
 }
 
 #[no_mangle]
@@ -306,18 +457,7 @@ unsafe extern "C" fn cc_launch_async(
     for i in 0..count as usize {
         board.add_next_piece((*pieces.add(i)).into());
     }
-    let book = if book.is_null() {
-        None
-    } else {
-        Arc::increment_strong_count(book);
-        Some(Arc::from_raw(book))
-    };
-    Box::into_raw(Box::new(cold_clear::Interface::launch(
-        board,
-        convert_from_c_options(options),
-        convert_from_c_weights(weights),
-        book,
-    )))
+    launch(board, options, weights, book)
 }
 
 #[no_mangle]
@@ -327,6 +467,17 @@ extern "C" fn cc_destroy_async(bot: *mut CCAsyncBot) {
     }
 }
 
+#[no_mangle]
+extern "C" fn cc_shutdown(bot: *mut CCAsyncBot) -> CCShutdownReport {
+    let bot = unsafe { Box::from_raw(bot) };
+    let report = bot.shutdown();
+    CCShutdownReport {
+        total_nodes: report.total_nodes,
+        peak_tree_size: report.peak_tree_size,
+        wall_time: report.wall_time.as_secs_f64(),
+    }
+}
+
 #[no_mangle]
 extern "C" fn cc_reset_async(
     bot: &mut CCAsyncBot,
@@ -342,13 +493,108 @@ extern "C" fn cc_add_next_piece_async(bot: &mut CCAsyncBot, piece: CCPiece) {
     bot.add_next_piece(piece.into());
 }
 
+#[no_mangle]
+extern "C" fn cc_expect_garbage_holes(bot: &mut CCAsyncBot, columns: &[u32; 10]) {
+    bot.expect_garbage_holes(*columns);
+}
+
+#[no_mangle]
+extern "C" fn cc_set_opponent_board(bot: &mut CCAsyncBot, field: &[[bool; 10]; 40]) {
+    bot.set_opponent_board(field);
+}
+
+#[no_mangle]
+extern "C" fn cc_update_weights(bot: &mut CCAsyncBot, weights: &CCWeights) -> bool {
+    if weights.downstack {
+        bot.update_evaluator(convert_from_c_downstack_weights(weights))
+    } else {
+        bot.update_evaluator(convert_from_c_weights(weights))
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn cc_force_analysis_line(
+    bot: &mut CCAsyncBot,
+    placements: *const CCPlanPlacement,
+    count: u32,
+) -> bool {
+    match placement_path(placements, count) {
+        Some(path) => {
+            bot.force_analysis_line(path);
+            true
+        }
+        None => false,
+    }
+}
+
+#[no_mangle]
+extern "C" fn cc_clear_forced_lines(bot: &mut CCAsyncBot) {
+    bot.clear_forced_lines();
+}
+
+/// Finds the piece locations of an array of `count` placements, or `None` if any placement isn't
+/// the shape of its piece, or if `placements` is null and `count` isn't 0.
+unsafe fn placement_path(
+    placements: *const CCPlanPlacement,
+    count: u32,
+) -> Option<Vec<FallingPiece>> {
+    let placements = if placements.is_null() {
+        if count != 0 {
+            return None;
+        }
+        &[]
+    } else {
+        std::slice::from_raw_parts(placements, count as usize)
+    };
+    placements.iter().map(placement_location).collect()
+}
+
+/// Finds the piece location with the cells of a placement, or `None` if the cells aren't the
+/// shape of the placement's piece.
+fn placement_location(placement: &CCPlanPlacement) -> Option<FallingPiece> {
+    let mut expected = [(0, 0); 4];
+    for i in 0..4 {
+        expected[i] = (
+            placement.expected_x[i] as i32,
+            placement.expected_y[i] as i32,
+        );
+    }
+    expected.sort();
+    let rotations = [
+        RotationState::North,
+        RotationState::East,
+        RotationState::South,
+        RotationState::West,
+    ];
+    rotations.iter().find_map(|&rotation| {
+        let kind = PieceState(placement.piece.into(), rotation);
+        let mut cells = kind.cells();
+        cells.sort();
+        let piece = FallingPiece {
+            kind,
+            x: expected[0].0 - cells[0].0,
+            y: expected[0].1 - cells[0].1,
+            tspin: placement.tspin.into(),
+        };
+        let mut cells = piece.cells();
+        cells.sort();
+        if cells == expected {
+            Some(piece)
+        } else {
+            None
+        }
+    })
+}
+
 #[no_mangle]
 extern "C" fn cc_request_next_move(bot: &mut CCAsyncBot, incoming: u32) {
     bot.suggest_next_move(incoming);
 }
 
 fn convert_plan_placement(
-    (falling_piece, lock_result): &(FallingPiece, LockResult),
+    (falling_piece, lock_result, hold): &(FallingPiece, LockResult, bool),
+    (evaluation, spike): (i32, i32),
+    attack: u32,
 ) -> CCPlanPlacement {
     let mut expected_x = [0; 4];
     let mut expected_y = [0; 4];
@@ -368,6 +614,10 @@ fn convert_plan_placement(
         expected_x: expected_x,
         expected_y: expected_y,
         cleared_lines: cleared_lines,
+        hold: *hold,
+        evaluation,
+        spike,
+        attack,
     }
 }
 
@@ -380,8 +630,12 @@ fn convert_plan(
         let plan_length = unsafe { &mut *plan_length };
         let plan = unsafe { std::slice::from_raw_parts_mut(plan, *plan_length as usize) };
         let n = info.plan().len().min(plan.len());
+        let mut attack = 0;
         for i in 0..n {
-            plan[i] = MaybeUninit::new(convert_plan_placement(&info.plan()[i]));
+            let placement = &info.plan()[i];
+            attack += placement.1.garbage_sent;
+            let evaluation = info.plan_evaluations().get(i).copied().unwrap_or((0, 0));
+            plan[i] = MaybeUninit::new(convert_plan_placement(placement, evaluation, attack));
         }
         *plan_length = n as u32;
     }
@@ -419,6 +673,19 @@ fn convert(m: libtetris::Move, info: cold_clear::Info) -> CCMove {
             cold_clear::Info::PcLoop(_) => 0,
             cold_clear::Info::Book => 0,
         },
+        pc_available: match &info {
+            cold_clear::Info::Normal(info) => info.pc_available.unwrap_or(0),
+            cold_clear::Info::PcLoop(_) => 0,
+            cold_clear::Info::Book => 0,
+        },
+        evaluation: match &info {
+            cold_clear::Info::Normal(info) => info.evaluation.map_or(0, |(value, _)| value),
+            _ => 0,
+        },
+        spike: match &info {
+            cold_clear::Info::Normal(info) => info.evaluation.map_or(0, |(_, spike)| spike),
+            _ => 0,
+        },
     }
 }
 
@@ -459,6 +726,17 @@ extern "C" fn cc_block_next_move(
     }
 }
 
+#[no_mangle]
+unsafe extern "C" fn cc_get_stats(bot: &mut CCAsyncBot, stats: *mut CCBotStats) {
+    let s = bot.stats();
+    stats.write(CCBotStats {
+        nodes_per_second: s.nodes_per_second,
+        total_nodes: s.total_nodes,
+        tree_size: s.tree_size,
+        depth: s.depth,
+    });
+}
+
 #[no_mangle]
 unsafe extern "C" fn cc_default_options(options: *mut CCOptions) {
     let o = cold_clear::Options::default();
@@ -467,14 +745,44 @@ unsafe extern "C" fn cc_default_options(options: *mut CCOptions) {
         min_nodes: o.min_nodes,
         use_hold: o.use_hold,
         speculate: o.speculate,
+        speculation_replan: o.speculation_replan,
+        speculation_penalty: o.speculation_penalty,
         pcloop: o.pcloop.into(),
+        pcloop_time_limit: o.pcloop_time_limit,
+        report_pc: o.report_pc,
+        book_transition: o.book_transition,
+        max_forced_lines: o.max_forced_lines,
         mode: o.mode.into(),
         spawn_rule: o.spawn_rule.into(),
         threads: o.threads,
+        exploration: o.exploration,
+        latency_ticks: o.latency_ticks,
+        gravity: o.gravity,
+        spawn_delay: o.spawn_delay,
+        garbage_cap: o.garbage_cap,
+        prefer_fast_inputs: o.prefer_fast_inputs,
+        seed: o.seed.unwrap_or(0),
+        allow_flip: o.allow_flip,
+        kick_table: o.kick_table.into(),
+        spin_detection: o.spin_detection.into(),
+        pcloop_min_pieces: o.pcloop_min_pieces,
+        max_memory_bytes: o.max_memory_bytes.map_or(0, |bytes| bytes as u64),
+        lock_delay: o.lock_delay,
+        forced_opener: std::ptr::null(),
+        forced_opener_length: 0,
     });
 }
 
 fn convert_weights(w: cold_clear::evaluation::Standard) -> CCWeights {
+    let mut combo_table = [0; MAX_COMBO_TABLE];
+    let combo_table_length = match &w.combo_table {
+        Some(table) => {
+            let len = table.len().min(MAX_COMBO_TABLE);
+            combo_table[..len].copy_from_slice(&table[..len]);
+            len as u32
+        }
+        None => 0,
+    };
     CCWeights {
         back_to_back: w.back_to_back,
         bumpiness: w.bumpiness,
@@ -484,6 +792,7 @@ fn convert_weights(w: cold_clear::evaluation::Standard) -> CCWeights {
         top_half: w.top_half,
         top_quarter: w.top_quarter,
         jeopardy: w.jeopardy,
+        incoming_jeopardy: w.incoming_jeopardy,
         cavity_cells: w.cavity_cells,
         cavity_cells_sq: w.cavity_cells_sq,
         overhang_cells: w.overhang_cells,
@@ -494,6 +803,10 @@ fn convert_weights(w: cold_clear::evaluation::Standard) -> CCWeights {
         well_depth: w.well_depth,
         max_well_depth: w.max_well_depth,
         well_column: w.well_column,
+        sz_dependency: w.sz_dependency,
+        dig_row_transitions: w.dig_row_transitions,
+        surface_spikes: w.surface_spikes,
+        stack_balance: w.stack_balance,
 
         b2b_clear: w.b2b_clear,
         clear1: w.clear1,
@@ -509,10 +822,23 @@ fn convert_weights(w: cold_clear::evaluation::Standard) -> CCWeights {
         combo_garbage: w.combo_garbage,
         move_time: w.move_time,
         wasted_t: w.wasted_t,
+        filled_tslot: w.filled_tslot,
+        held_t: w.held_t,
 
         use_bag: w.use_bag,
         timed_jeopardy: w.timed_jeopardy,
         stack_pc_damage: w.stack_pc_damage,
+        lookahead_defense: w.lookahead_defense,
+        flood_fill_holes: w.flood_fill_holes,
+        combo_extrapolate: w.combo_extrapolate,
+        vanish_zone_top: w.vanish_zone_top,
+
+        downstack: false,
+        garbage_clear: 0,
+        holes: 0,
+
+        combo_table,
+        combo_table_length,
     }
 }
 
@@ -528,9 +854,23 @@ unsafe extern "C" fn cc_fast_weights(weights: *mut CCWeights) {
     ));
 }
 
+#[no_mangle]
+unsafe extern "C" fn cc_downstack_weights(weights: *mut CCWeights) {
+    let w = cold_clear::evaluation::Downstack::default();
+    weights.write(CCWeights {
+        downstack: true,
+        garbage_clear: w.garbage_clear,
+        holes: w.holes,
+        ..convert_weights(w.base)
+    });
+}
+
 #[no_mangle]
 unsafe extern "C" fn cc_load_book_from_file(path: *const c_char) -> *const CCBook {
     let result = (|| {
+        if path.is_null() {
+            return None;
+        }
         let path = CStr::from_ptr(path).to_str().ok()?;
         Some(Arc::new(cold_clear::Book::load(path).ok()?))
     })();
@@ -553,3 +893,112 @@ unsafe extern "C" fn cc_load_book_from_memory(data: *const c_char, length: u32)
 unsafe extern "C" fn cc_destroy_book(book: *const CCBook) {
     Arc::from_raw(book);
 }
+
+#[cfg(test)]
+mod tests {
+    use std::mem::MaybeUninit;
+
+    use super::*;
+
+    #[test]
+    fn force_analysis_line_accepts_null_only_without_placements() {
+        unsafe {
+            let mut options = MaybeUninit::uninit();
+            cc_default_options(options.as_mut_ptr());
+            let mut weights = MaybeUninit::uninit();
+            cc_default_weights(weights.as_mut_ptr());
+            let pieces = [CCPiece::CC_T, CCPiece::CC_I];
+            let bot = cc_launch_async(
+                &options.assume_init(),
+                &weights.assume_init(),
+                std::ptr::null(),
+                pieces.as_ptr(),
+                pieces.len() as u32,
+            );
+
+            assert!(cc_force_analysis_line(&mut *bot, std::ptr::null(), 0));
+            assert!(!cc_force_analysis_line(&mut *bot, std::ptr::null(), 1));
+            cc_destroy_async(bot);
+        }
+    }
+
+    #[test]
+    fn launch_reads_forced_opener() {
+        unsafe {
+            let mut options = MaybeUninit::<CCOptions>::uninit();
+            cc_default_options(options.as_mut_ptr());
+            let mut options = options.assume_init();
+            let mut weights = MaybeUninit::uninit();
+            cc_default_weights(weights.as_mut_ptr());
+            let weights = weights.assume_init();
+            let pieces = [CCPiece::CC_O, CCPiece::CC_T];
+
+            // an O dropped off center, and one with a cell out of place
+            let mut placement = CCPlanPlacement {
+                piece: CCPiece::CC_O,
+                tspin: CCTspinStatus::CC_NONE,
+                expected_x: [6, 7, 6, 7],
+                expected_y: [0, 0, 1, 1],
+                cleared_lines: [-1; 4],
+                hold: false,
+                evaluation: 0,
+                spike: 0,
+                attack: 0,
+            };
+            options.forced_opener = &placement;
+            options.forced_opener_length = 1;
+            let launch = |options: &CCOptions| {
+                cc_launch_async(
+                    options,
+                    &weights,
+                    std::ptr::null(),
+                    pieces.as_ptr(),
+                    pieces.len() as u32,
+                )
+            };
+            let bot = launch(&options);
+            assert!(!bot.is_null());
+            cc_request_next_move(&mut *bot, 0);
+            let mut mv = MaybeUninit::uninit();
+            let status = cc_block_next_move(
+                &mut *bot,
+                mv.as_mut_ptr(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            );
+            assert!(matches!(status, CCBotPollStatus::CC_MOVE_PROVIDED));
+            let mv = mv.assume_init();
+            let mut cells: Vec<_> = mv.expected_x.iter().zip(&mv.expected_y).collect();
+            cells.sort();
+            assert_eq!(cells, vec![(&6, &0), (&6, &1), (&7, &0), (&7, &1)]);
+            cc_destroy_async(bot);
+
+            placement.expected_x[3] = 8;
+            options.forced_opener = &placement;
+            assert!(launch(&options).is_null());
+            options.forced_opener = std::ptr::null();
+            assert!(launch(&options).is_null());
+        }
+    }
+
+    #[test]
+    fn update_weights_rejects_other_kind_of_weights() {
+        unsafe {
+            let mut options = MaybeUninit::<CCOptions>::uninit();
+            cc_default_options(options.as_mut_ptr());
+            let options = options.assume_init();
+            let mut weights = MaybeUninit::uninit();
+            cc_default_weights(weights.as_mut_ptr());
+            let weights = weights.assume_init();
+            let mut downstack = MaybeUninit::uninit();
+            cc_downstack_weights(downstack.as_mut_ptr());
+            let downstack = downstack.assume_init();
+
+            let bot = cc_launch_async(&options, &weights, std::ptr::null(), std::ptr::null(), 0);
+            assert!(!bot.is_null());
+            assert!(!cc_update_weights(&mut *bot, &downstack));
+            assert!(cc_update_weights(&mut *bot, &weights));
+            cc_destroy_async(bot);
+        }
+    }
+}
@@ -157,6 +157,8 @@ impl InputSource for UserInput {
                 self.gamepad.soft_drop,
             ),
             hold: self.read_input(keys, gamepad, self.keyboard.hold, self.gamepad.hold),
+            // there's no binding for 180 rotation yet
+            rotate_180: false,
         }
     }
 
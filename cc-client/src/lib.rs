@@ -275,64 +275,79 @@ where
     ) -> (Box<dyn input::InputSource>, String) {
         use crate::input::BotInput;
         if self.is_bot {
-            let mut name = format!("Cold Clear\n{}", self.bot_config.weights.name());
+            let evaluator_name = match &self.bot_config.downstack {
+                Some(downstack) => downstack.name(),
+                None => self.bot_config.weights.name(),
+            };
+            let mut name = format!("Cold Clear\n{}", evaluator_name);
             if self.bot_config.speed_limit != 0 {
                 name.push_str(&format!(
                     "\n{:.1}%",
                     100.0 / (self.bot_config.speed_limit + 1) as f32
                 ));
             }
-            #[cfg(not(target_arch = "wasm32"))]
-            let result = (
-                Box::new(BotInput::new(
-                    cold_clear::Interface::launch(
-                        board,
-                        self.bot_config.options,
-                        self.bot_config.weights.clone(),
-                        self.bot_config.book_path.as_ref().and_then(|path| {
-                            let mut book_cache = self.bot_config.book_cache.borrow_mut();
-                            match &*book_cache {
-                                Some(b) => Some(b.clone()),
-                                None => {
-                                    let book = Book::load(path).unwrap();
-                                    let book = std::sync::Arc::new(book);
-                                    *book_cache = Some(book.clone());
-                                    Some(book)
-                                }
-                            }
-                        }),
-                    ),
-                    self.bot_config.speed_limit,
-                )) as Box<_>,
-                name,
-            );
-
-            #[cfg(target_arch = "wasm32")]
-            let result = (
-                Box::new(BotInput::new(
-                    cold_clear::Interface::launch(
-                        "./worker.js",
-                        board,
-                        self.bot_config.options,
-                        self.bot_config.weights.clone(),
-                    )
-                    .await,
-                    self.bot_config.speed_limit,
-                )) as Box<_>,
+            let mut options = self.bot_config.options.clone();
+            options.spawn_delay = self.game.spawn_delay;
+            options.garbage_cap = self.game.max_garbage_add;
+            let bot = match &self.bot_config.downstack {
+                Some(downstack) => self.launch_bot(board, options, downstack.clone()).await,
+                None => {
+                    self.launch_bot(board, options, self.bot_config.weights.clone())
+                        .await
+                }
+            };
+            (
+                Box::new(BotInput::new(bot, self.bot_config.speed_limit)) as Box<_>,
                 name,
-            );
-
-            result
+            )
         } else {
             (Box::new(self.controls), "Human".to_owned())
         }
     }
+
+    async fn launch_bot<B>(
+        &self,
+        board: libtetris::Board,
+        options: cold_clear::Options,
+        evaluator: B,
+    ) -> cold_clear::Interface
+    where
+        B: Evaluator + Clone + Serialize + DeserializeOwned + 'static,
+        B::Reward: Serialize + DeserializeOwned,
+        B::Value: Serialize + DeserializeOwned,
+    {
+        #[cfg(not(target_arch = "wasm32"))]
+        let bot = cold_clear::Interface::launch(
+            board,
+            options,
+            evaluator,
+            self.bot_config.book_path.as_ref().and_then(|path| {
+                let mut book_cache = self.bot_config.book_cache.borrow_mut();
+                match &*book_cache {
+                    Some(b) => Some(b.clone()),
+                    None => {
+                        let book = Book::load(path).unwrap();
+                        let book = std::sync::Arc::new(book);
+                        *book_cache = Some(book.clone());
+                        Some(book)
+                    }
+                }
+            }),
+        );
+
+        #[cfg(target_arch = "wasm32")]
+        let bot = cold_clear::Interface::launch("./worker.js", board, options, evaluator).await;
+
+        bot
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Default)]
 #[serde(default)]
 struct BotConfig<E> {
     weights: E,
+    /// When set, the bot digs through garbage using these weights instead of using `weights`.
+    downstack: Option<cold_clear::evaluation::Downstack>,
     options: cold_clear::Options,
     speed_limit: u32,
     book_path: Option<String>,
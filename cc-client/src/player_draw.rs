@@ -336,7 +336,7 @@ impl PlayerDrawState {
         if self.show_plan {
             if let Some(ref info) = self.info {
                 let mut has_pc = false;
-                for (_, l) in info.plan() {
+                for (_, l, _) in info.plan() {
                     if l.perfect_clear {
                         has_pc = true;
                     }
@@ -347,7 +347,7 @@ impl PlayerDrawState {
                 for i in 0..40 {
                     y_map[i] = i as i32;
                 }
-                for (placement, lock) in info.plan() {
+                for (placement, lock, _) in info.plan() {
                     for &(x, y, d) in &placement.cells_with_connections() {
                         res.sprite_batch.draw(
                             &res.sprites.plan[d.as_usize()],
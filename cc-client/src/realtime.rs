@@ -142,12 +142,12 @@ impl crate::State for RealtimeGame {
             let p1_info_update = self.p1_input.update(
                 &self.battle.player_1.board,
                 &update.player_1.events,
-                self.battle.player_1.garbage_queue,
+                (-self.battle.player_1.net_garbage_balance()).max(0) as u32,
             );
             let p2_info_update = self.p2_input.update(
                 &self.battle.player_2.board,
                 &update.player_2.events,
-                self.battle.player_2.garbage_queue,
+                (-self.battle.player_2.net_garbage_balance()).max(0) as u32,
             );
 
             self.p1_info_updates.push_back(p1_info_update.clone());
@@ -35,13 +35,7 @@ impl ReplayGame {
             p2_info_updates,
         } = bincode::deserialize_from(libflate::deflate::Decoder::new(File::open(&file).unwrap()))
             .unwrap();
-        let battle = Battle::new(
-            replay.p1_config,
-            replay.p2_config,
-            replay.p1_seed,
-            replay.p2_seed,
-            replay.garbage_seed,
-        );
+        let battle = replay.battle();
         ReplayGame {
             ui: BattleUi::new(
                 &battle,
@@ -103,13 +97,7 @@ impl crate::State for ReplayGame {
                     p1_info_updates,
                     p2_info_updates,
                 } = replay;
-                let battle = Battle::new(
-                    replay.p1_config,
-                    replay.p2_config,
-                    replay.p1_seed,
-                    replay.p2_seed,
-                    replay.garbage_seed,
-                );
+                let battle = replay.battle();
                 self.ui = BattleUi::new(
                     &battle,
                     replay.p1_name,
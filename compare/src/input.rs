@@ -10,18 +10,33 @@ pub struct BotInput<E: Evaluator> {
     time_budget: Duration,
     bot: cold_clear::BotState<E>,
     eval: E,
+    deterministic: bool,
 }
 
 const THINK_AMOUNT: Duration = Duration::from_millis(4);
+/// The number of thinks done each frame by seeded bots, which is roughly how many fit in
+/// `THINK_AMOUNT` on a typical machine.
+#[cfg(not(test))]
+const THINK_STEPS: u32 = 10;
+// unoptimized builds think far slower, so tests would take minutes per game otherwise
+#[cfg(test)]
+const THINK_STEPS: u32 = 1;
 
 impl<E: Evaluator> BotInput<E> {
-    pub fn new(board: Board, eval: E) -> Self {
+    /// Creates a bot input. With a seed, the bot thinks a fixed amount each frame instead of for
+    /// a fixed time, so it plays the same way every time it is given the same game.
+    pub fn new(board: Board, eval: E, seed: Option<u64>) -> Self {
+        let options = cold_clear::Options {
+            seed,
+            ..Default::default()
+        };
         let mut this = BotInput {
             controller: Controller::default(),
             executing: None,
             time_budget: Duration::new(0, 0),
-            bot: cold_clear::BotState::new(board, Default::default()),
+            bot: cold_clear::BotState::new(board, options),
             eval,
+            deterministic: seed.is_some(),
         };
         for _ in 0..180 {
             // equivalent of 3 realtime seconds of thinking
@@ -31,6 +46,16 @@ impl<E: Evaluator> BotInput<E> {
     }
 
     fn think(&mut self) {
+        if self.deterministic {
+            for _ in 0..THINK_STEPS {
+                match self.bot.think() {
+                    Ok(thinker) => self.bot.finish_thinking(thinker.think(&self.eval)),
+                    Err(_) => break,
+                }
+            }
+            return;
+        }
+
         std::thread::yield_now(); // get a new timeslice
         while self.time_budget < THINK_AMOUNT {
             let start = Instant::now();
@@ -63,7 +88,10 @@ impl<E: Evaluator> BotInput<E> {
                 Event::PieceSpawned { new_in_queue } => {
                     self.bot.add_next_piece(*new_in_queue);
                     if self.executing.is_none() {
-                        if let Some((mv, inf)) = self.bot.suggest_move(&self.eval, None, incoming) {
+                        if let Some((mv, inf)) = self
+                            .bot
+                            .suggest_move(&self.eval, None, incoming, &[0; 10], 0)
+                        {
                             info = Some(inf);
                             self.executing = Some((
                                 mv.expected_location,
@@ -1,4 +1,6 @@
 use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 use battle::{Battle, GameConfig, Replay};
 use cold_clear::evaluation::Evaluator;
@@ -17,15 +19,25 @@ fn main() {
 
     let p2_eval = changed::Standard::default();
 
+    // with a seed, game n is played with seed + n, so any game can be reproduced on its own by
+    // passing its seed and stopping after the first game
+    let seed: Option<u64> = std::env::args()
+        .nth(1)
+        .map(|s| s.parse().expect("the seed must be a number"));
+    let next_game = Arc::new(AtomicU64::new(0));
+
     let (send, recv) = std::sync::mpsc::channel();
 
     for _ in 0..12 {
         let p1_eval = p1_eval.clone();
         let p2_eval = p2_eval.clone();
         let send = send.clone();
+        let next_game = next_game.clone();
         std::thread::spawn(move || loop {
+            let game = next_game.fetch_add(1, Ordering::Relaxed);
+            let seed = seed.map(|s| s.wrapping_add(game));
             if send
-                .send(do_battle(p1_eval.clone(), p2_eval.clone()))
+                .send((seed, do_battle(p1_eval.clone(), p2_eval.clone(), seed)))
                 .is_err()
             {
                 break;
@@ -40,7 +52,7 @@ fn main() {
 
     while p1_wins + p2_wins < games {
         match recv.recv() {
-            Ok((replay, p1_won)) => {
+            Ok((seed, (replay, p1_won))) => {
                 if p1_won {
                     p1_wins += 1;
                 } else {
@@ -52,6 +64,15 @@ fn main() {
                 bincode::serialize_into(&mut encoder, &replay).unwrap();
                 encoder.finish().unwrap();
 
+                if let Some(death) = replay.replay.death_info() {
+                    println!(
+                        "Player {} topped out at height {} with {} garbage pending",
+                        death.player, death.final_height, death.pending_garbage
+                    );
+                }
+                if let Some(seed) = seed {
+                    println!("Game seed: {}", seed);
+                }
                 println!("{} of {}", p1_wins + p2_wins, games);
                 println!("{} - {}", p1_wins, p2_wins);
             }
@@ -63,20 +84,30 @@ fn main() {
     println!("p = {:.4}", p);
 }
 
-fn do_battle(p1: impl Evaluator + Clone, p2: impl Evaluator + Clone) -> (InfoReplay, bool) {
+fn do_battle(
+    p1: impl Evaluator + Clone,
+    p2: impl Evaluator + Clone,
+    seed: Option<u64>,
+) -> (InfoReplay, bool) {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
     let mut battle = Battle::new(
         GameConfig::default(),
         GameConfig::default(),
-        thread_rng().gen(),
-        thread_rng().gen(),
-        thread_rng().gen(),
+        rng.gen(),
+        rng.gen(),
+        rng.gen(),
     );
 
     battle.replay.p1_name = format!("Cold Clear\n{}", p1.name());
     battle.replay.p2_name = format!("Cold Clear\n{}", p2.name());
 
-    let mut p1 = BotInput::new(battle.player_1.board.to_compressed(), p1);
-    let mut p2 = BotInput::new(battle.player_2.board.to_compressed(), p2);
+    let p1_seed = seed.map(|_| rng.gen());
+    let p2_seed = seed.map(|_| rng.gen());
+    let mut p1 = BotInput::new(battle.player_1.board.to_compressed(), p1, p1_seed);
+    let mut p2 = BotInput::new(battle.player_2.board.to_compressed(), p2, p2_seed);
 
     let mut p1_info_updates = VecDeque::new();
     let mut p2_info_updates = VecDeque::new();
@@ -139,3 +170,30 @@ pub struct InfoReplay {
     pub p1_info_updates: VecDeque<Option<cold_clear::Info>>,
     pub p2_info_updates: VecDeque<Option<cold_clear::Info>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use cold_clear::evaluation::Standard;
+
+    use super::*;
+
+    /// A bot that stacks as high as it can, so games end quickly.
+    fn reckless() -> Standard {
+        Standard {
+            height: 100,
+            top_half: 0,
+            top_quarter: 0,
+            jeopardy: 0,
+            ..Standard::default()
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_identical_replays() {
+        let play = || {
+            let (replay, _) = do_battle(reckless(), reckless(), Some(3));
+            bincode::serialize(&replay).unwrap()
+        };
+        assert_eq!(play(), play());
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::VecDeque;
+use std::convert::TryInto;
 use std::iter::DoubleEndedIterator;
 
 use arrayvec::ArrayVec;
@@ -7,8 +8,13 @@ use serde::{Deserialize, Serialize};
 
 use crate::*;
 
+/// A playfield along with the queue, hold piece, and combo and back-to-back state.
+///
+/// The field is 40 rows tall and `W` columns wide. `W` can be at most 10, and narrower boards
+/// are only supported by the board itself and the move generator; the bot's evaluators and
+/// search assume the standard width of 10.
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Board<R = u16> {
+pub struct Board<R = u16, const W: usize = 10> {
     cells: ArrayVec<[R; 40]>,
     column_heights: [i32; 10],
     pub combo: u32,
@@ -16,8 +22,42 @@ pub struct Board<R = u16> {
     pub hold_piece: Option<Piece>,
     next_pieces: VecDeque<Piece>,
     pub bag: EnumSet<Piece>,
+    /// The number of pieces that have been locked on this board.
+    #[serde(default)]
+    pub piece_count: u32,
+    /// The number of back-to-back eligible line clears made in a row, or 0 if there haven't been
+    /// any since the last line clear that wasn't. Some games scale the back-to-back bonus with
+    /// this. It is kept up to date by `lock_piece` along with `b2b_bonus`.
+    #[serde(default)]
+    pub b2b_chain: u32,
 }
 
+/// The reason a field was rejected by `Board::try_set_field`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum FieldError {
+    /// The row is completely filled.
+    FullRow(usize),
+    /// The row has cells in it, but there is a completely empty row below it.
+    FloatingRow { row: usize, empty_row: usize },
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FieldError::FullRow(y) => write!(f, "row {} is full", y),
+            FieldError::FloatingRow { row, empty_row } => {
+                write!(f, "row {} is floating above empty row {}", row, empty_row)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+/// Storage for a single row of the board.
+///
+/// `u16` only stores whether each cell is occupied, which is all the bot needs. `ColoredRow` also
+/// keeps the color of each cell, which follows the cell around as pieces lock and lines clear.
 pub trait Row: Copy + Clone + 'static {
     fn set(&mut self, x: usize, color: CellColor);
     fn get(&self, x: usize) -> bool;
@@ -29,9 +69,10 @@ pub trait Row: Copy + Clone + 'static {
     const SOLID: &'static Self;
 }
 
-impl<R: Row> Board<R> {
+impl<R: Row, const W: usize> Board<R, W> {
     /// Creates a blank board with an empty queue.
     pub fn new() -> Self {
+        assert!(W <= 10, "boards can be at most 10 columns wide");
         Board {
             cells: [*R::EMPTY; 40].into(),
             column_heights: [0; 10],
@@ -40,17 +81,20 @@ impl<R: Row> Board<R> {
             hold_piece: None,
             next_pieces: VecDeque::new(),
             bag: EnumSet::all(),
+            piece_count: 0,
+            b2b_chain: 0,
         }
     }
 
     /// Creates a board with existing field, remain pieces in the bag, hold piece, back-to-back status and combo count.
     pub fn new_with_state(
-        field: [[bool; 10]; 40],
+        field: [[bool; W]; 40],
         bag_remain: EnumSet<Piece>,
         hold: Option<Piece>,
         b2b: bool,
         combo: u32,
     ) -> Self {
+        assert!(W <= 10, "boards can be at most 10 columns wide");
         let mut board = Board {
             cells: [*R::EMPTY; 40].into(),
             column_heights: [0; 10],
@@ -63,11 +107,23 @@ impl<R: Row> Board<R> {
             } else {
                 bag_remain
             },
+            piece_count: 0,
+            b2b_chain: b2b as u32,
         };
         board.set_field(field);
         board
     }
 
+    /// Creates a blank board with an empty queue and the specified number of garbage rows at the
+    /// bottom of the field, each with a single randomly placed hole.
+    pub fn with_starting_garbage(rows: usize, rng: &mut impl rand::Rng) -> Self {
+        let mut board = Board::new();
+        for _ in 0..rows {
+            board.add_garbage(rng.gen_range(0, W));
+        }
+        board
+    }
+
     /// Randomly selects a piece from the bag.
     ///
     /// This function does not remove the generated piece from the bag.
@@ -105,7 +161,12 @@ impl<R: Row> Board<R> {
         let mut cleared = ArrayVec::new();
         let mut lineno = 0;
         self.cells.retain(|r| {
-            let full = r.is_full();
+            // rows only store 10 cells, so on narrower boards the rest of the row is ignored
+            let full = if W == 10 {
+                r.is_full()
+            } else {
+                (0..W).all(|x| r.get(x))
+            };
             if full {
                 cleared.push(lineno);
             }
@@ -116,7 +177,7 @@ impl<R: Row> Board<R> {
         for _ in 0..cleared.len() {
             self.cells.push(*R::EMPTY);
         }
-        for x in 0..10 {
+        for x in 0..W {
             self.column_heights[x] -= cleared.len() as i32;
             while self.column_heights[x] > 0
                 && !self.cells[self.column_heights[x] as usize - 1].get(x)
@@ -128,7 +189,7 @@ impl<R: Row> Board<R> {
     }
 
     pub fn occupied(&self, x: i32, y: i32) -> bool {
-        x < 0 || y < 0 || x >= 10 || y >= 40 || (self.cells[y as usize].get(x as usize))
+        x < 0 || y < 0 || x >= W as i32 || y >= 40 || (self.cells[y as usize].get(x as usize))
     }
 
     pub fn get_row(&self, y: i32) -> &R {
@@ -141,6 +202,18 @@ impl<R: Row> Board<R> {
         }
     }
 
+    /// The color of the specified cell. Like `occupied`, cells outside the board are filled
+    /// except for those above it.
+    ///
+    /// Boards using `u16` rows don't keep track of colors, so every filled cell is `Garbage`.
+    pub fn cell_color(&self, x: i32, y: i32) -> CellColor {
+        if x < 0 || x >= W as i32 {
+            CellColor::Unclearable
+        } else {
+            self.get_row(y).cell_color(x as usize)
+        }
+    }
+
     pub fn set_cell_color(&mut self, x: i32, y: i32, color: CellColor) {
         self.cells[y as usize].set(x as usize, color);
         let h = &mut self.column_heights[x as usize];
@@ -168,11 +241,20 @@ impl<R: Row> Board<R> {
         piece.cells().iter().any(|&(x, y)| self.occupied(x, y - 1))
     }
 
+    /// Checks whether the piece could lock where it is: it must fit in the field without
+    /// overlapping any filled cells and be resting on the stack or the floor.
+    ///
+    /// This does not check whether the piece can actually be moved there from where it spawns.
+    pub fn is_placement_legal(&self, piece: &FallingPiece) -> bool {
+        !self.obstructed(piece) && self.on_stack(piece)
+    }
+
     /// Does all logic associated with locking a piece.
     ///
     /// Clears lines, detects clear kind, calculates garbage, maintains combo and back-to-back
     /// state, detects perfect clears, detects lockout.
     pub fn lock_piece(&mut self, piece: FallingPiece) -> LockResult {
+        self.piece_count += 1;
         let mut locked_out = true;
         for &(x, y) in &piece.cells() {
             self.cells[y as usize].set(x as usize, piece.kind.0.color());
@@ -197,8 +279,10 @@ impl<R: Row> Board<R> {
                     did_b2b = true;
                 }
                 self.b2b_bonus = true;
+                self.b2b_chain += 1;
             } else {
                 self.b2b_bonus = false;
+                self.b2b_chain = 0;
             }
 
             if self.combo as usize >= COMBO_GARBAGE.len() {
@@ -243,6 +327,26 @@ impl<R: Row> Board<R> {
         hold
     }
 
+    /// Returns the board after the next piece is placed at the specified location, along with the
+    /// result of placing it.
+    ///
+    /// If `hold` is true, the next piece is held and the piece placed is the previous hold piece,
+    /// or the piece after the next piece if there was nothing in hold. Panics if the queue doesn't
+    /// have the pieces this needs.
+    pub fn with_placement(&self, piece: FallingPiece, hold: bool) -> (Self, LockResult) {
+        let mut board = self.clone();
+        let mut next = board.advance_queue().unwrap();
+        if hold {
+            next = match board.hold(next) {
+                Some(held) => held,
+                None => board.advance_queue().unwrap(),
+            };
+        }
+        debug_assert_eq!(next, piece.kind.0);
+        let lock = board.lock_piece(piece);
+        (board, lock)
+    }
+
     pub fn next_queue<'a>(&'a self) -> impl DoubleEndedIterator<Item = Piece> + 'a {
         self.next_pieces.iter().copied()
     }
@@ -252,13 +356,13 @@ impl<R: Row> Board<R> {
         self.next_pieces.pop_front()
     }
 
-    pub fn column_heights(&self) -> &[i32; 10] {
-        &self.column_heights
+    pub fn column_heights(&self) -> &[i32; W] {
+        self.column_heights[..W].try_into().unwrap()
     }
 
     pub fn add_garbage(&mut self, col: usize) -> bool {
         let mut row = *R::EMPTY;
-        for x in 0..10 {
+        for x in 0..W {
             if x == col {
                 if self.column_heights[x] != 0 {
                     self.column_heights[x] += 1;
@@ -273,14 +377,50 @@ impl<R: Row> Board<R> {
         dead
     }
 
-    pub fn to_compressed(&self) -> Board {
+    /// Pushes `lines` rows of garbage in from the bottom of the field, cycling through
+    /// `hole_columns` for the hole of each row. Returns whether any filled cells were pushed off
+    /// the top of the field.
+    pub fn add_garbage_lines(&mut self, lines: u32, hole_columns: &[usize]) -> bool {
+        assert!(!hole_columns.is_empty(), "no garbage hole columns");
+        assert!(
+            hole_columns.iter().all(|&c| c < W),
+            "garbage hole column out of range"
+        );
+        let mut dead = false;
+        for &col in hole_columns.iter().cycle().take(lines as usize) {
+            dead |= self.add_garbage(col);
+        }
+        dead
+    }
+
+    /// Pushes `lines` rows of garbage in from the bottom of the field. The hole of the first row
+    /// is random, and each row after that moves the hole to a random column with probability
+    /// `messiness`. Returns whether any filled cells were pushed off the top of the field.
+    pub fn add_messy_garbage_lines(
+        &mut self,
+        lines: u32,
+        messiness: f64,
+        rng: &mut impl rand::Rng,
+    ) -> bool {
+        let mut col = rng.gen_range(0, W);
+        let mut dead = false;
+        for i in 0..lines {
+            if i != 0 && rng.gen_bool(messiness) {
+                col = rng.gen_range(0, W);
+            }
+            dead |= self.add_garbage(col);
+        }
+        dead
+    }
+
+    pub fn to_compressed(&self) -> Board<u16, W> {
         Board {
             cells: self
                 .cells
                 .iter()
                 .map(|r| {
                     let mut row = 0;
-                    for x in 0..10 {
+                    for x in 0..W {
                         row.set(x, r.cell_color(x));
                     }
                     row
@@ -292,15 +432,17 @@ impl<R: Row> Board<R> {
             next_pieces: self.next_pieces.clone(),
             hold_piece: self.hold_piece,
             bag: self.bag,
+            piece_count: self.piece_count,
+            b2b_chain: self.b2b_chain,
         }
     }
 
-    pub fn set_field(&mut self, field: [[bool; 10]; 40]) {
+    pub fn set_field(&mut self, field: [[bool; W]; 40]) {
         self.cells.clear();
         self.column_heights = [0; 10];
         for y in 0..40 {
             let mut r = *R::EMPTY;
-            for x in 0..10 {
+            for x in 0..W {
                 if field[y][x] {
                     r.set(x, CellColor::Garbage);
                     self.column_heights[x] = y as i32 + 1;
@@ -310,10 +452,34 @@ impl<R: Row> Board<R> {
         }
     }
 
-    pub fn get_field(&self) -> [[bool; 10]; 40] {
-        let mut field = [[false; 10]; 40];
+    /// Like `set_field`, but first checks that the field could occur in a real game.
+    ///
+    /// Full rows are rejected since they would have been cleared, as are rows with cells in them
+    /// that are above a completely empty row, since nothing could be holding those cells up. The
+    /// board is left unchanged if the field is rejected.
+    pub fn try_set_field(&mut self, field: [[bool; W]; 40]) -> Result<(), FieldError> {
+        let mut empty_row = None;
         for y in 0..40 {
-            for x in 0..10 {
+            let count = field[y].iter().filter(|&&c| c).count();
+            if count == W {
+                return Err(FieldError::FullRow(y));
+            } else if count == 0 {
+                empty_row.get_or_insert(y);
+            } else if let Some(below) = empty_row {
+                return Err(FieldError::FloatingRow {
+                    row: y,
+                    empty_row: below,
+                });
+            }
+        }
+        self.set_field(field);
+        Ok(())
+    }
+
+    pub fn get_field(&self) -> [[bool; W]; 40] {
+        let mut field = [[false; W]; 40];
+        for y in 0..40 {
+            for x in 0..W {
                 field[y][x] = self.occupied(x as i32, y as i32)
             }
         }
@@ -399,3 +565,158 @@ impl Row for ColoredRow {
     const SOLID: &'static Self = &ColoredRow([CellColor::Unclearable; 10]);
     const EMPTY: &'static Self = &ColoredRow([CellColor::Empty; 10]);
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn starting_garbage_rows_have_one_hole() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let board = Board::<ColoredRow>::with_starting_garbage(6, &mut rng);
+        for y in 0..6 {
+            let row = board.get_row(y);
+            let garbage = (0..10)
+                .filter(|&x| row.cell_color(x) == CellColor::Garbage)
+                .count();
+            let empty = (0..10).filter(|&x| !row.get(x)).count();
+            assert_eq!((garbage, empty), (9, 1), "row {}", y);
+        }
+        assert!(board.get_row(6).is_empty());
+    }
+
+    #[test]
+    fn colors_follow_locked_cells_through_line_clears() {
+        let mut field = [[false; 10]; 40];
+        for x in (0..3).chain(7..10) {
+            field[0][x] = true;
+        }
+        let mut board = Board::<ColoredRow>::new();
+        board.set_field(field);
+
+        let t = FallingPiece {
+            kind: PieceState(Piece::T, RotationState::North),
+            x: 1,
+            y: 1,
+            tspin: TspinStatus::None,
+        };
+        board.lock_piece(t);
+        assert_eq!(board.cell_color(1, 2), CellColor::T);
+        assert_eq!(board.cell_color(0, 0), CellColor::Garbage);
+
+        let i = FallingPiece {
+            kind: PieceState(Piece::I, RotationState::North),
+            x: 4,
+            y: 0,
+            tspin: TspinStatus::None,
+        };
+        let lock = board.lock_piece(i);
+        assert_eq!(&lock.cleared_lines[..], &[0]);
+        // the T moved down with the rest of the board, and nothing of the cleared row is left
+        assert_eq!(board.cell_color(0, 0), CellColor::T);
+        assert_eq!(board.cell_color(1, 1), CellColor::T);
+        assert_eq!(board.cell_color(5, 0), CellColor::Empty);
+        // u16 boards don't track colors
+        let mut plain = Board::<u16>::new();
+        plain.lock_piece(t);
+        assert_eq!(plain.cell_color(1, 1), CellColor::Garbage);
+    }
+
+    #[test]
+    fn placement_legality() {
+        let mut field = [[false; 10]; 40];
+        for x in 0..3 {
+            field[0][x] = true;
+        }
+        let mut board = Board::<u16>::new();
+        board.set_field(field);
+        let t = |x, y| FallingPiece {
+            kind: PieceState(Piece::T, RotationState::North),
+            x,
+            y,
+            tspin: TspinStatus::None,
+        };
+
+        // resting on the stack and on the floor
+        assert!(board.is_placement_legal(&t(1, 1)));
+        assert!(board.is_placement_legal(&t(5, 0)));
+        // overlapping the stack
+        assert!(!board.is_placement_legal(&t(1, 0)));
+        // hanging in the air
+        assert!(!board.is_placement_legal(&t(5, 3)));
+        // sticking out of the field
+        assert!(!board.is_placement_legal(&t(0, 1)));
+    }
+
+    #[test]
+    fn floating_cell_is_rejected() {
+        let mut field = [[false; 10]; 40];
+        field[0][0] = true;
+        field[2][5] = true;
+        let mut board = Board::<u16>::new();
+        assert_eq!(
+            board.try_set_field(field),
+            Err(FieldError::FloatingRow {
+                row: 2,
+                empty_row: 1
+            })
+        );
+        // the board wasn't changed
+        assert_eq!(board.get_field(), [[false; 10]; 40]);
+
+        field[1][5] = true;
+        assert_eq!(board.try_set_field(field), Ok(()));
+        assert_eq!(board.get_field(), field);
+
+        field[0] = [true; 10];
+        assert_eq!(board.try_set_field(field), Err(FieldError::FullRow(0)));
+    }
+
+    #[test]
+    fn b2b_chain_counts_hard_clears_in_a_row() {
+        let mut field = [[false; 10]; 40];
+        for row in &mut field[..9] {
+            for cell in &mut row[..9] {
+                *cell = true;
+            }
+        }
+        let mut board = Board::<u16>::new();
+        board.set_field(field);
+        let well = |y| FallingPiece {
+            kind: PieceState(Piece::I, RotationState::East),
+            x: 9,
+            y,
+            tspin: TspinStatus::None,
+        };
+
+        board.lock_piece(well(2));
+        assert_eq!((board.b2b_chain, board.b2b_bonus), (1, true));
+        assert!(board.lock_piece(well(2)).b2b);
+        assert_eq!(board.b2b_chain, 2);
+        // the single left over breaks the chain
+        board.lock_piece(well(2));
+        assert_eq!((board.b2b_chain, board.b2b_bonus), (0, false));
+    }
+
+    #[test]
+    fn garbage_lines_have_holes_in_the_given_column() {
+        let mut board = Board::<u16>::new();
+        board.lock_piece(FallingPiece {
+            kind: PieceState(Piece::T, RotationState::North),
+            x: 5,
+            y: 0,
+            tspin: TspinStatus::None,
+        });
+
+        assert!(!board.add_garbage_lines(4, &[3]));
+        for y in 0..4 {
+            let empty: Vec<_> = (0..10).filter(|&x| !board.occupied(x, y)).collect();
+            assert_eq!(empty, vec![3], "row {}", y);
+        }
+        // the T was pushed up on top of the garbage
+        assert!(board.occupied(5, 5) && board.occupied(4, 4) && !board.occupied(4, 5));
+        assert_eq!(board.column_heights(), &[4, 4, 4, 0, 5, 6, 5, 4, 4, 4]);
+    }
+}
@@ -71,3 +71,123 @@ impl From<RotationState> for fumen::RotationState {
         }
     }
 }
+
+impl From<CellColor> for fumen::CellColor {
+    fn from(v: CellColor) -> fumen::CellColor {
+        match v {
+            CellColor::I => fumen::CellColor::I,
+            CellColor::O => fumen::CellColor::O,
+            CellColor::T => fumen::CellColor::T,
+            CellColor::L => fumen::CellColor::L,
+            CellColor::J => fumen::CellColor::J,
+            CellColor::S => fumen::CellColor::S,
+            CellColor::Z => fumen::CellColor::Z,
+            CellColor::Garbage | CellColor::Unclearable => fumen::CellColor::Grey,
+            CellColor::Empty => fumen::CellColor::Empty,
+        }
+    }
+}
+
+impl<R: Row, const W: usize> Board<R, W> {
+    /// Encodes the field as a single page fumen. Only the bottom 23 rows fit in a fumen, and the
+    /// columns to the right of narrow boards are filled with garbage.
+    pub fn to_fumen(&self) -> String {
+        self.fumen().encode()
+    }
+
+    fn fumen(&self) -> fumen::Fumen {
+        let mut fumen = fumen::Fumen::default();
+        let page = fumen.add_page();
+        for (y, row) in page.field.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = self.cell_color(x as i32, y as i32).into();
+            }
+        }
+        fumen
+    }
+}
+
+/// Encodes a plan as a fumen starting from `board`, with one page per placement. Each page is
+/// commented with the kind of placement and the garbage it sends.
+pub fn plan_to_fumen<R: Row, const W: usize>(
+    board: &Board<R, W>,
+    plan: &[(FallingPiece, LockResult)],
+) -> String {
+    let mut fumen = board.fumen();
+    for (i, &(piece, ref lock)) in plan.iter().enumerate() {
+        // the field of each page after the first is the result of locking the previous piece
+        let page = match i {
+            0 => &mut fumen.pages[0],
+            _ => fumen.add_page(),
+        };
+        page.piece = Some(piece.into());
+        let kind = match lock.perfect_clear {
+            true => "Perfect Clear",
+            false => lock.placement_kind.name(),
+        };
+        page.comment = match (kind, lock.garbage_sent) {
+            ("", 0) => None,
+            ("", sent) => Some(format!("{} sent", sent)),
+            (kind, 0) => Some(kind.to_owned()),
+            (kind, sent) => Some(format!("{} ({} sent)", kind, sent)),
+        };
+    }
+    fumen.encode()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+
+    use super::*;
+
+    fn assert_same_field<R: Row>(board: &Board<R>, page: &fumen::Page) {
+        for (y, row) in page.field.iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                let expected: fumen::CellColor = board.cell_color(x as i32, y as i32).into();
+                assert_eq!(cell, expected, "cell ({}, {})", x, y);
+            }
+        }
+    }
+
+    fn piece(kind: Piece, rotation: RotationState, x: i32, y: i32) -> FallingPiece {
+        FallingPiece {
+            kind: PieceState(kind, rotation),
+            x,
+            y,
+            tspin: TspinStatus::None,
+        }
+    }
+
+    #[test]
+    fn field_round_trips() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut board = Board::<ColoredRow>::with_starting_garbage(3, &mut rng);
+        board.lock_piece(piece(Piece::T, RotationState::North, 4, 3));
+        board.lock_piece(piece(Piece::I, RotationState::East, 0, 5));
+
+        let fumen = fumen::Fumen::decode(&board.to_fumen()).unwrap();
+        assert_eq!(fumen.pages.len(), 1);
+        assert_same_field(&board, &fumen.pages[0]);
+    }
+
+    #[test]
+    fn plan_has_a_page_per_placement() {
+        let mut board = Board::<ColoredRow>::new();
+        let start = board.clone();
+        let plan: Vec<_> = [
+            piece(Piece::O, RotationState::North, 0, 0),
+            piece(Piece::L, RotationState::North, 3, 0),
+        ]
+        .iter()
+        .map(|&p| (p, board.lock_piece(p)))
+        .collect();
+
+        let fumen = fumen::Fumen::decode(&plan_to_fumen(&start, &plan)).unwrap();
+        assert_eq!(fumen.pages.len(), 2);
+        assert_same_field(&start, &fumen.pages[0]);
+        for (page, &(p, _)) in fumen.pages.iter().zip(&plan) {
+            assert!(FallingPiece::from(page.piece.unwrap()).same_location(&p));
+        }
+    }
+}
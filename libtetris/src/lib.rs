@@ -6,6 +6,8 @@ mod piece;
 #[cfg(feature = "fumen")]
 mod fumen_conv;
 
+#[cfg(feature = "pcf")]
+mod pc;
 #[cfg(feature = "pcf")]
 mod pcf_conv;
 
@@ -14,6 +16,11 @@ pub use lock_data::*;
 pub use moves::*;
 pub use piece::*;
 
+#[cfg(feature = "fumen")]
+pub use fumen_conv::plan_to_fumen;
+#[cfg(feature = "pcf")]
+pub use pc::pc_solutions;
+
 #[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
 pub struct Controller {
     pub left: bool,
@@ -23,12 +30,14 @@ pub struct Controller {
     pub soft_drop: bool,
     pub hard_drop: bool,
     pub hold: bool,
+    pub rotate_180: bool,
 }
 
 impl serde::Serialize for Controller {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         serializer.serialize_u8(
-            (self.left as u8) << 1
+            self.rotate_180 as u8
+                | (self.left as u8) << 1
                 | (self.right as u8) << 2
                 | (self.rotate_left as u8) << 3
                 | (self.rotate_right as u8) << 4
@@ -56,6 +65,7 @@ impl<'de> serde::Deserialize<'de> for Controller {
                     hold: (v >> 5) & 1 != 0,
                     soft_drop: (v >> 6) & 1 != 0,
                     hard_drop: (v >> 7) & 1 != 0,
+                    rotate_180: v & 1 != 0,
                 })
             }
         }
@@ -74,7 +74,8 @@ impl PlacementKind {
             (3, TspinStatus::None) => PlacementKind::Clear3,
             (3, TspinStatus::Mini) => unreachable!(),
             (3, _) => PlacementKind::Tspin3,
-            (4, TspinStatus::None) => PlacementKind::Clear4,
+            // only possible with all-spin rules, which don't have a bonus for spin quads
+            (4, _) => PlacementKind::Clear4,
             _ => unreachable!(),
         }
     }
@@ -4,7 +4,10 @@ use std::collections::{BinaryHeap, HashMap, HashSet};
 use arrayvec::ArrayVec;
 use serde::{Deserialize, Serialize};
 
-use crate::{Board, FallingPiece, Piece, PieceMovement, PieceState, RotationState, TspinStatus};
+use crate::{
+    Board, FallingPiece, KickTable, Piece, PieceMovement, PieceState, RotationState,
+    SpinDetectionMode, TspinStatus,
+};
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct InputList {
@@ -54,15 +57,30 @@ impl PartialOrd for Placement {
     }
 }
 
-pub fn find_moves(board: &Board, mut spawned: FallingPiece, mode: MovementMode) -> Vec<Placement> {
+/// Finds every placement of `spawned` reachable with the movement mode, along with the fastest
+/// inputs to reach each one. 180 degree rotations are only used if `allow_flip` is set, and 90
+/// degree rotations use the kicks of `kick_table`. Spins are detected according to `spins`.
+pub fn find_moves<const W: usize>(
+    board: &Board<u16, W>,
+    mut spawned: FallingPiece,
+    mode: MovementMode,
+    allow_flip: bool,
+    kick_table: KickTable,
+    spins: SpinDetectionMode,
+) -> Vec<Placement> {
     let mut locks = HashMap::with_capacity(128);
     let mut checked = HashSet::with_capacity(128);
     let mut check_queue = Vec::with_capacity(64);
     let fast_mode;
 
-    if board.column_heights().iter().all(|&v| v < 16) {
+    if W == 10
+        && board.column_heights().iter().all(|&v| v < 16)
+        && (mode == MovementMode::TwentyG || spawned.y >= 19)
+    {
         // We know that we can reach any column and rotation state without bumping into the terrain
-        // at 0G here, so we can just grab those starting positions.
+        // at 0G here, so we can just grab those starting positions. This doesn't hold if the piece
+        // has already fallen from where it spawned, and the starting positions are only known for
+        // standard width boards.
         let starts = match mode {
             MovementMode::TwentyG => vec![(
                 spawned,
@@ -123,6 +141,8 @@ pub fn find_moves(board: &Board, mut spawned: FallingPiece, mode: MovementMode)
                 &mut check_queue,
                 mode,
                 fast_mode,
+                kick_table,
+                spins,
                 PieceMovement::Left,
                 false,
             );
@@ -134,6 +154,8 @@ pub fn find_moves(board: &Board, mut spawned: FallingPiece, mode: MovementMode)
                 &mut check_queue,
                 mode,
                 fast_mode,
+                kick_table,
+                spins,
                 PieceMovement::Right,
                 false,
             );
@@ -147,6 +169,8 @@ pub fn find_moves(board: &Board, mut spawned: FallingPiece, mode: MovementMode)
                     &mut check_queue,
                     mode,
                     fast_mode,
+                    kick_table,
+                    spins,
                     PieceMovement::Cw,
                     false,
                 );
@@ -159,9 +183,27 @@ pub fn find_moves(board: &Board, mut spawned: FallingPiece, mode: MovementMode)
                     &mut check_queue,
                     mode,
                     fast_mode,
+                    kick_table,
+                    spins,
                     PieceMovement::Ccw,
                     false,
                 );
+
+                if allow_flip {
+                    attempt(
+                        board,
+                        &moves,
+                        position,
+                        &mut checked,
+                        &mut check_queue,
+                        mode,
+                        fast_mode,
+                        kick_table,
+                        spins,
+                        PieceMovement::Flip,
+                        false,
+                    );
+                }
             }
 
             if mode == MovementMode::ZeroG {
@@ -173,6 +215,8 @@ pub fn find_moves(board: &Board, mut spawned: FallingPiece, mode: MovementMode)
                     &mut check_queue,
                     mode,
                     fast_mode,
+                    kick_table,
+                    spins,
                     PieceMovement::Left,
                     true,
                 );
@@ -185,6 +229,8 @@ pub fn find_moves(board: &Board, mut spawned: FallingPiece, mode: MovementMode)
                     &mut check_queue,
                     mode,
                     fast_mode,
+                    kick_table,
+                    spins,
                     PieceMovement::Right,
                     true,
                 );
@@ -198,6 +244,8 @@ pub fn find_moves(board: &Board, mut spawned: FallingPiece, mode: MovementMode)
                 &mut check_queue,
                 mode,
                 fast_mode,
+                kick_table,
+                spins,
                 PieceMovement::SonicDrop,
                 false,
             );
@@ -208,7 +256,14 @@ pub fn find_moves(board: &Board, mut spawned: FallingPiece, mode: MovementMode)
         lock_check(position, &mut locks, moves);
     }
 
-    locks.into_iter().map(|(_, v)| v).collect()
+    let mut placements: Vec<_> = locks.into_iter().map(|(_, v)| v).collect();
+    // HashMap iteration order is random, so the placements are sorted so that the bot's search
+    // can be reproduced.
+    placements.sort_unstable_by_key(|p| {
+        let l = p.location;
+        (l.x, l.y, l.kind.1 as u8, l.tspin as u8)
+    });
+    placements
 }
 
 fn lock_check(piece: FallingPiece, locks: &mut HashMap<FallingPiece, Placement>, moves: InputList) {
@@ -225,19 +280,21 @@ fn lock_check(piece: FallingPiece, locks: &mut HashMap<FallingPiece, Placement>,
     });
 }
 
-fn attempt(
-    board: &Board,
+fn attempt<const W: usize>(
+    board: &Board<u16, W>,
     moves: &InputList,
     mut piece: FallingPiece,
     checked: &mut HashSet<FallingPiece>,
     check_queue: &mut BinaryHeap<Placement>,
     mode: MovementMode,
     fast_mode: bool,
+    kick_table: KickTable,
+    spins: SpinDetectionMode,
     input: PieceMovement,
     repeat: bool,
 ) -> FallingPiece {
     let orig_y = piece.y;
-    if input.apply(&mut piece, board) {
+    if input.apply(&mut piece, board, kick_table, spins) {
         let mut moves = moves.clone();
         if input == PieceMovement::SonicDrop {
             // We don't actually know the soft drop speed, but 1 cell every 2 ticks is probably a
@@ -254,7 +311,10 @@ fn attempt(
             }
         }
         moves.movements.push(input);
-        while repeat && !moves.movements.is_full() && input.apply(&mut piece, board) {
+        while repeat
+            && !moves.movements.is_full()
+            && input.apply(&mut piece, board, kick_table, spins)
+        {
             // This is the DAS left/right case
             moves.movements.push(input);
             moves.time += 2;
@@ -393,3 +453,152 @@ fn start(
         },
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use enumset::EnumSet;
+
+    use super::*;
+    use crate::{PlacementKind, SpawnRule};
+
+    /// Builds a board from rows of `#` and `.`, bottom row first.
+    fn board_from(rows: &[&str]) -> Board {
+        let mut field = [[false; 10]; 40];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                field[y][x] = c == '#';
+            }
+        }
+        let mut board = Board::new();
+        board.set_field(field);
+        board
+    }
+
+    fn moves<const W: usize>(board: &Board<u16, W>, piece: Piece) -> Vec<Placement> {
+        moves_with(board, piece, KickTable::Srs, SpinDetectionMode::TSpinOnly)
+    }
+
+    fn moves_with<const W: usize>(
+        board: &Board<u16, W>,
+        piece: Piece,
+        kick_table: KickTable,
+        spins: SpinDetectionMode,
+    ) -> Vec<Placement> {
+        let spawned = SpawnRule::Row19Or20.spawn(piece, board).unwrap();
+        find_moves(
+            board,
+            spawned,
+            MovementMode::ZeroG,
+            false,
+            kick_table,
+            spins,
+        )
+    }
+
+    #[test]
+    fn placements_come_in_the_same_order() {
+        let mut board = Board::new();
+        let mut field = [[false; 10]; 40];
+        for (x, &height) in [3, 1, 0, 2, 4, 4, 1, 0, 2, 3].iter().enumerate() {
+            for row in &mut field[..height] {
+                row[x] = true;
+            }
+        }
+        board.set_field(field);
+
+        // each search uses fresh hash maps with their own iteration order
+        for &piece in &[Piece::T, Piece::L, Piece::I] {
+            let first = moves(&board, piece);
+            for _ in 0..5 {
+                assert_eq!(moves(&board, piece), first);
+            }
+        }
+    }
+
+    #[test]
+    fn narrow_boards_generate_moves() {
+        fn check<const W: usize>() {
+            let mut board = Board::<u16, W>::new();
+            let mut field = [[false; W]; 40];
+            for x in 1..W {
+                field[0][x] = true;
+            }
+            board.set_field(field);
+
+            for piece in EnumSet::<Piece>::all() {
+                let placements = moves(&board, piece);
+                assert!(!placements.is_empty(), "no moves for {:?}", piece);
+                for p in placements {
+                    for &(x, y) in &p.location.cells() {
+                        assert!(x >= 0 && (x as usize) < W && y >= 0);
+                    }
+                    board.clone().lock_piece(p.location);
+                }
+            }
+        }
+        check::<4>();
+        check::<6>();
+    }
+
+    #[test]
+    fn srs_plus_reaches_more_i_placements() {
+        let board = board_from(&["#......#.#", "##.#......", "..#.#...##"]);
+
+        let srs = moves_with(
+            &board,
+            Piece::I,
+            KickTable::Srs,
+            SpinDetectionMode::TSpinOnly,
+        );
+        let srs_plus = moves_with(
+            &board,
+            Piece::I,
+            KickTable::SrsPlus,
+            SpinDetectionMode::TSpinOnly,
+        );
+        let reaches = |moves: &[Placement], x, y| {
+            moves.iter().any(|p| {
+                p.location.kind.1 == RotationState::North
+                    && p.location.cells().contains(&(x, y))
+                    && p.location.cells().iter().all(|&(_, cy)| cy == y)
+            })
+        };
+
+        // the I piece can only get under the overhang into the bottom row with SRS+'s kicks
+        assert!(!reaches(&srs, 1, 0));
+        assert!(reaches(&srs_plus, 1, 0));
+        for p in &srs {
+            assert!(srs_plus
+                .iter()
+                .any(|q| q.location.same_location(&p.location)));
+        }
+    }
+
+    #[test]
+    fn immobile_l_only_spins_in_all_spin_mode() {
+        let board = board_from(&["##...#####", "####.#####"]);
+
+        let slot = |spins| {
+            let placements = moves_with(&board, Piece::L, KickTable::Srs, spins);
+            let p = placements
+                .into_iter()
+                .find(|p| {
+                    p.location.kind.1 == RotationState::North
+                        && p.location.x == 3
+                        && p.location.y == 0
+                })
+                .unwrap();
+            board.clone().lock_piece(p.location).placement_kind
+        };
+
+        assert_eq!(slot(SpinDetectionMode::TSpinOnly), PlacementKind::Clear2);
+        assert_eq!(
+            slot(SpinDetectionMode::AllSpinImmobile),
+            PlacementKind::Tspin2
+        );
+        assert_eq!(
+            slot(SpinDetectionMode::AllSpin4Point),
+            PlacementKind::Tspin2
+        );
+    }
+}
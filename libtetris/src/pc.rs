@@ -0,0 +1,99 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{Board, FallingPiece, Row};
+
+/// The most solutions `pc_solutions` will return, since some residues have a huge number.
+const MAX_SOLUTIONS: usize = 1000;
+
+/// Finds the ways to perfect clear the board using the hold piece and next queue.
+///
+/// Only perfect clears that finish within the bottom 4 rows of the field and take at most
+/// `max_pieces` placements are considered. Each solution lists the placements in the order they
+/// are made. The hold piece is used if it is known. At most 1000 solutions are returned.
+pub fn pc_solutions<R: Row>(board: &Board<R>, max_pieces: u32) -> Vec<Vec<FallingPiece>> {
+    if board.column_heights().iter().any(|&h| h > 4) {
+        return vec![];
+    }
+    let mut bitboard = pcf::BitBoard(0);
+    for y in 0..4 {
+        for x in 0..10 {
+            if board.occupied(x, y) {
+                bitboard.0 |= 1 << (10 * y + x);
+            }
+        }
+    }
+    let empty_cells = 40 - bitboard.0.count_ones();
+    let pieces = empty_cells as usize / 4;
+    if empty_cells % 4 != 0 || pieces > max_pieces as usize {
+        return vec![];
+    }
+
+    let hold_enabled = board.hold_piece.is_some();
+    let queue: Vec<pcf::Piece> = board
+        .hold_piece
+        .into_iter()
+        .chain(board.next_queue())
+        .take(pieces + hold_enabled as usize)
+        .map(Into::into)
+        .collect();
+    if queue.len() < pieces + hold_enabled as usize {
+        return vec![];
+    }
+
+    let abort = AtomicBool::new(false);
+    let mut solutions = vec![];
+    pcf::solve_pc(
+        &queue,
+        bitboard,
+        hold_enabled,
+        false,
+        &abort,
+        pcf::placeability::simple_srs_spins,
+        |soln| {
+            let mut b = bitboard;
+            let mut placements = Vec::with_capacity(soln.len());
+            for &placement in soln {
+                placements.push(placement.srs_piece(b)[0].into());
+                b = b.combine(placement.board());
+            }
+            if !solutions.contains(&placements) {
+                solutions.push(placements);
+            }
+            if solutions.len() >= MAX_SOLUTIONS {
+                abort.store(true, Ordering::Relaxed);
+            }
+        },
+    );
+    solutions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Piece;
+
+    #[test]
+    fn solutions_clear_the_board() {
+        let mut field = [[false; 10]; 40];
+        for row in &mut field[..4] {
+            for cell in &mut row[2..] {
+                *cell = true;
+            }
+        }
+        let mut board = Board::<u16>::new();
+        board.set_field(field);
+        board.add_next_piece(Piece::I);
+        board.add_next_piece(Piece::I);
+
+        let solutions = pc_solutions(&board, 10);
+        assert!(!solutions.is_empty());
+        for solution in solutions {
+            let mut result = board.clone();
+            for placement in solution {
+                assert!(!result.obstructed(&placement));
+                result.lock_piece(placement);
+            }
+            assert!(result.column_heights().iter().all(|&h| h == 0));
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use arrayvec::ArrayVec;
 use enum_map::Enum;
 use enumset::{enum_set, EnumSet, EnumSetType};
 use serde::{Deserialize, Serialize};
@@ -33,7 +34,7 @@ impl FallingPiece {
         cells
     }
 
-    pub fn shift<R: Row>(&mut self, board: &Board<R>, dx: i32, dy: i32) -> bool {
+    pub fn shift<R: Row, const W: usize>(&mut self, board: &Board<R, W>, dx: i32, dy: i32) -> bool {
         self.x += dx;
         self.y += dy;
         if board.obstructed(self) {
@@ -46,7 +47,7 @@ impl FallingPiece {
         }
     }
 
-    pub fn sonic_drop<R: Row>(&mut self, board: &Board<R>) -> bool {
+    pub fn sonic_drop<R: Row, const W: usize>(&mut self, board: &Board<R, W>) -> bool {
         let drop_by = self
             .cells()
             .iter()
@@ -74,45 +75,22 @@ impl FallingPiece {
         }
     }
 
-    fn rotate<R: Row>(&mut self, target: PieceState, board: &Board<R>) -> bool {
+    fn rotate<R: Row, const W: usize>(
+        &mut self,
+        target: PieceState,
+        board: &Board<R, W>,
+        kick_table: KickTable,
+        spins: SpinDetectionMode,
+    ) -> bool {
         let initial = *self;
         self.kind = target;
-        let initial_offsets = initial.kind.rotation_points();
-        let target_offsets = target.rotation_points();
-        let kicks = initial_offsets
-            .iter()
-            .zip(target_offsets.iter())
-            .map(|(&(x1, y1), &(x2, y2))| (x1 - x2, y1 - y2));
+        let kicks = kick_table.kicks(initial.kind, target);
 
-        for (i, (dx, dy)) in kicks.enumerate() {
+        for (i, &(dx, dy)) in kicks.iter().enumerate() {
             self.x = initial.x + dx;
             self.y = initial.y + dy;
             if !board.obstructed(self) {
-                if target.0 == Piece::T {
-                    let mut mini_corners = 0;
-                    for &(dx, dy) in &target.1.mini_tspin_corners() {
-                        if board.occupied(self.x + dx, self.y + dy) {
-                            mini_corners += 1;
-                        }
-                    }
-
-                    let mut non_mini_corners = 0;
-                    for &(dx, dy) in &target.1.non_mini_tspin_corners() {
-                        if board.occupied(self.x + dx, self.y + dy) {
-                            non_mini_corners += 1;
-                        }
-                    }
-
-                    if non_mini_corners + mini_corners >= 3 {
-                        if i == 4 || mini_corners == 2 {
-                            self.tspin = TspinStatus::Full;
-                        } else {
-                            self.tspin = TspinStatus::Mini;
-                        }
-                    } else {
-                        self.tspin = TspinStatus::None;
-                    }
-                }
+                self.detect_spin(board, i == 4, spins);
                 return true;
             }
         }
@@ -121,16 +99,136 @@ impl FallingPiece {
         false
     }
 
-    pub fn cw<R: Row>(&mut self, board: &Board<R>) -> bool {
+    /// Sets the spin status after a successful rotation according to the spin rules of the game.
+    fn detect_spin<R: Row, const W: usize>(
+        &mut self,
+        board: &Board<R, W>,
+        last_kick: bool,
+        spins: SpinDetectionMode,
+    ) {
+        match spins {
+            SpinDetectionMode::TSpinOnly => self.detect_tspin(board, last_kick),
+            SpinDetectionMode::AllSpinImmobile if self.kind.0 == Piece::T => {
+                self.detect_tspin(board, last_kick)
+            }
+            SpinDetectionMode::AllSpinImmobile | SpinDetectionMode::AllSpin4Point => {
+                self.tspin = if self.immobile(board) {
+                    TspinStatus::Full
+                } else {
+                    TspinStatus::None
+                };
+            }
+        }
+    }
+
+    /// Whether the piece is stuck, unable to move left, right, up, or down.
+    fn immobile<R: Row, const W: usize>(&self, board: &Board<R, W>) -> bool {
+        [(-1, 0), (1, 0), (0, 1), (0, -1)].iter().all(|&(dx, dy)| {
+            board.obstructed(&FallingPiece {
+                x: self.x + dx,
+                y: self.y + dy,
+                ..*self
+            })
+        })
+    }
+
+    /// Sets the T-spin status after a successful rotation. `last_kick` is whether the rotation
+    /// used the fifth kick, which upgrades T-spin minis to full T-spins.
+    fn detect_tspin<R: Row, const W: usize>(&mut self, board: &Board<R, W>, last_kick: bool) {
+        if self.kind.0 != Piece::T {
+            return;
+        }
+        let mut mini_corners = 0;
+        for &(dx, dy) in &self.kind.1.mini_tspin_corners() {
+            if board.occupied(self.x + dx, self.y + dy) {
+                mini_corners += 1;
+            }
+        }
+
+        let mut non_mini_corners = 0;
+        for &(dx, dy) in &self.kind.1.non_mini_tspin_corners() {
+            if board.occupied(self.x + dx, self.y + dy) {
+                non_mini_corners += 1;
+            }
+        }
+
+        if non_mini_corners + mini_corners >= 3 {
+            if last_kick || mini_corners == 2 {
+                self.tspin = TspinStatus::Full;
+            } else {
+                self.tspin = TspinStatus::Mini;
+            }
+        } else {
+            self.tspin = TspinStatus::None;
+        }
+    }
+
+    pub fn cw<R: Row, const W: usize>(&mut self, board: &Board<R, W>) -> bool {
+        self.cw_with(board, KickTable::Srs, SpinDetectionMode::TSpinOnly)
+    }
+
+    pub fn ccw<R: Row, const W: usize>(&mut self, board: &Board<R, W>) -> bool {
+        self.ccw_with(board, KickTable::Srs, SpinDetectionMode::TSpinOnly)
+    }
+
+    /// Like `cw`, but tries the kicks of the specified kick table instead of SRS and detects
+    /// spins using the specified rules instead of only detecting T-spins.
+    pub fn cw_with<R: Row, const W: usize>(
+        &mut self,
+        board: &Board<R, W>,
+        kick_table: KickTable,
+        spins: SpinDetectionMode,
+    ) -> bool {
         let mut target = self.kind;
         target.cw();
-        self.rotate(target, board)
+        self.rotate(target, board, kick_table, spins)
     }
 
-    pub fn ccw<R: Row>(&mut self, board: &Board<R>) -> bool {
+    /// Like `ccw`, but tries the kicks of the specified kick table instead of SRS and detects
+    /// spins using the specified rules instead of only detecting T-spins.
+    pub fn ccw_with<R: Row, const W: usize>(
+        &mut self,
+        board: &Board<R, W>,
+        kick_table: KickTable,
+        spins: SpinDetectionMode,
+    ) -> bool {
         let mut target = self.kind;
         target.ccw();
-        self.rotate(target, board)
+        self.rotate(target, board, kick_table, spins)
+    }
+
+    /// Rotates the piece 180 degrees using the TETR.IO 180 kick table.
+    pub fn flip<R: Row, const W: usize>(&mut self, board: &Board<R, W>) -> bool {
+        self.flip_with(board, SpinDetectionMode::TSpinOnly)
+    }
+
+    /// Like `flip`, but detects spins using the specified rules instead of only detecting
+    /// T-spins.
+    pub fn flip_with<R: Row, const W: usize>(
+        &mut self,
+        board: &Board<R, W>,
+        spins: SpinDetectionMode,
+    ) -> bool {
+        let initial = *self;
+        let mut target = self.kind;
+        target.flip();
+        self.kind = target;
+        // the rotation points don't describe 180 kicks, but the first one still accounts for
+        // pieces whose rotation center isn't on a cell
+        let (x1, y1) = initial.kind.rotation_points()[0];
+        let (x2, y2) = target.rotation_points()[0];
+
+        for &(dx, dy) in &initial.kind.1.flip_kicks() {
+            self.x = initial.x + x1 - x2 + dx;
+            self.y = initial.y + y1 - y2 + dy;
+            if !board.obstructed(self) {
+                self.detect_spin(board, false, spins);
+                return true;
+            }
+        }
+
+        *self = initial;
+        false
     }
 
     pub fn same_location(&self, other: &Self) -> bool {
@@ -262,6 +360,27 @@ impl RotationState {
         }
     }
 
+    pub fn flip(&mut self) {
+        use RotationState::*;
+        match self {
+            North => *self = South,
+            South => *self = North,
+            East => *self = West,
+            West => *self = East,
+        }
+    }
+
+    /// The kicks tried when rotating 180 degrees out of this state, in order.
+    pub fn flip_kicks(self) -> [(i32, i32); 6] {
+        use RotationState::*;
+        match self {
+            North => [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)],
+            South => [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)],
+            East => [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)],
+            West => [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)],
+        }
+    }
+
     pub fn mini_tspin_corners(self) -> [(i32, i32); 2] {
         use RotationState::*;
         match self {
@@ -292,6 +411,10 @@ impl PieceState {
         self.1.ccw()
     }
 
+    pub fn flip(&mut self) {
+        self.1.flip()
+    }
+
     /// Returns the cells this piece and orientation occupy relative to rotation point 1, as well
     /// as the connection directions, in no particular order.
     #[inline(always)]
@@ -459,16 +582,25 @@ pub enum PieceMovement {
     Cw,
     Ccw,
     SonicDrop,
+    /// Rotate 180 degrees. Only generated by `find_moves` when 180 rotations are allowed.
+    Flip,
 }
 
 impl PieceMovement {
-    pub fn apply(self, piece: &mut FallingPiece, board: &Board) -> bool {
+    pub fn apply<const W: usize>(
+        self,
+        piece: &mut FallingPiece,
+        board: &Board<u16, W>,
+        kick_table: KickTable,
+        spins: SpinDetectionMode,
+    ) -> bool {
         match self {
             PieceMovement::Left => piece.shift(board, -1, 0),
             PieceMovement::Right => piece.shift(board, 1, 0),
-            PieceMovement::Ccw => piece.ccw(board),
-            PieceMovement::Cw => piece.cw(board),
+            PieceMovement::Ccw => piece.ccw_with(board, kick_table, spins),
+            PieceMovement::Cw => piece.cw_with(board, kick_table, spins),
             PieceMovement::SonicDrop => piece.sonic_drop(board),
+            PieceMovement::Flip => piece.flip_with(board, spins),
         }
     }
 }
@@ -510,19 +642,114 @@ impl Direction {
     }
 }
 
+/// The wall kicks tried when rotating a piece 90 degrees, in the game's rotation system.
+///
+/// Pieces have the same shapes and rotate around the same points in every rotation system; only
+/// the kicks tried when the rotated piece doesn't fit differ.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum KickTable {
+    /// The Super Rotation System used by guideline games.
+    Srs,
+    /// SRS with TETR.IO's I piece kicks, which are symmetric between left and right.
+    SrsPlus,
+    /// Arika Rotation System style kicks: one column to the right, then one to the left. The I
+    /// piece can't kick.
+    Arika,
+}
+
+impl Default for KickTable {
+    fn default() -> Self {
+        KickTable::Srs
+    }
+}
+
+impl KickTable {
+    /// The offsets tried when rotating from `from` to `to`, in order.
+    pub fn kicks(self, from: PieceState, to: PieceState) -> ArrayVec<[(i32, i32); 5]> {
+        let from_points = from.rotation_points();
+        let to_points = to.rotation_points();
+        // the first rotation point accounts for pieces whose rotation center isn't on a cell
+        let (bx, by) = (
+            from_points[0].0 - to_points[0].0,
+            from_points[0].1 - to_points[0].1,
+        );
+        match self {
+            KickTable::SrsPlus if from.0 == Piece::I => srs_plus_i_kicks(from.1, to.1)
+                .iter()
+                .map(|&(dx, dy)| (bx + dx, by + dy))
+                .collect(),
+            KickTable::Srs | KickTable::SrsPlus => from_points
+                .iter()
+                .zip(to_points.iter())
+                .map(|(&(x1, y1), &(x2, y2))| (x1 - x2, y1 - y2))
+                .collect(),
+            KickTable::Arika if from.0 == Piece::I => [(bx, by)].iter().copied().collect(),
+            KickTable::Arika => [(bx, by), (bx + 1, by), (bx - 1, by)]
+                .iter()
+                .copied()
+                .collect(),
+        }
+    }
+}
+
+fn srs_plus_i_kicks(from: RotationState, to: RotationState) -> [(i32, i32); 5] {
+    use RotationState::*;
+    match (from, to) {
+        (North, East) => [(0, 0), (1, 0), (-2, 0), (-2, -1), (1, 2)],
+        (East, North) => [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)],
+        (East, South) => [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+        (South, East) => [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)],
+        (South, West) => [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+        (West, South) => [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)],
+        (West, North) => [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+        (North, West) => [(0, 0), (-1, 0), (2, 0), (2, -1), (-1, 2)],
+        _ => [(0, 0); 5],
+    }
+}
+
+/// Which rotations into a tight spot count as spins.
+///
+/// Spins of pieces other than T are reported as T-spins, so they are scored the same way.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SpinDetectionMode {
+    /// Only T pieces can spin, using the guideline 3-corner rule.
+    TSpinOnly,
+    /// T pieces use the 3-corner rule, and any other piece that can't move left, right, up, or
+    /// down after rotating has spun.
+    AllSpinImmobile,
+    /// Every piece, T included, has spun if it can't move left, right, up, or down after
+    /// rotating. There are no minis.
+    AllSpin4Point,
+}
+
+impl Default for SpinDetectionMode {
+    fn default() -> Self {
+        SpinDetectionMode::TSpinOnly
+    }
+}
+
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum SpawnRule {
     Row19Or20,
     Row21AndFall,
 }
 
+/// The column pieces spawn in, which is column 4 on a standard 10 wide board.
+fn spawn_column(width: usize) -> i32 {
+    (width as i32 - 1) / 2
+}
+
 impl SpawnRule {
-    pub fn spawn<R: Row>(self, piece: Piece, board: &Board<R>) -> Option<FallingPiece> {
+    pub fn spawn<R: Row, const W: usize>(
+        self,
+        piece: Piece,
+        board: &Board<R, W>,
+    ) -> Option<FallingPiece> {
         match self {
             SpawnRule::Row19Or20 => {
                 let mut spawned = FallingPiece {
                     kind: PieceState(piece, RotationState::North),
-                    x: 4,
+                    x: spawn_column(W),
                     y: 19,
                     tspin: TspinStatus::None,
                 };
@@ -537,7 +764,7 @@ impl SpawnRule {
             SpawnRule::Row21AndFall => {
                 let mut spawned = FallingPiece {
                     kind: PieceState(piece, RotationState::North),
-                    x: 4,
+                    x: spawn_column(W),
                     y: 21,
                     tspin: TspinStatus::None,
                 };
@@ -0,0 +1,66 @@
+use libtetris::*;
+use opening_book::MemoryBook;
+
+/// How many of the piece sequences reachable from the start of the game the book has moves for.
+#[derive(Default)]
+pub struct Coverage {
+    pub covered: usize,
+    /// The pieces revealed up to each sequence the book has no move for, in order.
+    pub uncovered: Vec<String>,
+}
+
+impl Coverage {
+    pub fn total(&self) -> usize {
+        self.covered + self.uncovered.len()
+    }
+}
+
+/// Plays the book's moves from an empty board for every possible order of pieces, stopping after
+/// `depth` placements or when the book has no move.
+pub fn coverage(book: &MemoryBook, depth: usize) -> Coverage {
+    let mut coverage = Coverage::default();
+    explore(book, Board::new(), &mut String::new(), depth, &mut coverage);
+    coverage
+}
+
+fn explore(
+    book: &MemoryBook,
+    board: Board,
+    revealed: &mut String,
+    depth: usize,
+    coverage: &mut Coverage,
+) {
+    if depth == 0 {
+        return;
+    }
+
+    // books tell positions apart by the current and hold pieces and the pieces after them
+    let known = board.hold_piece.is_some() as usize + board.next_queue().count();
+    if known < 2 + book.next_pieces() {
+        for piece in board.bag {
+            let mut board = board.clone();
+            board.add_next_piece(piece);
+            revealed.push(piece.to_char());
+            explore(book, board, revealed, depth, coverage);
+            revealed.pop();
+        }
+        return;
+    }
+
+    let mv = match book.suggest_move(&board) {
+        Some(mv) => mv,
+        None => {
+            coverage.uncovered.push(revealed.clone());
+            return;
+        }
+    };
+    coverage.covered += 1;
+
+    let mut board = board;
+    let next = board.advance_queue().unwrap();
+    if mv.kind.0 != next && board.hold(next).is_none() {
+        board.advance_queue();
+    }
+    board.lock_piece(mv);
+    explore(book, board, revealed, depth - 1, coverage);
+}
@@ -4,8 +4,26 @@ use std::io::prelude::*;
 use libtetris::*;
 use opening_book::BookBuilder;
 
+mod coverage;
+mod selfplay;
+
 fn main() {
     let mut book = BookBuilder::new();
+    // for rule sets that aren't horizontally symmetric, the mirrored positions are wrong
+    let mirror = !std::env::args().any(|s| s == "--no-mirror");
+
+    let args: Vec<_> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|s| s == "--selfplay") {
+        let games = args[i + 1]
+            .parse()
+            .expect("--selfplay requires a number of games");
+        let depth = args[i + 2]
+            .parse()
+            .expect("--selfplay requires a number of pieces");
+        let t = std::time::Instant::now();
+        selfplay::add_selfplay(&mut book, games, depth, &Default::default(), mirror);
+        println!("Took {:?} to play {} games", t.elapsed(), games);
+    }
 
     for (line, l) in std::io::BufReader::new(std::io::stdin())
         .lines()
@@ -61,16 +79,14 @@ fn main() {
             match value {
                 Value::Unvalued => {
                     let p = convert(fumen.pages[0].piece.unwrap());
-                    book.add_move(mirror_board(&b), mirror_placement(p), None);
-                    book.add_move(b, p, None);
+                    add_move(&mut book, &b, p, None, mirror);
                 }
                 Value::Value(v) => {
                     let p = convert(fumen.pages[0].piece.unwrap());
-                    book.add_move(mirror_board(&b), mirror_placement(p), Some(v));
-                    book.add_move(b, p, Some(v));
+                    add_move(&mut book, &b, p, Some(v), mirror);
                 }
                 Value::Pc(c) => {
-                    add_pcs(&mut book, &b, c);
+                    add_pcs(&mut book, &b, c, mirror);
                 }
             }
         } else {
@@ -105,8 +121,7 @@ fn main() {
                     if !b.on_stack(&p) || !allow_sd && !b.above_stack(&p) {
                         break;
                     }
-                    book.add_move(mirror_board(&b), mirror_placement(p), None);
-                    book.add_move(&b, p, None);
+                    add_move(&mut book, &b, p, None, mirror);
                     b.add_next_piece(p.kind.0);
                     b.advance_queue();
                     offset += b.lock_piece(p).cleared_lines.len() as i32;
@@ -131,9 +146,37 @@ fn main() {
     let compiled = book.compile(&[Board::new().into()]);
     println!("Took {:?} to compile", t.elapsed());
 
+    // lower levels save much faster, which helps when iterating on a book
+    let level = match args.iter().position(|s| s == "--level") {
+        Some(i) => args[i + 1]
+            .parse()
+            .expect("--level requires a zstd compression level"),
+        None => opening_book::DEFAULT_COMPRESSION_LEVEL,
+    };
     compiled
-        .save(std::fs::File::create("book.ccbook").unwrap())
+        .save_with_level(std::fs::File::create("book.ccbook").unwrap(), level)
         .unwrap();
+
+    if let Some(i) = args.iter().position(|s| s == "--coverage") {
+        let depth = args[i + 1]
+            .parse()
+            .expect("--coverage requires a number of pieces");
+        let t = std::time::Instant::now();
+        let coverage = coverage::coverage(&compiled, depth);
+        println!("Took {:?} to check coverage", t.elapsed());
+        println!(
+            "Book has moves for {} of {} sequences ({:.2}%), {} uncovered",
+            coverage.covered,
+            coverage.total(),
+            coverage.covered as f64 / coverage.total() as f64 * 100.0,
+            coverage.uncovered.len()
+        );
+        if args.iter().any(|s| s == "--list-uncovered") {
+            for sequence in &coverage.uncovered {
+                println!("{}", sequence);
+            }
+        }
+    }
 }
 
 enum Value {
@@ -167,6 +210,13 @@ fn convert(p: fumen::Piece) -> FallingPiece {
     }
 }
 
+fn add_move(book: &mut BookBuilder, b: &Board, p: FallingPiece, value: Option<f32>, mirror: bool) {
+    if mirror {
+        book.add_move(mirror_board(b), mirror_placement(p), value);
+    }
+    book.add_move(b, p, value);
+}
+
 fn mirror_board(b: &Board) -> Board {
     let mut b = b.clone();
     b.bag = b.bag.iter().map(mirror_piece).collect();
@@ -233,6 +283,7 @@ fn dump(book: &opening_book::BookBuilder) {
     }
     std::fs::create_dir_all("book").unwrap();
     std::fs::write("book/.gitignore", "*").unwrap();
+    book.export_fumen_index("book").unwrap();
     for pos in book.positions() {
         let mut f = std::fs::File::create(format!("book/{}.html", name(pos))).unwrap();
         write!(
@@ -329,7 +380,7 @@ fn dump(book: &opening_book::BookBuilder) {
     }
 }
 
-fn add_pcs(book: &mut BookBuilder, b: &Board, pieces: u32) {
+fn add_pcs(book: &mut BookBuilder, b: &Board, pieces: u32, mirror: bool) {
     let bitboard = pcf::BitBoard(
         *b.get_row(0) as u64
             | (*b.get_row(1) as u64) << 10
@@ -391,8 +442,7 @@ fn add_pcs(book: &mut BookBuilder, b: &Board, pieces: u32) {
                             for (i, p) in soln.iter().enumerate() {
                                 let mv = p.srs_piece(bitb)[0].into();
                                 let score = if i == soln.len() - 1 { Some(1.0) } else { None };
-                                book.add_move(&b, mv, score);
-                                book.add_move(mirror_board(&b), mirror_placement(mv), score);
+                                add_move(&mut book, &b, mv, score, mirror);
                                 b.add_next_piece(mv.kind.0);
                                 b.advance_queue();
                                 b.lock_piece(mv);
@@ -429,3 +479,37 @@ fn all_sequences_impl(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use opening_book::Position;
+
+    use super::*;
+
+    fn positions_added(mirror: bool) -> (Board, Vec<Position>) {
+        let mut field = [[false; 10]; 40];
+        field[0][0] = true;
+        let mut b = Board::new();
+        b.set_field(field);
+        let mut p = SpawnRule::Row19Or20.spawn(Piece::L, &b).unwrap();
+        p.sonic_drop(&b);
+
+        let mut book = BookBuilder::new();
+        add_move(&mut book, &b, p, Some(1.0), mirror);
+        (b, book.positions().collect())
+    }
+
+    #[test]
+    fn no_mirror_adds_only_the_board() {
+        let (b, positions) = positions_added(false);
+        assert_eq!(positions, vec![Position::from(&b)]);
+    }
+
+    #[test]
+    fn mirror_adds_the_mirrored_board() {
+        let (b, positions) = positions_added(true);
+        assert_eq!(positions.len(), 2);
+        assert!(positions.contains(&Position::from(&b)));
+        assert!(positions.contains(&Position::from(&mirror_board(&b))));
+    }
+}
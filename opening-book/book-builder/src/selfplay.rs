@@ -0,0 +1,72 @@
+use cold_clear::evaluation::Standard;
+use libtetris::*;
+use opening_book::BookBuilder;
+use rand::prelude::*;
+
+use crate::add_move;
+
+/// Has the bot play `games` games from an empty board and adds the first `depth` placements of
+/// each game to the book.
+///
+/// The last placement of each game is valued by the bot's opinion of the resulting position,
+/// mapped to the 0 to 1 range that perfect clears are valued in, so the book prefers the lines the
+/// bot played its way into good positions with.
+pub fn add_selfplay(
+    book: &mut BookBuilder,
+    games: usize,
+    depth: usize,
+    weights: &Standard,
+    mirror: bool,
+) {
+    for _ in 0..games {
+        let mut rng = thread_rng();
+        let mut board = Board::new();
+        for _ in 0..5 {
+            board.add_next_piece(board.generate_next_piece(&mut rng));
+        }
+        let bot =
+            cold_clear::Interface::launch(board.clone(), Default::default(), weights.clone(), None);
+
+        for i in 0..depth {
+            let piece = board.generate_next_piece(&mut rng);
+            board.add_next_piece(piece);
+            bot.add_next_piece(piece);
+
+            bot.suggest_next_move(0);
+            let (mv, info) = match bot.block_next_move() {
+                Some(result) => result,
+                None => break,
+            };
+            let value = if i + 1 == depth {
+                let advantage = info.advantage_in_lines().unwrap_or(0.0);
+                Some(1.0 / (1.0 + (-advantage).exp()))
+            } else {
+                None
+            };
+            add_move(book, &board, mv.expected_location, value, mirror);
+            bot.play_next_move(mv.expected_location);
+            board = board.with_placement(mv.expected_location, mv.hold).0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selfplay_adds_the_empty_board_opener() {
+        let mut book = BookBuilder::new();
+        add_selfplay(&mut book, 2, 3, &Standard::default(), false);
+        book.recalculate_graph();
+
+        let openers: Vec<_> = book
+            .positions()
+            .filter(|pos| pos.rows().iter().all(|&row| row == 0))
+            .collect();
+        assert!(!openers.is_empty());
+        for pos in openers {
+            assert!(!book.moves(pos).is_empty());
+        }
+    }
+}
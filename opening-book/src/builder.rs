@@ -90,7 +90,7 @@ impl BookBuilder {
         possibilities
             .into_iter()
             .map(|(queue, _)| {
-                lookup(values, Sequence { next, queue }).map_or(Default::default(), |v| v.1)
+                lookup(values, Sequence::new(next, &queue)).map_or(Default::default(), |v| v.1)
             })
             .sum()
     }
@@ -99,7 +99,7 @@ impl BookBuilder {
         let mut sequences = vec![];
         for (next, bag) in pos.next_possibilities() {
             for (queue, qbag) in possible_sequences(vec![], bag) {
-                sequences.push((Sequence { next, queue }, qbag));
+                sequences.push((Sequence::new(next, &queue), qbag));
             }
         }
         sequences.sort();
@@ -108,7 +108,9 @@ impl BookBuilder {
         let this = self.data.get(&pos).unwrap();
         sequences
             .into_par_iter()
-            .map(|(Sequence { next, queue }, qbag)| {
+            .map(|(seq, qbag)| {
+                let next = seq.next;
+                let queue = seq.queue();
                 let mut best = MoveValue::default();
                 let mut best_moves = SmallVec::new();
                 for &mv in &this.moves {
@@ -141,7 +143,7 @@ impl BookBuilder {
                         best_moves.push(mv.location);
                     }
                 }
-                (Sequence { next, queue }, best, best_moves)
+                (seq, best, best_moves)
             })
             .collect_into_vec(&mut values);
         values.dedup_by(|(_, a1, a2), (_, b1, b2)| a1 == b1 && a2 == b2);
@@ -218,6 +220,42 @@ impl BookBuilder {
         self.data.keys().copied()
     }
 
+    /// Writes every position in the book to `index.txt` in the specified directory as a fumen, one
+    /// per line. The first page of each fumen shows the position and the second shows the best
+    /// move in that position, if there is one.
+    #[cfg(feature = "fumen-export")]
+    pub fn export_fumen_index(&self, dir: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let mut f = std::io::BufWriter::new(File::create(dir.as_ref().join("index.txt"))?);
+        for pos in self.positions() {
+            let mut fumen = fumen::Fumen::default();
+            let page = fumen.add_page();
+            for (y, &row) in pos.rows().iter().enumerate() {
+                for x in 0..10 {
+                    if row & 1 << x != 0 {
+                        page.field[y][x] = fumen::CellColor::Grey;
+                    }
+                }
+            }
+            let mut comment: String = pos.bag().iter().map(|p| p.to_char()).collect();
+            if let Some(p) = pos.extra() {
+                comment.push('/');
+                comment.push(p.to_char());
+            }
+            page.comment = Some(comment);
+
+            let best = self.moves(pos).iter().max_by(|a, b| {
+                let a = self.value_of_position(pos.advance(a.location()).0);
+                let b = self.value_of_position(pos.advance(b.location()).0);
+                a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+            });
+            if let Some(mv) = best {
+                fumen.add_page().piece = Some(mv.location().into());
+            }
+            writeln!(f, "{}", fumen.encode())?;
+        }
+        f.flush()
+    }
+
     pub fn compile(mut self, roots: &[Position]) -> MemoryBook {
         self.data.retain(|_, v| !v.values.is_empty());
         let mut book = HashMap::new();
@@ -236,7 +274,10 @@ impl BookBuilder {
                 moves
             });
         }
-        MemoryBook(book)
+        MemoryBook {
+            rows: book,
+            next_pieces: NEXT_PIECES,
+        }
     }
 
     fn build_position(&mut self, pos: &Position) -> Row {
@@ -371,3 +412,45 @@ pub fn possible_sequences(
     solve(&mut q, bag, &mut result);
     result
 }
+
+#[cfg(all(test, feature = "fumen-export"))]
+mod tests {
+    use libtetris::{PieceState, RotationState, TspinStatus};
+
+    use super::*;
+
+    fn piece(kind: Piece, x: i32) -> FallingPiece {
+        FallingPiece {
+            kind: PieceState(kind, RotationState::North),
+            x,
+            y: 0,
+            tspin: TspinStatus::None,
+        }
+    }
+
+    #[test]
+    fn fumen_index_has_one_fumen_per_position() {
+        let mut builder = BookBuilder::new();
+        let start = Position::from(&Board::new());
+        let o = piece(Piece::O, 4);
+        builder.add_move(start, o, Some(1.0));
+        builder.add_move(start.advance(o).0, piece(Piece::I, 1), Some(1.0));
+        builder.recalculate_graph();
+
+        let dir = std::env::temp_dir().join(format!("fumen-index-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        builder.export_fumen_index(&dir).unwrap();
+        let index = std::fs::read_to_string(dir.join("index.txt")).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        let lines: Vec<_> = index.lines().collect();
+        assert_eq!(lines.len(), builder.positions().count());
+        assert_eq!(lines.len(), 2);
+        for line in lines {
+            // the position, followed by its best move
+            let fumen = fumen::Fumen::decode(line).unwrap();
+            assert_eq!(fumen.pages.len(), 2);
+            assert!(fumen.pages[1].piece.is_some());
+        }
+    }
+}
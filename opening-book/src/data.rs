@@ -2,7 +2,7 @@ use enumset::EnumSet;
 use libtetris::{Board, FallingPiece, Piece, RotationState};
 use serde::{Deserialize, Serialize};
 
-use crate::NEXT_PIECES;
+use crate::{LEGACY_NEXT_PIECES, MAX_NEXT_PIECES};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Default, Serialize, Deserialize)]
 pub struct Position {
@@ -13,17 +13,75 @@ pub struct Position {
     extra: Option<Piece>,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
-pub(crate) struct Sequence {
+/// The pieces that can be placed in a book position: the current and hold pieces, and the next
+/// pieces after them.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Sequence {
     // this represents what can be placed with current or hold. if this has a single element,
     // that means that the current piece and the hold piece are the same.
     pub(crate) next: EnumSet<Piece>,
-    pub(crate) queue: [Piece; NEXT_PIECES],
+    // only the first `len` pieces are part of the sequence, the rest are always I
+    queue: [Piece; MAX_NEXT_PIECES],
+    len: u8,
+}
+
+/// How sequences were stored in books saved before the number of next pieces was configurable.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LegacySequence {
+    next: EnumSet<Piece>,
+    queue: [Piece; LEGACY_NEXT_PIECES],
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub(crate) struct CompactPiece(pub(crate) std::num::NonZeroU16);
 
+impl Sequence {
+    pub(crate) fn new(next: EnumSet<Piece>, queue: &[Piece]) -> Self {
+        assert!(queue.len() <= MAX_NEXT_PIECES);
+        let mut this = Sequence {
+            next,
+            queue: [Piece::I; MAX_NEXT_PIECES],
+            len: queue.len() as u8,
+        };
+        this.queue[..queue.len()].copy_from_slice(queue);
+        this
+    }
+
+    /// The pieces that can be placed now using the current piece or hold.
+    pub fn next(&self) -> EnumSet<Piece> {
+        self.next
+    }
+
+    pub fn queue(&self) -> &[Piece] {
+        &self.queue[..self.len as usize]
+    }
+}
+
+impl From<LegacySequence> for Sequence {
+    fn from(v: LegacySequence) -> Self {
+        Sequence::new(v.next, &v.queue)
+    }
+}
+
+impl Serialize for Sequence {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        (self.next, self.queue()).serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for Sequence {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let (next, queue): (EnumSet<Piece>, Vec<Piece>) = Deserialize::deserialize(d)?;
+        if queue.len() > MAX_NEXT_PIECES {
+            return Err(serde::de::Error::invalid_length(
+                queue.len(),
+                &"at most MAX_NEXT_PIECES pieces",
+            ));
+        }
+        Ok(Sequence::new(next, &queue))
+    }
+}
+
 impl Position {
     pub fn advance(&self, mv: FallingPiece) -> (Position, f32) {
         let mut field = [[false; 10]; 40];
@@ -128,7 +186,8 @@ impl From<Board> for Position {
     }
 }
 
-pub(crate) fn decompose_board(board: &Board) -> Option<(Position, Sequence)> {
+/// Finds the position and sequence of a board in a book that looks `next_pieces` pieces ahead.
+pub(crate) fn decompose_board(board: &Board, next_pieces: usize) -> Option<(Position, Sequence)> {
     let position = board.into();
     let mut next = EnumSet::empty();
     let mut q = board.next_queue();
@@ -138,8 +197,11 @@ pub(crate) fn decompose_board(board: &Board) -> Option<(Position, Sequence)> {
     } else {
         next.insert(q.next()?);
     }
-    let queue = [q.next()?, q.next()?, q.next()?, q.next()?];
-    Some((position, Sequence { next, queue }))
+    let queue: Vec<_> = q.take(next_pieces).collect();
+    if queue.len() < next_pieces {
+        return None;
+    }
+    Some((position, Sequence::new(next, &queue)))
 }
 
 pub fn refill_if_empty<T: enumset::EnumSetType>(bag: EnumSet<T>) -> EnumSet<T> {
@@ -156,13 +218,13 @@ impl Ord for Sequence {
         let p1 = i.next().unwrap();
         let p2 = i.next().unwrap_or(p1);
         let mut q1 = vec![PieceOrd(p1), PieceOrd(p2)];
-        q1.extend(self.queue.iter().map(|&p| PieceOrd(p)));
+        q1.extend(self.queue().iter().map(|&p| PieceOrd(p)));
 
         let mut i = other.next.iter();
         let p1 = i.next().unwrap();
         let p2 = i.next().unwrap_or(p1);
         let mut q2 = vec![PieceOrd(p1), PieceOrd(p2)];
-        q2.extend(other.queue.iter().map(|&p| PieceOrd(p)));
+        q2.extend(other.queue().iter().map(|&p| PieceOrd(p)));
 
         q1.cmp(&q2)
     }
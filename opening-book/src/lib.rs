@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fs::File;
@@ -9,14 +11,23 @@ use enumset::EnumSet;
 use libtetris::{Board, FallingPiece, Piece};
 use serde::{Deserialize, Serialize};
 
+/// How many pieces after the current and hold pieces the positions of built books are told apart
+/// by. This is stored in saved books, so books that look further ahead can still be loaded.
+#[cfg(any(feature = "builder", test))]
 const NEXT_PIECES: usize = 4;
+/// The most pieces after the current and hold pieces a book can tell positions apart by.
+const MAX_NEXT_PIECES: usize = 8;
+/// The number of next pieces in books saved before it was stored in the file.
+const LEGACY_NEXT_PIECES: usize = 4;
+/// The zstd compression level books are saved with unless another is specified. This is slow, so
+/// tools that save books often may want to use a lower level like 3.
+pub const DEFAULT_COMPRESSION_LEVEL: i32 = 19;
 
 #[cfg(feature = "builder")]
 mod builder;
 #[cfg(feature = "builder")]
 pub use builder::*;
-#[cfg(feature = "builder")]
-pub use data::Position;
+pub use data::{Position, Sequence};
 
 mod data;
 use crate::data::*;
@@ -29,13 +40,20 @@ enum BookType {
     Disk(DiskBook),
 }
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct MemoryBook(HashMap<Position, Row>);
+#[derive(Clone)]
+pub struct MemoryBook {
+    rows: HashMap<Position, Row>,
+    next_pieces: usize,
+}
 
 #[cfg(not(target_arch = "wasm32"))]
 pub struct DiskBook {
     index: HashMap<Position, (u64, u64)>,
+    next_pieces: usize,
     file: File,
+    /// The whole book file, if it could be mapped. Rows are read from here instead of the file.
+    #[cfg(feature = "mmap")]
+    map: Option<memmap2::Mmap>,
     dict: zstd::dict::DecoderDictionary<'static>,
 }
 
@@ -54,20 +72,18 @@ impl Row {
     fn custom_serialize(&self, mut to: impl Write) -> std::io::Result<()> {
         for &(seq, p) in &*self.0 {
             let [piece_low, piece_high] = p.map(|p| p.0.into()).unwrap_or(0).to_le_bytes();
-            to.write(&[
-                seq.next.try_as_u8().unwrap(),
-                seq.queue[0] as u8,
-                seq.queue[1] as u8,
-                seq.queue[2] as u8,
-                seq.queue[3] as u8,
-                piece_low,
-                piece_high,
-            ])?;
+            to.write_all(&[seq.next.try_as_u8().unwrap()])?;
+            for &piece in seq.queue() {
+                to.write_all(&[piece as u8])?;
+            }
+            to.write_all(&[piece_low, piece_high])?;
         }
         Ok(())
     }
 
-    fn custom_deserialize(mut from: impl Read) -> std::io::Result<Self> {
+    /// Reads a row written by `custom_serialize` for a book that looks `next_pieces` pieces
+    /// ahead.
+    fn custom_deserialize(mut from: impl Read, next_pieces: usize) -> std::io::Result<Self> {
         fn bad_data() -> std::io::Error {
             std::io::Error::new(std::io::ErrorKind::Other, "Bad data")
         }
@@ -84,25 +100,23 @@ impl Row {
             }
         }
         let mut result = vec![];
+        let mut buf = [0; 3 + MAX_NEXT_PIECES];
+        let buf = &mut buf[..3 + next_pieces];
         loop {
-            let mut buf = [0; 7];
-            match from.read_exact(&mut buf) {
+            match from.read_exact(buf) {
                 Ok(()) => {}
                 Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
                     return Ok(Row(result.into_boxed_slice()))
                 }
                 Err(e) => return Err(e),
             }
-            let seq = Sequence {
-                next: EnumSet::try_from_u8(buf[0]).ok_or(bad_data())?,
-                queue: [
-                    conv_piece(buf[1])?,
-                    conv_piece(buf[2])?,
-                    conv_piece(buf[3])?,
-                    conv_piece(buf[4])?,
-                ],
-            };
-            let mv = u16::from_le_bytes(buf[5..7].try_into().unwrap());
+            let next = EnumSet::try_from_u8(buf[0]).ok_or(bad_data())?;
+            let queue = buf[1..1 + next_pieces]
+                .iter()
+                .map(|&p| conv_piece(p))
+                .collect::<Result<Vec<_>, _>>()?;
+            let seq = Sequence::new(next, &queue);
+            let mv = u16::from_le_bytes(buf[1 + next_pieces..].try_into().unwrap());
             if mv != 0 && mv & 0b111 == 0 {
                 return Err(bad_data());
             }
@@ -112,121 +126,454 @@ impl Row {
     }
 }
 
+/// The reason a book couldn't be loaded.
+#[derive(Debug)]
+pub enum BookError {
+    /// The file couldn't be read.
+    Io(std::io::Error),
+    /// The file isn't a book.
+    BadMagic,
+    /// The file is a book, but it's truncated or its contents are invalid.
+    Corrupt(String),
+    /// The book tells positions apart by more next pieces than this version supports.
+    UnsupportedVersion { next_pieces: usize },
+    /// The books being combined tell positions apart by different numbers of next pieces.
+    NextPiecesMismatch { expected: usize, found: usize },
+}
+
+impl BookError {
+    fn corrupt(e: impl std::fmt::Display) -> Self {
+        BookError::Corrupt(e.to_string())
+    }
+}
+
+impl std::fmt::Display for BookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BookError::Io(e) => write!(f, "failed to read book: {}", e),
+            BookError::BadMagic => write!(f, "not a Cold Clear book"),
+            BookError::Corrupt(e) => write!(f, "book is corrupt: {}", e),
+            BookError::UnsupportedVersion { next_pieces } => write!(
+                f,
+                "book looks {} pieces ahead, but at most {} are supported",
+                next_pieces, MAX_NEXT_PIECES
+            ),
+            BookError::NextPiecesMismatch { expected, found } => write!(
+                f,
+                "book looks {} pieces ahead, but {} were expected",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BookError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BookError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for BookError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => BookError::corrupt(e),
+            _ => BookError::Io(e),
+        }
+    }
+}
+
+impl From<bincode::Error> for BookError {
+    fn from(e: bincode::Error) -> Self {
+        match *e {
+            bincode::ErrorKind::Io(e) => e.into(),
+            e => BookError::corrupt(e),
+        }
+    }
+}
+
+impl From<BookError> for bincode::Error {
+    fn from(e: BookError) -> Self {
+        match e {
+            BookError::Io(e) => e.into(),
+            e => Box::new(bincode::ErrorKind::Custom(e.to_string())),
+        }
+    }
+}
+
+fn check_next_pieces(next_pieces: usize) -> Result<usize, BookError> {
+    if next_pieces <= MAX_NEXT_PIECES {
+        Ok(next_pieces)
+    } else {
+        Err(BookError::UnsupportedVersion { next_pieces })
+    }
+}
+
 impl MemoryBook {
+    const MAGIC_BYTES: [u8; 4] = [0xB7, 0x1E, 0xA0, 0x74];
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn load(from: impl BufRead) -> bincode::Result<Self> {
-        bincode::deserialize_from(zstd::Decoder::new(from)?)
+    const MAGIC: u32 = u32::from_le_bytes(Self::MAGIC_BYTES);
+
+    /// Reads the header saying how many next pieces the book uses. Books saved before there was
+    /// a header return `None`.
+    fn read_header(from: &mut impl BufRead) -> Result<Option<usize>, BookError> {
+        if from.fill_buf()?.starts_with(&Self::MAGIC_BYTES) {
+            let mut header = [0; 5];
+            from.read_exact(&mut header)?;
+            check_next_pieces(header[4] as usize).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Reads the rows of the book from the decompressed data after the header.
+    fn read_rows(data: impl Read, next_pieces: Option<usize>) -> Result<Self, BookError> {
+        let (rows, next_pieces) = match next_pieces {
+            Some(next_pieces) => {
+                let rows: HashMap<Position, Row> =
+                    bincode::deserialize_from(data).map_err(BookError::corrupt)?;
+                (rows, next_pieces)
+            }
+            None => {
+                let rows: HashMap<Position, Box<[(LegacySequence, Option<CompactPiece>)]>> =
+                    bincode::deserialize_from(data).map_err(BookError::corrupt)?;
+                let rows = rows
+                    .into_iter()
+                    .map(|(pos, row)| {
+                        let row = row.into_vec().into_iter().map(|(s, m)| (s.into(), m));
+                        (pos, Row(row.collect()))
+                    })
+                    .collect();
+                (rows, LEGACY_NEXT_PIECES)
+            }
+        };
+        let mut sequences = rows.values().flat_map(|row| row.0.iter());
+        if sequences.any(|(seq, _)| seq.queue().len() != next_pieces) {
+            return Err(BookError::corrupt("sequences don't match the header"));
+        }
+        Ok(MemoryBook { rows, next_pieces })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(mut from: impl BufRead) -> Result<Self, BookError> {
+        let next_pieces = Self::read_header(&mut from)?;
+        // anything that goes wrong while decompressing is a problem with the data
+        Self::read_rows(zstd::Decoder::new(from)?, next_pieces)
     }
 
     #[cfg(target_arch = "wasm32")]
-    pub fn load(from: impl BufRead) -> bincode::Result<Self> {
-        bincode::deserialize_from(
-            ruzstd::StreamingDecoder::new(&mut { from })
-                .map_err(|err| bincode::ErrorKind::Custom(err))?,
+    pub fn load(mut from: impl BufRead) -> Result<Self, BookError> {
+        let next_pieces = Self::read_header(&mut from)?;
+        Self::read_rows(
+            ruzstd::StreamingDecoder::new(&mut { from }).map_err(BookError::Corrupt)?,
+            next_pieces,
         )
     }
 
+    /// How many pieces after the current and hold pieces the book tells positions apart by.
+    pub fn next_pieces(&self) -> usize {
+        self.next_pieces
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     pub fn save<W: Write>(&self, to: W) -> bincode::Result<()> {
-        let mut to = zstd::Encoder::new(to, 19)?;
+        self.save_with_level(to, DEFAULT_COMPRESSION_LEVEL)
+    }
+
+    /// Like `save`, but compresses the book with the specified zstd compression level.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_with_level<W: Write>(&self, mut to: W, level: i32) -> bincode::Result<()> {
+        to.write_all(&Self::MAGIC_BYTES)?;
+        to.write_all(&[self.next_pieces as u8])?;
+        let mut to = zstd::Encoder::new(to, level)?;
         to.multithread(num_cpus::get() as u32)?;
-        bincode::serialize_into(&mut to, self)?;
+        bincode::serialize_into(&mut to, &self.rows)?;
         to.finish()?;
         Ok(())
     }
 
     pub fn suggest_move(&self, state: &Board) -> Option<FallingPiece> {
-        let (pos, seq) = decompose_board(state)?;
-        self.0.get(&pos)?.lookup(&seq)
+        let (pos, seq) = decompose_board(state, self.next_pieces)?;
+        self.rows.get(&pos)?.lookup(&seq)
+    }
+
+    pub fn positions<'a>(&'a self) -> impl Iterator<Item = Position> + 'a {
+        self.rows.keys().copied()
+    }
+
+    /// Lists the moves the book makes in a position, in sequence order.
+    ///
+    /// Each move is used for its sequence and every sequence after it up to the next listed one.
+    /// `None` means the book has no move for those sequences.
+    pub fn moves(&self, pos: Position) -> Vec<(Sequence, Option<FallingPiece>)> {
+        self.rows.get(&pos).map_or(vec![], |row| {
+            row.0
+                .iter()
+                .map(|&(seq, mv)| (seq, mv.map(Into::into)))
+                .collect()
+        })
     }
 
+    /// Adds the positions of `other` to this book. Where both books have a position, this book's
+    /// moves are kept.
     pub fn merge(&mut self, other: MemoryBook) {
-        for (pos, data) in other.0 {
-            self.0.entry(pos).or_insert(data);
+        self.merge_with(other, |_| false);
+    }
+
+    /// Adds the positions of `other` to this book. Where both books have a position,
+    /// `prefer_other` is called with it to decide whether to replace this book's moves with
+    /// `other`'s.
+    ///
+    /// Panics if the books don't look the same number of pieces ahead.
+    pub fn merge_with(
+        &mut self,
+        other: MemoryBook,
+        mut prefer_other: impl FnMut(&Position) -> bool,
+    ) {
+        assert_eq!(self.next_pieces, other.next_pieces);
+        for (pos, data) in other.rows {
+            match self.rows.entry(pos) {
+                Entry::Vacant(entry) => {
+                    entry.insert(data);
+                }
+                Entry::Occupied(mut entry) => {
+                    if prefer_other(&pos) {
+                        entry.insert(data);
+                    }
+                }
+            }
         }
     }
 
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn save_as_disk_book(&self, mut to: impl Write) -> bincode::Result<()> {
+    pub fn save_as_disk_book(&self, to: impl Write) -> bincode::Result<()> {
+        self.save_as_disk_book_with_level(to, DEFAULT_COMPRESSION_LEVEL)
+    }
+
+    /// Like `save_as_disk_book`, but compresses the book with the specified zstd compression
+    /// level.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_as_disk_book_with_level(
+        &self,
+        mut to: impl Write,
+        level: i32,
+    ) -> bincode::Result<()> {
         to.write_all(&DiskBook::MAGIC_BYTES)?;
-        let mut index = HashMap::with_capacity(self.0.len());
-
-        let dict = zstd::dict::EncoderDictionary::new(include_bytes!("dictionary"), 19);
-
-        let mut offset = 4;
-        for (&pos, row) in &self.0 {
-            if row.0.len() == 1 {
-                index.insert(
-                    pos,
-                    (row.0[0].1.map(|v| v.0.into()).unwrap_or(0) as u64, 0u64),
-                );
-            } else if row.0.len() == 2 {
-                let v1 = row.0[0].1.map(|v| v.0.into()).unwrap_or(0);
-                let mut buf = [0; 8];
-                Row(vec![row.0[1]].into_boxed_slice()).custom_serialize(buf.as_mut())?;
-                index.insert(pos, (u64::from_le_bytes(buf), (v1 as u64) << 32 | 1 << 24));
-            } else {
-                let mut buf = vec![];
-                let mut encoder = zstd::Encoder::with_prepared_dictionary(&mut buf, &dict)?;
-                encoder.include_magicbytes(false)?;
-                encoder.include_contentsize(false)?;
-                encoder.include_checksum(false)?;
-                encoder.include_dictid(false)?;
-                row.custom_serialize(&mut encoder)?;
-                encoder.finish()?;
-
-                to.write_all(&buf)?;
-                index.insert(pos, (offset, buf.len() as u64));
-                offset += buf.len() as u64;
-            }
+        to.write_all(&[self.next_pieces as u8])?;
+        let mut index = HashMap::with_capacity(self.rows.len());
+
+        let dict = DiskBook::encoder_dictionary(level);
+
+        let mut offset = 5;
+        for (&pos, row) in &self.rows {
+            index.insert(pos, row.write_disk_entry(&mut to, &mut offset, &dict)?);
         }
 
-        let mut buf = vec![];
-        let mut w = zstd::stream::Encoder::new(&mut buf, 19)?;
-        bincode::serialize_into(&mut w, &index)?;
-        w.finish()?;
-        to.write_all(buf.as_slice())?;
-        to.write_all((buf.len() as u64).to_le_bytes().as_ref())?;
-        Ok(())
+        DiskBook::write_index(&index, to, level)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Row {
+    /// Finds the index entry of the row in a disk book. Rows that don't fit in the index entry
+    /// are compressed and written to `to` at `offset`, which is advanced past them. The second
+    /// sequence of a two sequence row only fits if the book looks at most 5 pieces ahead.
+    fn write_disk_entry(
+        &self,
+        mut to: impl Write,
+        offset: &mut u64,
+        dict: &zstd::dict::EncoderDictionary,
+    ) -> bincode::Result<(u64, u64)> {
+        if self.0.len() == 1 {
+            Ok((self.0[0].1.map(|v| v.0.into()).unwrap_or(0) as u64, 0u64))
+        } else if self.0.len() == 2 && self.0[1].0.queue().len() <= 5 {
+            let v1 = self.0[0].1.map(|v| v.0.into()).unwrap_or(0);
+            let mut buf = [0; 8];
+            Row(vec![self.0[1]].into_boxed_slice()).custom_serialize(buf.as_mut())?;
+            Ok((u64::from_le_bytes(buf), (v1 as u64) << 32 | 1 << 24))
+        } else {
+            let mut buf = vec![];
+            let mut encoder = zstd::Encoder::with_prepared_dictionary(&mut buf, dict)?;
+            encoder.include_magicbytes(false)?;
+            encoder.include_contentsize(false)?;
+            encoder.include_checksum(false)?;
+            encoder.include_dictid(false)?;
+            self.custom_serialize(&mut encoder)?;
+            encoder.finish()?;
+
+            to.write_all(&buf)?;
+            let entry = (*offset, buf.len() as u64);
+            *offset += buf.len() as u64;
+            Ok(entry)
+        }
     }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 impl DiskBook {
-    const MAGIC_BYTES: [u8; 4] = [0xB7, 0x1E, 0xA0, 0x73];
+    /// Disk books with this magic are followed by the number of next pieces the book uses.
+    const MAGIC_BYTES: [u8; 4] = [0xB7, 0x1E, 0xA0, 0x75];
     const MAGIC: u32 = u32::from_le_bytes(Self::MAGIC_BYTES);
+    const LEGACY_MAGIC_BYTES: [u8; 4] = [0xB7, 0x1E, 0xA0, 0x73];
+    const LEGACY_MAGIC: u32 = u32::from_le_bytes(Self::LEGACY_MAGIC_BYTES);
 
-    pub fn load(mut file: File) -> bincode::Result<Self> {
-        let mut magic = [0; 4];
-        file.read_exact(&mut magic)?;
-        if magic != Self::MAGIC_BYTES {
-            return Err(serde::de::Error::custom("Invalid CC book file"));
-        }
+    fn encoder_dictionary(level: i32) -> zstd::dict::EncoderDictionary<'static> {
+        zstd::dict::EncoderDictionary::new(include_bytes!("dictionary"), level)
+    }
+
+    /// Writes the index and the footer saying how large it is, which end the book file.
+    fn write_index(
+        index: &HashMap<Position, (u64, u64)>,
+        mut to: impl Write,
+        level: i32,
+    ) -> bincode::Result<()> {
+        let mut buf = vec![];
+        let mut w = zstd::stream::Encoder::new(&mut buf, level)?;
+        bincode::serialize_into(&mut w, index)?;
+        w.finish()?;
+        to.write_all(buf.as_slice())?;
+        to.write_all((buf.len() as u64).to_le_bytes().as_ref())?;
+        Ok(())
+    }
 
+    /// Reads the size of the index from the footer at the end of the book file.
+    fn index_size(mut file: &File) -> std::io::Result<u64> {
         file.seek(SeekFrom::End(-8))?;
         let mut index_size = [0; 8];
         file.read_exact(&mut index_size)?;
-        let index_size = u64::from_le_bytes(index_size);
+        Ok(u64::from_le_bytes(index_size))
+    }
+
+    pub fn load(mut file: File) -> Result<Self, BookError> {
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)?;
+        let next_pieces = if magic == Self::MAGIC_BYTES {
+            let mut next_pieces = [0; 1];
+            file.read_exact(&mut next_pieces)?;
+            check_next_pieces(next_pieces[0] as usize)?
+        } else if magic == Self::LEGACY_MAGIC_BYTES {
+            LEGACY_NEXT_PIECES
+        } else {
+            return Err(BookError::BadMagic);
+        };
+
+        let index_size = Self::index_size(&file)?;
+        if index_size + 8 > file.metadata()?.len() {
+            return Err(BookError::corrupt("index is larger than the file"));
+        }
 
         file.seek(SeekFrom::End(-8 - index_size as i64))?;
         let mut buf = vec![0; index_size as usize];
         file.read_exact(&mut buf)?;
-        let index = zstd::stream::decode_all(buf.as_slice())?;
+        let index = zstd::stream::decode_all(buf.as_slice()).map_err(BookError::corrupt)?;
         let index = bincode::deserialize(&index)?;
 
         let dict = zstd::dict::DecoderDictionary::new(include_bytes!("dictionary"));
 
-        Ok(DiskBook { file, index, dict })
+        // Safety: the book file isn't expected to be modified while it's in use. If mapping fails
+        // we just fall back to reading rows from the file.
+        #[cfg(feature = "mmap")]
+        let map = unsafe { memmap2::Mmap::map(&file) }.ok();
+
+        Ok(DiskBook {
+            file,
+            index,
+            next_pieces,
+            dict,
+            #[cfg(feature = "mmap")]
+            map,
+        })
+    }
+
+    /// Adds the positions of `book` to the end of this book's file without rewriting the rows
+    /// already in it. Where both books have a position, `prefer_new` is called with it to decide
+    /// whether to replace this book's moves with `book`'s. The old moves of replaced positions
+    /// are left in the file, but can't be reached anymore.
+    ///
+    /// The book must have been loaded from a file opened for writing, and both books must look
+    /// the same number of pieces ahead.
+    ///
+    /// Nothing already in the file is overwritten: the new rows and index are written after the
+    /// old footer, and the new footer is written last. If writing fails, the file is cut back to
+    /// its old length, so the old book is left as it was.
+    pub fn append(
+        &mut self,
+        book: &MemoryBook,
+        mut prefer_new: impl FnMut(&Position) -> bool,
+    ) -> Result<(), BookError> {
+        if book.next_pieces != self.next_pieces {
+            return Err(BookError::NextPiecesMismatch {
+                expected: self.next_pieces,
+                found: book.next_pieces,
+            });
+        }
+
+        // the map would be invalidated by resizing the file
+        #[cfg(feature = "mmap")]
+        {
+            self.map = None;
+        }
+
+        let end = self.file.seek(SeekFrom::End(0))?;
+        let mut index = self.index.clone();
+        let result = self.write_appended(book, &mut prefer_new, &mut index, end);
+        match result {
+            Ok(()) => self.index = index,
+            Err(_) => {
+                self.file.set_len(end).ok();
+            }
+        }
+
+        #[cfg(feature = "mmap")]
+        {
+            self.map = unsafe { memmap2::Mmap::map(&self.file) }.ok();
+        }
+        result
+    }
+
+    /// Writes the rows `append` adds and an index of them at `offset`, the end of the file.
+    fn write_appended(
+        &self,
+        book: &MemoryBook,
+        prefer_new: &mut impl FnMut(&Position) -> bool,
+        index: &mut HashMap<Position, (u64, u64)>,
+        mut offset: u64,
+    ) -> Result<(), BookError> {
+        let mut to = std::io::BufWriter::new(&self.file);
+        let dict = Self::encoder_dictionary(DEFAULT_COMPRESSION_LEVEL);
+        for (&pos, row) in &book.rows {
+            if index.contains_key(&pos) && !prefer_new(&pos) {
+                continue;
+            }
+            let entry = row.write_disk_entry(&mut to, &mut offset, &dict)?;
+            index.insert(pos, entry);
+        }
+
+        // until the footer is written, the end of the file doesn't point at the new index
+        let mut new_index = vec![];
+        Self::write_index(index, &mut new_index, DEFAULT_COMPRESSION_LEVEL)?;
+        let (new_index, footer) = new_index.split_at(new_index.len() - 8);
+        to.write_all(new_index)?;
+        to.flush()?;
+        drop(to);
+        self.file.sync_data()?;
+        (&self.file).write_all(footer)?;
+        self.file.sync_data()?;
+        Ok(())
     }
 
     pub fn suggest_move(&self, state: &Board) -> Option<FallingPiece> {
-        let (pos, seq) = decompose_board(state)?;
+        let (pos, seq) = decompose_board(state, self.next_pieces)?;
         let &(offset, length) = self.index.get(&pos)?;
         if length & (1 << 24) - 1 == 0 {
             if length == 0 {
                 CompactPiece::from_u16(offset as u16).map(Into::into)
             } else {
-                let row = Row::custom_deserialize(&offset.to_le_bytes()[..7]).ok()?;
+                let bytes = offset.to_le_bytes();
+                let row = Row::custom_deserialize(&bytes[..3 + self.next_pieces], self.next_pieces)
+                    .ok()?;
                 let s = row.0[0].0;
                 let v = row.0[0].1;
                 if seq < s {
@@ -236,13 +583,28 @@ impl DiskBook {
                 }
             }
         } else {
-            let mut buf = vec![0; length as usize];
-            Self::read_raw(&self.file, offset, &mut buf).ok()?;
-            let mut decoder =
-                zstd::Decoder::with_prepared_dictionary(buf.as_slice(), &self.dict).ok()?;
+            let buf = self.read_row(offset, length).ok()?;
+            let mut decoder = zstd::Decoder::with_prepared_dictionary(&*buf, &self.dict).ok()?;
             decoder.include_magicbytes(false).ok()?;
-            Row::custom_deserialize(decoder).ok()?.lookup(&seq)
+            Row::custom_deserialize(decoder, self.next_pieces)
+                .ok()?
+                .lookup(&seq)
+        }
+    }
+
+    fn read_row(&self, offset: u64, length: u64) -> std::io::Result<Cow<'_, [u8]>> {
+        #[cfg(feature = "mmap")]
+        {
+            if let Some(map) = &self.map {
+                return map
+                    .get(offset as usize..(offset + length) as usize)
+                    .map(Cow::Borrowed)
+                    .ok_or_else(|| std::io::ErrorKind::UnexpectedEof.into());
+            }
         }
+        let mut buf = vec![0; length as usize];
+        Self::read_raw(&self.file, offset, &mut buf)?;
+        Ok(Cow::Owned(buf))
     }
 
     #[cfg(unix)]
@@ -265,21 +627,23 @@ impl DiskBook {
 
 impl Book {
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn load(path: impl AsRef<Path>) -> Result<Self, bincode::Error> {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BookError> {
         let mut file = File::open(path)?;
         let mut magic = [0; 4];
         file.read_exact(&mut magic)?;
         file.seek(SeekFrom::Start(0))?;
         match u32::from_le_bytes(magic) {
             // this is just the zstd header since saved memory books are just zstd'd bincode
-            0xFD2FB528 => MemoryBook::load(std::io::BufReader::new(file)).map(Into::into),
-            DiskBook::MAGIC => DiskBook::load(file).map(Into::into),
-            _ => Err(serde::de::Error::custom("Invalid file")),
+            0xFD2FB528 | MemoryBook::MAGIC => {
+                MemoryBook::load(std::io::BufReader::new(file)).map(Into::into)
+            }
+            DiskBook::MAGIC | DiskBook::LEGACY_MAGIC => DiskBook::load(file).map(Into::into),
+            _ => Err(BookError::BadMagic),
         }
     }
 
     #[cfg(target_arch = "wasm32")]
-    pub fn load(from: impl BufRead) -> Result<Self, bincode::Error> {
+    pub fn load(from: impl BufRead) -> Result<Self, BookError> {
         MemoryBook::load(from).map(Into::into)
     }
 
@@ -304,3 +668,345 @@ impl From<DiskBook> for Book {
         Book(BookType::Disk(v))
     }
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use libtetris::{PieceState, RotationState, TspinStatus};
+
+    use super::*;
+
+    fn board(queue: &[Piece]) -> Board {
+        let mut board = Board::new();
+        for &piece in queue {
+            board.add_next_piece(piece);
+        }
+        board
+    }
+
+    fn piece(kind: Piece, x: i32) -> FallingPiece {
+        FallingPiece {
+            kind: PieceState(kind, RotationState::North),
+            x,
+            y: 0,
+            tspin: TspinStatus::None,
+        }
+    }
+
+    /// A book with only the move `mv` in the position of `state`.
+    fn single_move_book(state: &Board, mv: FallingPiece) -> MemoryBook {
+        let (pos, seq) = decompose_board(state, NEXT_PIECES).unwrap();
+        let mut rows = HashMap::new();
+        rows.insert(pos, Row(vec![(seq, Some(mv.into()))].into_boxed_slice()));
+        MemoryBook {
+            rows,
+            next_pieces: NEXT_PIECES,
+        }
+    }
+
+    /// A book with the moves for each state, which may share positions.
+    fn book_of(moves: &[(Board, FallingPiece)]) -> MemoryBook {
+        let mut rows: HashMap<_, Vec<_>> = HashMap::new();
+        for (state, mv) in moves {
+            let (pos, seq) = decompose_board(state, NEXT_PIECES).unwrap();
+            rows.entry(pos).or_default().push((seq, Some((*mv).into())));
+        }
+        let rows = rows
+            .into_iter()
+            .map(|(pos, mut row)| {
+                row.sort_by_key(|&(seq, _)| seq);
+                (pos, Row(row.into_boxed_slice()))
+            })
+            .collect();
+        MemoryBook {
+            rows,
+            next_pieces: NEXT_PIECES,
+        }
+    }
+
+    /// States in one position that differ only in the sequence of pieces, on an empty field or
+    /// with part of the bottom row filled.
+    fn states(filled: bool) -> Vec<Board> {
+        use Piece::*;
+        let queues = [[I, O, T, L, J, S], [I, O, T, L, J, Z], [I, O, T, L, S, J]];
+        let mut field = [[false; 10]; 40];
+        for x in 0..4 {
+            field[0][x] = filled;
+        }
+        queues
+            .iter()
+            .map(|q| {
+                let mut state = board(q);
+                state.set_field(field);
+                state
+            })
+            .collect()
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.ccbook", name, std::process::id()))
+    }
+
+    #[test]
+    fn deeper_book_round_trips() {
+        use Piece::*;
+        // these only differ in the 6th piece after the current and hold pieces
+        let early = board(&[I, O, T, L, J, S, Z, I]);
+        let late = board(&[I, O, T, L, J, S, Z, O]);
+        let (pos, early_seq) = decompose_board(&early, 6).unwrap();
+        let (_, late_seq) = decompose_board(&late, 6).unwrap();
+        let mut rows = HashMap::new();
+        let row = vec![
+            (early_seq, Some(piece(I, 1).into())),
+            (late_seq, Some(piece(I, 5).into())),
+        ];
+        rows.insert(pos, Row(row.into_boxed_slice()));
+        let book = MemoryBook {
+            rows,
+            next_pieces: 6,
+        };
+        assert_eq!(book.suggest_move(&early), Some(piece(I, 1)));
+        assert_eq!(book.suggest_move(&late), Some(piece(I, 5)));
+
+        let mut saved = vec![];
+        book.save(&mut saved).unwrap();
+        let loaded = MemoryBook::load(&saved[..]).unwrap();
+        assert_eq!(loaded.next_pieces(), 6);
+        assert_eq!(loaded.suggest_move(&early), Some(piece(I, 1)));
+        assert_eq!(loaded.suggest_move(&late), Some(piece(I, 5)));
+
+        let path = temp_path("deeper-book");
+        let file = File::create(&path).unwrap();
+        book.save_as_disk_book(file).unwrap();
+        let loaded = Book::load(&path);
+        std::fs::remove_file(&path).ok();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.suggest_move(&early), Some(piece(I, 1)));
+        assert_eq!(loaded.suggest_move(&late), Some(piece(I, 5)));
+    }
+
+    #[test]
+    fn legacy_books_load() {
+        use Piece::*;
+        let state = board(&[I, O, T, L, J, S]);
+        let pos = Position::from(&state);
+        let next = I | O;
+        let mv = Some(CompactPiece::from(piece(T, 4)));
+
+        // memory books used to be the bincode of the rows, with 4 piece sequences
+        let mut rows = HashMap::new();
+        rows.insert(pos, vec![((next, [T, L, J, S]), mv)]);
+        let legacy = zstd::encode_all(&bincode::serialize(&rows).unwrap()[..], 3).unwrap();
+        let loaded = MemoryBook::load(&legacy[..]).unwrap();
+        assert_eq!(loaded.next_pieces(), 4);
+        assert_eq!(loaded.suggest_move(&state), Some(piece(T, 4)));
+
+        // disk books used to have no header after the magic. rows with one sequence are stored
+        // in the index, so nothing else depends on the size of the header.
+        let mut rows = HashMap::new();
+        let row = vec![(Sequence::new(next, &[T, L, J, S]), mv)];
+        rows.insert(pos, Row(row.into_boxed_slice()));
+        let book = MemoryBook {
+            rows,
+            next_pieces: 4,
+        };
+        let mut saved = vec![];
+        book.save_as_disk_book(&mut saved).unwrap();
+        let mut legacy = DiskBook::LEGACY_MAGIC_BYTES.to_vec();
+        legacy.extend_from_slice(&saved[5..]);
+        let path = temp_path("legacy-disk-book");
+        std::fs::write(&path, legacy).unwrap();
+        let loaded = Book::load(&path);
+        std::fs::remove_file(&path).ok();
+        assert_eq!(loaded.unwrap().suggest_move(&state), Some(piece(T, 4)));
+    }
+
+    #[test]
+    fn merge_with_takes_preferred_moves() {
+        use Piece::*;
+        let state = board(&[I, O, T, L, J, S]);
+        let worse = single_move_book(&state, piece(I, 1));
+        let better = single_move_book(&state, piece(I, 5));
+
+        let mut merged = worse.clone();
+        merged.merge(better.clone());
+        assert_eq!(merged.suggest_move(&state), Some(piece(I, 1)));
+
+        let mut merged = worse;
+        merged.merge_with(better, |&pos| pos == Position::from(&state));
+        assert_eq!(merged.suggest_move(&state), Some(piece(I, 5)));
+    }
+
+    #[test]
+    fn too_deep_book_is_rejected() {
+        let mut saved = MemoryBook::MAGIC_BYTES.to_vec();
+        saved.push(MAX_NEXT_PIECES as u8 + 1);
+        match MemoryBook::load(&saved[..]) {
+            Err(BookError::UnsupportedVersion { next_pieces }) => {
+                assert_eq!(next_pieces, MAX_NEXT_PIECES + 1)
+            }
+            _ => panic!("book should have been rejected"),
+        }
+    }
+
+    #[test]
+    fn appended_positions_resolve_with_old_ones() {
+        use Piece::*;
+        let (old_states, new_states) = (states(false), states(true));
+
+        // both books have the empty field, only the new one has the other field
+        let old = book_of(&[
+            (old_states[0].clone(), piece(I, 1)),
+            (old_states[1].clone(), piece(I, 2)),
+            (old_states[2].clone(), piece(I, 3)),
+        ]);
+        let new = book_of(&[
+            (old_states[0].clone(), piece(O, 8)),
+            (new_states[0].clone(), piece(I, 5)),
+            (new_states[1].clone(), piece(I, 6)),
+            (new_states[2].clone(), piece(O, 8)),
+        ]);
+
+        let path = temp_path("appended-book");
+        old.save_as_disk_book(File::create(&path).unwrap()).unwrap();
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .unwrap();
+        let mut book = DiskBook::load(file).unwrap();
+        book.append(&new, |_| false).unwrap();
+        let reloaded = Book::load(&path);
+
+        let check = |suggest: &dyn Fn(&Board) -> Option<FallingPiece>| {
+            assert_eq!(suggest(&old_states[0]), Some(piece(I, 1)));
+            assert_eq!(suggest(&old_states[1]), Some(piece(I, 2)));
+            assert_eq!(suggest(&old_states[2]), Some(piece(I, 3)));
+            assert_eq!(suggest(&new_states[0]), Some(piece(I, 5)));
+            assert_eq!(suggest(&new_states[1]), Some(piece(I, 6)));
+            assert_eq!(suggest(&new_states[2]), Some(piece(O, 8)));
+        };
+        check(&|state| book.suggest_move(state));
+        let reloaded = reloaded.unwrap();
+        check(&|state| reloaded.suggest_move(state));
+
+        // preferring the new moves replaces the whole row of the empty field
+        book.append(&new, |_| true).unwrap();
+        let reloaded = Book::load(&path);
+        std::fs::remove_file(&path).ok();
+        let reloaded = reloaded.unwrap();
+        assert_eq!(book.suggest_move(&old_states[0]), Some(piece(O, 8)));
+        assert_eq!(reloaded.suggest_move(&old_states[0]), Some(piece(O, 8)));
+        assert_eq!(reloaded.suggest_move(&new_states[1]), Some(piece(I, 6)));
+    }
+
+    #[test]
+    fn append_keeps_old_book_in_place() {
+        use Piece::*;
+        let (old_states, new_states) = (states(false), states(true));
+        let old = book_of(&[(old_states[0].clone(), piece(I, 1))]);
+        let new = book_of(&[(new_states[0].clone(), piece(I, 5))]);
+
+        let path = temp_path("append-in-place-book");
+        old.save_as_disk_book(File::create(&path).unwrap()).unwrap();
+        let before = std::fs::read(&path).unwrap();
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .unwrap();
+        let mut book = DiskBook::load(file).unwrap();
+
+        let deeper = MemoryBook {
+            rows: HashMap::new(),
+            next_pieces: NEXT_PIECES + 1,
+        };
+        match book.append(&deeper, |_| true) {
+            Err(BookError::NextPiecesMismatch { expected, found }) => {
+                assert_eq!((expected, found), (NEXT_PIECES, NEXT_PIECES + 1))
+            }
+            _ => panic!("book should have been rejected"),
+        }
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+
+        // the old rows, index and footer are all still there in front of the new ones
+        book.append(&new, |_| false).unwrap();
+        let after = std::fs::read(&path);
+        std::fs::remove_file(&path).ok();
+        let after = after.unwrap();
+        assert!(after.len() > before.len());
+        assert_eq!(after[..before.len()], before[..]);
+        assert_eq!(book.suggest_move(&old_states[0]), Some(piece(I, 1)));
+        assert_eq!(book.suggest_move(&new_states[0]), Some(piece(I, 5)));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mapped_and_read_rows_agree() {
+        use Piece::*;
+        let moves: Vec<_> = states(false)
+            .into_iter()
+            .zip(vec![piece(I, 1), piece(I, 2), piece(O, 8)])
+            .collect();
+        let book = book_of(&moves);
+
+        let path = temp_path("mapped-book");
+        book.save_as_disk_book(File::create(&path).unwrap())
+            .unwrap();
+        let mut loaded = DiskBook::load(File::open(&path).unwrap()).unwrap();
+        assert!(loaded.map.is_some());
+
+        // the row has to be read from the file rather than stored in the index
+        let (pos, _) = decompose_board(&moves[0].0, NEXT_PIECES).unwrap();
+        assert_ne!(loaded.index[&pos].1 & (1 << 24) - 1, 0);
+        for (state, mv) in &moves {
+            assert_eq!(loaded.suggest_move(state), Some(*mv));
+        }
+
+        // the fallback for when the file can't be mapped
+        loaded.map = None;
+        for (state, mv) in &moves {
+            assert_eq!(loaded.suggest_move(state), Some(*mv));
+        }
+        drop(loaded);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn compression_level_doesnt_change_moves() {
+        use Piece::*;
+        let moves: Vec<_> = states(false)
+            .into_iter()
+            .chain(states(true))
+            .zip(vec![
+                piece(I, 1),
+                piece(I, 2),
+                piece(O, 8),
+                piece(T, 4),
+                piece(L, 6),
+                piece(J, 3),
+            ])
+            .collect();
+        let book = book_of(&moves);
+
+        let mut memory = vec![];
+        let mut disk = vec![];
+        for &level in &[3, 19] {
+            let mut saved = vec![];
+            book.save_with_level(&mut saved, level).unwrap();
+            memory.push(Book::from(MemoryBook::load(&saved[..]).unwrap()));
+
+            let path = temp_path(&format!("level-{}-book", level));
+            let file = File::create(&path).unwrap();
+            book.save_as_disk_book_with_level(file, level).unwrap();
+            let loaded = Book::load(&path);
+            std::fs::remove_file(&path).ok();
+            disk.push(loaded.unwrap());
+        }
+
+        for (state, mv) in &moves {
+            for book in memory.iter().chain(&disk) {
+                assert_eq!(book.suggest_move(state), Some(*mv));
+            }
+        }
+    }
+}
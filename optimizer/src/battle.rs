@@ -13,14 +13,22 @@ pub struct BotInput<E: Evaluator> {
     eval: E,
 }
 
+#[cfg(not(test))]
 const THINK_AMOUNT: usize = 10;
+// unoptimized builds think far slower, so tests would take minutes per game otherwise
+#[cfg(test)]
+const THINK_AMOUNT: usize = 1;
 
 impl<E: Evaluator> BotInput<E> {
-    pub fn new(board: Board, eval: E) -> Self {
+    pub fn new(board: Board, eval: E, seed: Option<u64>) -> Self {
+        let options = cold_clear::Options {
+            seed,
+            ..Default::default()
+        };
         let mut this = BotInput {
             controller: Controller::default(),
             executing: None,
-            bot: cold_clear::BotState::new(board, Default::default()),
+            bot: cold_clear::BotState::new(board, options),
             eval,
         };
         for _ in 0..180 {
@@ -58,7 +66,10 @@ impl<E: Evaluator> BotInput<E> {
                 Event::PieceSpawned { new_in_queue } => {
                     self.bot.add_next_piece(*new_in_queue);
                     if self.executing.is_none() {
-                        if let Some((mv, inf)) = self.bot.suggest_move(&self.eval, None, incoming) {
+                        if let Some((mv, inf)) = self
+                            .bot
+                            .suggest_move(&self.eval, None, incoming, &[0; 10], 0)
+                        {
                             info = Some(inf);
                             self.executing = Some((
                                 mv.expected_location,
@@ -89,23 +100,41 @@ impl<E: Evaluator> BotInput<E> {
     }
 }
 
+/// Plays a game between two bots. If `seed` is provided, both players get the same piece sequence,
+/// the garbage is generated from that seed, and the bots search deterministically, so the game
+/// plays out the same way every time.
 pub fn do_battle(
     p1: impl Evaluator + Clone,
     p2: impl Evaluator + Clone,
+    seed: Option<[u8; 16]>,
 ) -> Option<(InfoReplay, bool)> {
-    let mut battle = Battle::new(
-        GameConfig::default(),
-        GameConfig::default(),
-        thread_rng().gen(),
-        thread_rng().gen(),
-        thread_rng().gen(),
-    );
+    let mut battle = match seed {
+        Some(seed) => Battle::new(
+            GameConfig::default(),
+            GameConfig::default(),
+            seed,
+            seed,
+            seed,
+        ),
+        None => Battle::new(
+            GameConfig::default(),
+            GameConfig::default(),
+            thread_rng().gen(),
+            thread_rng().gen(),
+            thread_rng().gen(),
+        ),
+    };
 
     battle.replay.p1_name = format!("Cold Clear\n{}", p1.name());
     battle.replay.p2_name = format!("Cold Clear\n{}", p2.name());
 
-    let mut p1 = BotInput::new(battle.player_1.board.to_compressed(), p1);
-    let mut p2 = BotInput::new(battle.player_2.board.to_compressed(), p2);
+    let bot_seed = seed.map(|seed| {
+        u64::from_le_bytes([
+            seed[0], seed[1], seed[2], seed[3], seed[4], seed[5], seed[6], seed[7],
+        ])
+    });
+    let mut p1 = BotInput::new(battle.player_1.board.to_compressed(), p1, bot_seed);
+    let mut p2 = BotInput::new(battle.player_2.board.to_compressed(), p2, bot_seed);
 
     let mut p1_info_updates = VecDeque::new();
     let mut p2_info_updates = VecDeque::new();
@@ -173,3 +202,39 @@ pub struct InfoReplay {
     pub p1_info_updates: VecDeque<Option<cold_clear::Info>>,
     pub p2_info_updates: VecDeque<Option<cold_clear::Info>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cold_clear::evaluation::Standard;
+
+    /// A bot that stacks as high as it can, so games end quickly.
+    fn reckless() -> Standard {
+        Standard {
+            height: 100,
+            top_half: 0,
+            top_quarter: 0,
+            jeopardy: 0,
+            ..Standard::default()
+        }
+    }
+
+    fn tally(seeds: &[[u8; 16]]) -> Vec<Option<(bool, usize)>> {
+        seeds
+            .iter()
+            .map(|&seed| {
+                do_battle(reckless(), reckless(), Some(seed))
+                    .map(|(replay, p1_won)| (p1_won, replay.replay.updates.len()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fixed_seeds_give_identical_tallies() {
+        let seeds = [[1; 16]];
+        let first = tally(&seeds);
+        // the game has to finish for the tally to mean anything
+        assert!(first.iter().all(Option::is_some));
+        assert_eq!(first, tally(&seeds));
+    }
+}
@@ -15,6 +15,10 @@ use mutate::Mutateable;
 const BATTLES: usize = 6;
 
 fn main() {
+    // With fixed seeds, every matchup in a generation is played on the same set of piece
+    // sequences, so members are compared on equal footing.
+    let fixed_seeds = std::env::args().any(|arg| arg == "--fixed-seeds");
+
     let mut population = match std::fs::File::open("pop.json") {
         Ok(file) => serde_json::from_reader(file).unwrap_or_else(|e| {
             eprintln!("pop.json contained invalid data: {}", e);
@@ -29,7 +33,7 @@ fn main() {
         let matchups = matchups.clone();
         let send = send.clone();
         std::thread::spawn(move || loop {
-            let (p1, p1_e, p2, p2_e) = {
+            let (p1, p1_e, p2, p2_e, seed) = {
                 let (active, ref mut queue) = *matchups.lock().unwrap();
                 if !active {
                     break;
@@ -39,7 +43,7 @@ fn main() {
                     None => continue,
                 }
             };
-            if let Some((replay, p1_won)) = battle::do_battle(p1_e, p2_e) {
+            if let Some((replay, p1_won)) = battle::do_battle(p1_e, p2_e, seed) {
                 send.send(Some((if p1_won { p1 } else { p2 }, replay))).ok();
             } else {
                 send.send(None).ok();
@@ -49,6 +53,15 @@ fn main() {
 
     loop {
         let mut count = 0;
+        let seeds: Vec<_> = (0..BATTLES)
+            .map(|_| {
+                if fixed_seeds {
+                    Some(thread_rng().gen())
+                } else {
+                    None
+                }
+            })
+            .collect();
         {
             let mut matchups = matchups.lock().unwrap();
             for i in 0..population.members.len() {
@@ -56,12 +69,13 @@ fn main() {
                     if i == j {
                         continue;
                     }
-                    for _ in 0..BATTLES {
+                    for &seed in &seeds {
                         matchups.1.push_back((
                             i,
                             population.members[i].clone(),
                             j,
                             population.members[j].clone(),
+                            seed,
                         ));
                         count += 1;
                     }
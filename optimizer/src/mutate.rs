@@ -20,6 +20,7 @@ impl Mutateable for Standard {
             top_half: thread_rng().gen_range(-999, 1000),
             top_quarter: thread_rng().gen_range(-999, 1000),
             jeopardy: thread_rng().gen_range(-999, 1000),
+            incoming_jeopardy: thread_rng().gen_range(-999, 1000),
             cavity_cells: thread_rng().gen_range(-999, 1000),
             cavity_cells_sq: thread_rng().gen_range(-999, 1000),
             overhang_cells: thread_rng().gen_range(-999, 1000),
@@ -46,9 +47,15 @@ impl Mutateable for Standard {
                 thread_rng().gen_range(-999, 1000),
                 thread_rng().gen_range(-999, 1000),
             ],
+            sz_dependency: thread_rng().gen_range(-999, 1000),
+            dig_row_transitions: thread_rng().gen_range(-999, 1000),
+            surface_spikes: thread_rng().gen_range(-999, 1000),
+            stack_balance: thread_rng().gen_range(-999, 1000),
 
             move_time: thread_rng().gen_range(-999, 1000),
             wasted_t: thread_rng().gen_range(-999, 1000),
+            filled_tslot: thread_rng().gen_range(-999, 1000),
+            held_t: thread_rng().gen_range(-999, 1000),
             b2b_clear: thread_rng().gen_range(-999, 1000),
             clear1: thread_rng().gen_range(-999, 1000),
             clear2: thread_rng().gen_range(-999, 1000),
@@ -65,6 +72,11 @@ impl Mutateable for Standard {
             use_bag: true,
             timed_jeopardy: true,
             stack_pc_damage: false,
+            lookahead_defense: false,
+            flood_fill_holes: false,
+            combo_extrapolate: false,
+            combo_table: None,
+            vanish_zone_top: 20,
             sub_name: Some(sub_name),
         }
     }
@@ -79,6 +91,7 @@ impl Mutateable for Standard {
             top_half: crossover_gene(parent1.top_half, parent2.top_half),
             top_quarter: crossover_gene(parent1.top_quarter, parent2.top_quarter),
             jeopardy: crossover_gene(parent1.jeopardy, parent2.jeopardy),
+            incoming_jeopardy: crossover_gene(parent1.incoming_jeopardy, parent2.incoming_jeopardy),
             cavity_cells: crossover_gene(parent1.cavity_cells, parent2.cavity_cells),
             cavity_cells_sq: crossover_gene(parent1.cavity_cells_sq, parent2.cavity_cells_sq),
             overhang_cells: crossover_gene(parent1.overhang_cells, parent2.overhang_cells),
@@ -105,9 +118,18 @@ impl Mutateable for Standard {
                 crossover_gene(parent1.well_column[8], parent2.well_column[8]),
                 crossover_gene(parent1.well_column[9], parent2.well_column[9]),
             ],
+            sz_dependency: crossover_gene(parent1.sz_dependency, parent2.sz_dependency),
+            dig_row_transitions: crossover_gene(
+                parent1.dig_row_transitions,
+                parent2.dig_row_transitions,
+            ),
+            surface_spikes: crossover_gene(parent1.surface_spikes, parent2.surface_spikes),
+            stack_balance: crossover_gene(parent1.stack_balance, parent2.stack_balance),
 
             move_time: crossover_gene(parent1.move_time, parent2.move_time),
             wasted_t: crossover_gene(parent1.wasted_t, parent2.wasted_t),
+            filled_tslot: crossover_gene(parent1.filled_tslot, parent2.filled_tslot),
+            held_t: crossover_gene(parent1.held_t, parent2.held_t),
             b2b_clear: crossover_gene(parent1.b2b_clear, parent2.b2b_clear),
             clear1: crossover_gene(parent1.clear1, parent2.clear1),
             clear2: crossover_gene(parent1.clear2, parent2.clear2),
@@ -124,6 +146,11 @@ impl Mutateable for Standard {
             use_bag: true,
             timed_jeopardy: true,
             stack_pc_damage: false,
+            lookahead_defense: false,
+            flood_fill_holes: false,
+            combo_extrapolate: false,
+            combo_table: None,
+            vanish_zone_top: 20,
             sub_name: Some(sub_name),
         }
     }
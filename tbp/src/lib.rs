@@ -1,60 +1,131 @@
 use std::convert::Infallible;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
 
+use enumset::EnumSet;
 use futures::{Sink, SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
 use tbp::randomizer::RandomizerState;
 use tbp::{BotMessage, FrontendMessage};
 
+/// The most moves to list in a suggestion, best first.
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_SUGGESTIONS: usize = 5;
+
+/// How often statistics are sent to frontends that support the `stats` extension.
+#[cfg(not(target_arch = "wasm32"))]
+const STATS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A TBP message from the frontend.
+///
+/// The `rules` message may also list the extension messages the frontend understands in
+/// `extensions`. Currently, the only extension is `stats`.
+#[derive(Deserialize)]
+pub struct Incoming {
+    #[serde(flatten)]
+    pub message: FrontendMessage,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// With the `b2b-chain` feature, `start` messages may also give the number of back-to-back
+    /// line clears made in a row, for games which scale the back-to-back bonus with it.
+    #[cfg(feature = "b2b-chain")]
+    #[serde(default)]
+    pub b2b_chain: Option<u32>,
+}
+
+/// A message to the frontend, either from TBP or from an extension the frontend supports.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum Outgoing {
+    Tbp(BotMessage),
+    Extension(Extension),
+}
+
+impl From<BotMessage> for Outgoing {
+    fn from(v: BotMessage) -> Self {
+        Outgoing::Tbp(v)
+    }
+}
+
+impl From<Extension> for Outgoing {
+    fn from(v: Extension) -> Self {
+        Outgoing::Extension(v)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Extension {
+    /// Sent regularly while the bot is running. `advantage` is how far ahead the bot expects to
+    /// be after its best move in lines of garbage.
+    Stats {
+        nodes: u32,
+        depth: u32,
+        nodes_per_second: f64,
+        advantage: Option<f32>,
+    },
+}
+
+enum Event {
+    Message(Incoming),
+    Tick,
+    Closed,
+}
+
 pub async fn run(
-    mut incoming: impl Stream<Item = tbp::FrontendMessage> + Unpin,
-    mut outgoing: impl Sink<tbp::BotMessage, Error = Infallible> + Unpin,
+    incoming: impl Stream<Item = Incoming> + Unpin,
+    mut outgoing: impl Sink<Outgoing, Error = Infallible> + Unpin,
 ) {
     let mut bot = None;
+    let mut send_stats = false;
 
     outgoing
-        .send(BotMessage::Info {
-            name: "Cold Clear".to_string(),
-            version: "2020-05-05".to_string(),
-            author: "MinusKelvin".to_string(),
-            features: tbp::Feature::enabled(),
-        })
+        .send(
+            BotMessage::Info {
+                name: "Cold Clear".to_string(),
+                version: "2020-05-05".to_string(),
+                author: "MinusKelvin".to_string(),
+                features: tbp::Feature::enabled(),
+            }
+            .into(),
+        )
         .await
         .unwrap();
 
-    while let Some(msg) = incoming.next().await {
-        match msg {
-            FrontendMessage::Rules { randomizer: _ } => {
-                outgoing.send(BotMessage::Ready).await.unwrap();
-            }
-            FrontendMessage::Start {
-                hold,
-                queue,
-                combo,
-                back_to_back,
-                board,
-                randomizer,
-            } => {
-                let mut b = libtetris::Board::new();
-                b.hold_piece = hold.map(from_tbp_piece);
-                for piece in queue {
-                    b.add_next_piece(from_tbp_piece(piece));
-                }
-                if let RandomizerState::SevenBag { bag_state } = &randomizer {
-                    b.bag = bag_state.iter().copied().map(from_tbp_piece).collect();
-                }
-                b.combo = combo;
-                b.b2b_bonus = back_to_back;
-                let mut field = [[false; 10]; 40];
-                for y in 0..40 {
-                    for x in 0..10 {
-                        field[y][x] = board[y][x].is_some();
+    let mut events = futures::stream::select(
+        incoming
+            .map(Event::Message)
+            .chain(futures::stream::once(futures::future::ready(Event::Closed))),
+        stats_ticks().map(|()| Event::Tick),
+    );
+
+    while let Some(event) = events.next().await {
+        let incoming = match event {
+            Event::Message(msg) => msg,
+            Event::Tick => {
+                if send_stats {
+                    if let Some(ref bot) = bot {
+                        outgoing.send(stats(bot).into()).await.unwrap();
                     }
                 }
-                b.set_field(field);
-
-                let options = cold_clear::Options {
-                    speculate: matches!(randomizer, RandomizerState::SevenBag { .. }),
-                    ..Default::default()
-                };
+                continue;
+            }
+            Event::Closed => return,
+        };
+        let start = start_board(&incoming);
+        let Incoming {
+            message,
+            extensions,
+            ..
+        } = incoming;
+        match message {
+            FrontendMessage::Rules { randomizer: _ } => {
+                send_stats = extensions.iter().any(|e| e == "stats");
+                outgoing.send(BotMessage::Ready.into()).await.unwrap();
+            }
+            FrontendMessage::Start { randomizer, .. } => {
+                let b = start.expect("start messages always describe a board");
+                let options = options_for(&randomizer);
                 let eval = cold_clear::evaluation::Standard::default();
 
                 #[cfg(not(target_arch = "wasm32"))]
@@ -76,10 +147,21 @@ pub async fn run(
                     let mvs = bot.block_next_move();
                     #[cfg(target_arch = "wasm32")]
                     let mvs = bot.block_next_move().await;
-                    let moves =
-                        mvs.map_or(vec![], |(mv, _)| vec![to_tbp_move(mv.expected_location)]);
+                    let mut moves = vec![];
+                    if let Some((mv, _)) = mvs {
+                        moves.push(mv.expected_location);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        for c in bot.suggest_candidates(MAX_SUGGESTIONS).unwrap_or_default() {
+                            if moves.len() < MAX_SUGGESTIONS
+                                && !moves.iter().any(|m| m.same_location(&c.mv))
+                            {
+                                moves.push(c.mv);
+                            }
+                        }
+                    }
+                    let moves = moves.into_iter().map(to_tbp_move).collect();
                     outgoing
-                        .send(BotMessage::Suggestion { moves })
+                        .send(BotMessage::Suggestion { moves }.into())
                         .await
                         .unwrap();
                 }
@@ -99,6 +181,109 @@ pub async fn run(
     }
 }
 
+/// The board a `start` message describes, or `None` for any other message.
+fn start_board(incoming: &Incoming) -> Option<libtetris::Board> {
+    let (hold, queue, combo, back_to_back, board, randomizer) = match &incoming.message {
+        FrontendMessage::Start {
+            hold,
+            queue,
+            combo,
+            back_to_back,
+            board,
+            randomizer,
+        } => (hold, queue, combo, back_to_back, board, randomizer),
+        _ => return None,
+    };
+    let mut b = libtetris::Board::new();
+    b.hold_piece = hold.map(from_tbp_piece);
+    for &piece in queue {
+        b.add_next_piece(from_tbp_piece(piece));
+    }
+    match randomizer {
+        RandomizerState::SevenBag { bag_state } => {
+            b.bag = bag_state.iter().copied().map(from_tbp_piece).collect();
+        }
+        // any piece can come next, so there is no bag to track and speculation is off
+        _ => b.bag = EnumSet::all(),
+    }
+    b.combo = *combo;
+    b.b2b_bonus = *back_to_back;
+    #[cfg(feature = "b2b-chain")]
+    set_b2b_chain(&mut b, incoming.b2b_chain);
+    let mut field = [[false; 10]; 40];
+    for y in 0..40 {
+        for x in 0..10 {
+            field[y][x] = board[y][x].is_some();
+        }
+    }
+    b.set_field(field);
+    Some(b)
+}
+
+/// The options to start a bot with for a game using `randomizer`. Speculation needs to know
+/// which pieces can come next, so it's only used with the 7-bag randomizer.
+fn options_for(randomizer: &RandomizerState) -> cold_clear::Options {
+    cold_clear::Options {
+        speculate: matches!(randomizer, RandomizerState::SevenBag { .. }),
+        ..Default::default()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn stats(bot: &cold_clear::Interface) -> Extension {
+    let stats = bot.stats();
+    Extension::Stats {
+        nodes: stats.tree_size,
+        depth: stats.depth,
+        nodes_per_second: stats.nodes_per_second,
+        advantage: bot.poll_status().and_then(|p| p.advantage),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn stats(_bot: &cold_clear::Interface) -> Extension {
+    Extension::Stats {
+        nodes: 0,
+        depth: 0,
+        nodes_per_second: 0.0,
+        advantage: None,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn stats_ticks() -> impl Stream<Item = ()> + Unpin {
+    let (mut send, recv) = futures::channel::mpsc::channel(0);
+    std::thread::spawn(move || loop {
+        std::thread::sleep(STATS_INTERVAL);
+        // ticks are dropped instead of piling up while a suggestion is being waited on
+        if let Err(e) = send.try_send(()) {
+            if e.is_disconnected() {
+                break;
+            }
+        }
+    });
+    recv
+}
+
+// there are no threads to tick from on the web, so statistics are never sent
+#[cfg(target_arch = "wasm32")]
+fn stats_ticks() -> impl Stream<Item = ()> + Unpin {
+    futures::stream::pending()
+}
+
+/// Applies the `b2b_chain` extension field of a `start` message. Without it, only whether
+/// back-to-back is active is known, so the chain is counted as 1 if it is.
+#[cfg(feature = "b2b-chain")]
+fn set_b2b_chain(board: &mut libtetris::Board, chain: Option<u32>) {
+    match chain {
+        Some(chain) => {
+            board.b2b_chain = chain;
+            board.b2b_bonus = chain > 0;
+        }
+        None => board.b2b_chain = board.b2b_bonus as u32,
+    }
+}
+
 fn from_tbp_piece(v: tbp::Piece) -> libtetris::Piece {
     match v {
         tbp::Piece::I => libtetris::Piece::I,
@@ -198,3 +383,100 @@ mod web {
         spawn_local(crate::run(incoming, outgoing));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn unknown_randomizer_disables_speculation() {
+        let start = json!({
+            "type": "start",
+            "hold": null,
+            // this could never come from a 7-bag
+            "queue": ["S", "S", "S", "S", "S", "S"],
+            "combo": 0,
+            "back_to_back": false,
+            "board": vec![vec![serde_json::Value::Null; 10]; 40],
+            "randomizer": { "type": "unknown" },
+        });
+        let start: Incoming = serde_json::from_value(start).unwrap();
+        match &start.message {
+            FrontendMessage::Start { randomizer, .. } => {
+                assert!(!options_for(randomizer).speculate);
+            }
+            _ => panic!("expected a start message"),
+        }
+
+        let suggest = serde_json::from_value(json!({ "type": "suggest" })).unwrap();
+        let quit = serde_json::from_value(json!({ "type": "quit" })).unwrap();
+        let sent = Arc::new(Mutex::new(vec![]));
+        let recorded = sent.clone();
+        let outgoing = futures::sink::unfold((), move |_, msg: Outgoing| {
+            recorded.lock().unwrap().push(msg);
+            async { Ok(()) }
+        });
+        futures::pin_mut!(outgoing);
+        let incoming = futures::stream::iter(vec![start, suggest, quit]);
+        futures::executor::block_on(run(incoming, outgoing));
+
+        let sent = sent.lock().unwrap();
+        let suggested = sent.iter().any(|msg| match msg {
+            Outgoing::Tbp(BotMessage::Suggestion { moves }) => !moves.is_empty(),
+            _ => false,
+        });
+        assert!(suggested);
+    }
+
+    #[cfg(feature = "b2b-chain")]
+    #[test]
+    fn b2b_chain_from_start_is_stored() {
+        use libtetris::{FallingPiece, Piece, PieceState, RotationState, TspinStatus};
+
+        // the bottom 4 rows are filled except for the rightmost column
+        let mut rows = vec![vec![json!("G"); 9]; 4];
+        for row in &mut rows {
+            row.push(serde_json::Value::Null);
+        }
+        rows.resize(40, vec![serde_json::Value::Null; 10]);
+        let start = json!({
+            "type": "start",
+            "hold": null,
+            "queue": ["I", "O", "T", "L", "J", "S"],
+            "combo": 0,
+            "back_to_back": false,
+            "board": rows,
+            "randomizer": { "type": "unknown" },
+            "b2b_chain": 3,
+        });
+        let start: Incoming = serde_json::from_value(start).unwrap();
+        let mut board = start_board(&start).unwrap();
+        let field = board.get_field();
+        assert!(field[..4]
+            .iter()
+            .all(|row| row[..9].iter().all(|&c| c) && !row[9]));
+        assert!(field[4..].iter().all(|row| row.iter().all(|&c| !c)));
+        assert_eq!(board.b2b_chain, 3);
+        assert!(board.b2b_bonus);
+
+        // the chain carries on from the frontend's count
+        let lock = board.lock_piece(FallingPiece {
+            kind: PieceState(Piece::I, RotationState::East),
+            x: 9,
+            y: 2,
+            tspin: TspinStatus::None,
+        });
+        assert!(lock.b2b);
+        assert_eq!(board.b2b_chain, 4);
+
+        // without the field, an active back-to-back counts as a chain of 1
+        let mut board = libtetris::Board::new();
+        board.b2b_bonus = true;
+        set_b2b_chain(&mut board, None);
+        assert_eq!(board.b2b_chain, 1);
+    }
+}
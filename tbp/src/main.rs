@@ -1,8 +1,28 @@
+use std::io::{BufRead, Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
 fn main() {
-    let incoming = futures::stream::repeat_with(|| {
-        let mut line = String::new();
-        std::io::stdin().read_line(&mut line).unwrap();
-        serde_json::from_str(&line).unwrap()
+    // Messages are newline-delimited JSON by default. With --cbor, each message is CBOR prefixed
+    // by its length in bytes as a big-endian u32, which is much cheaper to parse.
+    if std::env::args().any(|arg| arg == "--cbor") {
+        run_cbor();
+    } else {
+        run_json();
+    }
+}
+
+fn run_json() {
+    // stdin is read on its own thread so that the bot can send statistics while it waits
+    let (send, incoming) = futures::channel::mpsc::unbounded();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let msg = serde_json::from_str(&line.unwrap()).unwrap();
+            if send.unbounded_send(msg).is_err() {
+                break;
+            }
+        }
     });
 
     let outgoing = futures::sink::unfold((), |_, msg| {
@@ -16,3 +36,72 @@ fn main() {
 
     futures::executor::block_on(cc_tbp::run(incoming, outgoing));
 }
+
+fn run_cbor() {
+    let (send, incoming) = futures::channel::mpsc::unbounded();
+    std::thread::spawn(move || {
+        let mut stdin = std::io::stdin();
+        while let Some(msg) = read_cbor(&mut stdin) {
+            if send.unbounded_send(msg).is_err() {
+                break;
+            }
+        }
+    });
+
+    let outgoing = futures::sink::unfold((), |_, msg| {
+        write_cbor(&mut std::io::stdout(), &msg);
+        async { Ok(()) }
+    });
+
+    futures::pin_mut!(incoming);
+    futures::pin_mut!(outgoing);
+
+    futures::executor::block_on(cc_tbp::run(incoming, outgoing));
+}
+
+/// Reads a length-prefixed CBOR message, returning `None` once the stream ends.
+fn read_cbor<T: DeserializeOwned>(from: &mut impl Read) -> Option<T> {
+    let mut len = [0; 4];
+    from.read_exact(&mut len).ok()?;
+    let mut buf = vec![0; u32::from_be_bytes(len) as usize];
+    from.read_exact(&mut buf).unwrap();
+    Some(serde_cbor::from_slice(&buf).unwrap())
+}
+
+fn write_cbor(to: &mut impl Write, msg: &impl Serialize) {
+    let buf = serde_cbor::to_vec(msg).unwrap();
+    to.write_all(&(buf.len() as u32).to_be_bytes()).unwrap();
+    to.write_all(&buf).unwrap();
+    to.flush().unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use tbp::FrontendMessage;
+
+    use super::*;
+
+    #[test]
+    fn frontend_message_round_trips_through_cbor() {
+        let mut stream = vec![];
+        write_cbor(
+            &mut stream,
+            &FrontendMessage::NewPiece {
+                piece: tbp::Piece::T,
+            },
+        );
+        write_cbor(&mut stream, &FrontendMessage::Suggest);
+
+        let mut from = &stream[..];
+        let first: cc_tbp::Incoming = read_cbor(&mut from).unwrap();
+        assert!(matches!(
+            first.message,
+            FrontendMessage::NewPiece {
+                piece: tbp::Piece::T
+            }
+        ));
+        let second: cc_tbp::Incoming = read_cbor(&mut from).unwrap();
+        assert!(matches!(second.message, FrontendMessage::Suggest));
+        assert!(read_cbor::<cc_tbp::Incoming>(&mut from).is_none());
+    }
+}